@@ -0,0 +1,305 @@
+//Checked, saturating and wrapping arithmetic
+/*
+Chapter 3 mentions what happens when an integer overflows: a debug build
+panics and a release build quietly wraps around, so 255u8 + 1 is 0. The
+standard library has methods to pick what should happen instead:
+
+    checked_add     None when the result doesn't fit
+    saturating_add  stops at the largest or smallest value
+    wrapping_add    wraps around on purpose, the same as a release build
+
+These are methods on each integer type, with nothing in common between
+them, so a function can't be written once for all integers. The Integer
+trait below gives them one name, and a macro implements it for every
+primitive integer type the way chapter 19 suggests for repetitive impls.
+
+The checked functions return a Result instead of an Option, so the caller
+learns what went wrong: ArithmeticError says whether it was an overflow (and
+in which operation) or a division by zero. Division can overflow too: the
+smallest i8 is -128, and -128 / -1 would be 128, which doesn't fit.
+
+Dividing by zero has no answer to saturate or wrap to, so saturating_div
+and wrapping_div return the same error for it instead of panicking.
+*/
+
+use std::error::Error;
+use std::fmt;
+
+/// An arithmetic operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Operation::Add => "addition",
+            Operation::Sub => "subtraction",
+            Operation::Mul => "multiplication",
+            Operation::Div => "division",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Why an operation has no result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArithmeticError {
+    /// The result doesn't fit in the type.
+    Overflow(Operation),
+    DivisionByZero,
+}
+
+impl fmt::Display for ArithmeticError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArithmeticError::Overflow(operation) => write!(f, "{operation} overflowed"),
+            ArithmeticError::DivisionByZero => write!(f, "division by zero"),
+        }
+    }
+}
+
+impl Error for ArithmeticError {}
+
+/// The operations every primitive integer type has, under one name.
+pub trait Integer: Copy + PartialEq + PartialOrd + fmt::Debug + fmt::Display {
+    const MIN: Self;
+    const MAX: Self;
+    const ZERO: Self;
+
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    fn checked_div(self, rhs: Self) -> Option<Self>;
+    fn saturating_add(self, rhs: Self) -> Self;
+    fn saturating_sub(self, rhs: Self) -> Self;
+    fn saturating_mul(self, rhs: Self) -> Self;
+    fn wrapping_add(self, rhs: Self) -> Self;
+    fn wrapping_sub(self, rhs: Self) -> Self;
+    fn wrapping_mul(self, rhs: Self) -> Self;
+    /// Panics if `rhs` is zero.
+    fn wrapping_div(self, rhs: Self) -> Self;
+}
+
+macro_rules! impl_integer {
+    ($($t:ty)*) => {
+        $(
+            impl Integer for $t {
+                const MIN: $t = <$t>::MIN;
+                const MAX: $t = <$t>::MAX;
+                const ZERO: $t = 0;
+
+                fn checked_add(self, rhs: $t) -> Option<$t> {
+                    <$t>::checked_add(self, rhs)
+                }
+                fn checked_sub(self, rhs: $t) -> Option<$t> {
+                    <$t>::checked_sub(self, rhs)
+                }
+                fn checked_mul(self, rhs: $t) -> Option<$t> {
+                    <$t>::checked_mul(self, rhs)
+                }
+                fn checked_div(self, rhs: $t) -> Option<$t> {
+                    <$t>::checked_div(self, rhs)
+                }
+                fn saturating_add(self, rhs: $t) -> $t {
+                    <$t>::saturating_add(self, rhs)
+                }
+                fn saturating_sub(self, rhs: $t) -> $t {
+                    <$t>::saturating_sub(self, rhs)
+                }
+                fn saturating_mul(self, rhs: $t) -> $t {
+                    <$t>::saturating_mul(self, rhs)
+                }
+                fn wrapping_add(self, rhs: $t) -> $t {
+                    <$t>::wrapping_add(self, rhs)
+                }
+                fn wrapping_sub(self, rhs: $t) -> $t {
+                    <$t>::wrapping_sub(self, rhs)
+                }
+                fn wrapping_mul(self, rhs: $t) -> $t {
+                    <$t>::wrapping_mul(self, rhs)
+                }
+                fn wrapping_div(self, rhs: $t) -> $t {
+                    <$t>::wrapping_div(self, rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize);
+
+/// `a + b`, or an error if it overflows.
+pub fn checked_add<T: Integer>(a: T, b: T) -> Result<T, ArithmeticError> {
+    a.checked_add(b)
+        .ok_or(ArithmeticError::Overflow(Operation::Add))
+}
+
+/// `a - b`, or an error if it overflows.
+pub fn checked_sub<T: Integer>(a: T, b: T) -> Result<T, ArithmeticError> {
+    a.checked_sub(b)
+        .ok_or(ArithmeticError::Overflow(Operation::Sub))
+}
+
+/// `a * b`, or an error if it overflows.
+pub fn checked_mul<T: Integer>(a: T, b: T) -> Result<T, ArithmeticError> {
+    a.checked_mul(b)
+        .ok_or(ArithmeticError::Overflow(Operation::Mul))
+}
+
+/// `a / b` rounded toward zero, or an error if `b` is zero or it overflows.
+pub fn checked_div<T: Integer>(a: T, b: T) -> Result<T, ArithmeticError> {
+    if b == T::ZERO {
+        return Err(ArithmeticError::DivisionByZero);
+    }
+    a.checked_div(b)
+        .ok_or(ArithmeticError::Overflow(Operation::Div))
+}
+
+/// `a + b`, stopping at `T::MIN` or `T::MAX`.
+pub fn saturating_add<T: Integer>(a: T, b: T) -> T {
+    a.saturating_add(b)
+}
+
+/// `a - b`, stopping at `T::MIN` or `T::MAX`.
+pub fn saturating_sub<T: Integer>(a: T, b: T) -> T {
+    a.saturating_sub(b)
+}
+
+/// `a * b`, stopping at `T::MIN` or `T::MAX`.
+pub fn saturating_mul<T: Integer>(a: T, b: T) -> T {
+    a.saturating_mul(b)
+}
+
+/// `a / b`, giving `T::MAX` where that overflows, or an error if `b` is zero.
+pub fn saturating_div<T: Integer>(a: T, b: T) -> Result<T, ArithmeticError> {
+    match checked_div(a, b) {
+        // the only overflow is MIN / -1, which would be MAX + 1
+        Err(ArithmeticError::Overflow(_)) => Ok(T::MAX),
+        result => result,
+    }
+}
+
+/// `a + b`, wrapping around at the ends of `T`.
+pub fn wrapping_add<T: Integer>(a: T, b: T) -> T {
+    a.wrapping_add(b)
+}
+
+/// `a - b`, wrapping around at the ends of `T`.
+pub fn wrapping_sub<T: Integer>(a: T, b: T) -> T {
+    a.wrapping_sub(b)
+}
+
+/// `a * b`, wrapping around at the ends of `T`.
+pub fn wrapping_mul<T: Integer>(a: T, b: T) -> T {
+    a.wrapping_mul(b)
+}
+
+/// `a / b`, wrapping around where that overflows, or an error if `b` is zero.
+pub fn wrapping_div<T: Integer>(a: T, b: T) -> Result<T, ArithmeticError> {
+    if b == T::ZERO {
+        return Err(ArithmeticError::DivisionByZero);
+    }
+    Ok(a.wrapping_div(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ArithmeticError::{DivisionByZero, Overflow};
+
+    #[test]
+    fn checked_results() {
+        assert_eq!(checked_add(2u8, 3), Ok(5));
+        assert_eq!(checked_add(250u8, 6), Err(Overflow(Operation::Add)));
+        assert_eq!(checked_add(i32::MIN, -1), Err(Overflow(Operation::Add)));
+        assert_eq!(checked_sub(0u32, 1), Err(Overflow(Operation::Sub)));
+        assert_eq!(checked_sub(-5i64, 10), Ok(-15));
+        assert_eq!(checked_mul(16u8, 16), Err(Overflow(Operation::Mul)));
+        assert_eq!(checked_mul(-8i8, 16), Ok(-128));
+        assert_eq!(checked_div(7i16, -2), Ok(-3));
+        assert_eq!(checked_div(i8::MIN, -1), Err(Overflow(Operation::Div)));
+        assert_eq!(checked_div(1u128, 0), Err(DivisionByZero));
+    }
+
+    #[test]
+    fn saturating_results() {
+        assert_eq!(saturating_add(250u8, 6), u8::MAX);
+        assert_eq!(saturating_add(i16::MIN, -1), i16::MIN);
+        assert_eq!(saturating_sub(3usize, 5), 0);
+        assert_eq!(saturating_mul(-100i8, 2), i8::MIN);
+        assert_eq!(saturating_mul(100i8, -2), i8::MIN);
+        assert_eq!(saturating_mul(-100i8, -2), i8::MAX);
+        assert_eq!(saturating_div(i32::MIN, -1), Ok(i32::MAX));
+        assert_eq!(saturating_div(9u8, 2), Ok(4));
+        assert_eq!(saturating_div(9u8, 0), Err(DivisionByZero));
+    }
+
+    #[test]
+    fn wrapping_results() {
+        assert_eq!(wrapping_add(255u8, 1), 0);
+        assert_eq!(wrapping_add(i8::MAX, 1), i8::MIN);
+        assert_eq!(wrapping_sub(0u16, 1), u16::MAX);
+        assert_eq!(wrapping_mul(16u8, 17), 16);
+        assert_eq!(wrapping_div(i64::MIN, -1), Ok(i64::MIN));
+        assert_eq!(wrapping_div(-9isize, 2), Ok(-4));
+        assert_eq!(wrapping_div(0u64, 0), Err(DivisionByZero));
+    }
+
+    // every u8 and i8 pair, against arithmetic done in a wider type
+    #[test]
+    fn agrees_with_wider_arithmetic() {
+        for a in u8::MIN..=u8::MAX {
+            for b in u8::MIN..=u8::MAX {
+                let (wa, wb) = (i32::from(a), i32::from(b));
+                let fits = |n: i32| u8::try_from(n).ok();
+                assert_eq!(checked_add(a, b).ok(), fits(wa + wb));
+                assert_eq!(checked_sub(a, b).ok(), fits(wa - wb));
+                assert_eq!(checked_mul(a, b).ok(), fits(wa * wb));
+                assert_eq!(wrapping_mul(a, b), (wa * wb) as u8);
+                assert_eq!(saturating_sub(a, b), (wa - wb).max(0) as u8);
+            }
+        }
+        for a in i8::MIN..=i8::MAX {
+            for b in i8::MIN..=i8::MAX {
+                let (wa, wb) = (i32::from(a), i32::from(b));
+                let clamp = |n: i32| n.clamp(-128, 127) as i8;
+                assert_eq!(saturating_add(a, b), clamp(wa + wb));
+                assert_eq!(saturating_mul(a, b), clamp(wa * wb));
+                assert_eq!(wrapping_sub(a, b), (wa - wb) as i8);
+                if b != 0 {
+                    assert_eq!(saturating_div(a, b), Ok(clamp(wa / wb)));
+                    assert_eq!(wrapping_div(a, b), Ok((wa / wb) as i8));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn errors_describe_themselves() {
+        assert_eq!(
+            Overflow(Operation::Mul).to_string(),
+            "multiplication overflowed"
+        );
+        assert_eq!(DivisionByZero.to_string(), "division by zero");
+    }
+
+    // the functions work for any Integer, including in generic code
+    fn sum<T: Integer>(values: &[T]) -> Result<T, ArithmeticError> {
+        values
+            .iter()
+            .try_fold(T::ZERO, |total, &value| checked_add(total, value))
+    }
+
+    #[test]
+    fn generic_code() {
+        assert_eq!(sum(&[1u8, 2, 3]), Ok(6));
+        assert_eq!(sum(&[100u8, 100, 100]), Err(Overflow(Operation::Add)));
+        assert_eq!(sum::<i128>(&[]), Ok(0));
+    }
+}
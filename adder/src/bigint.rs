@@ -0,0 +1,1139 @@
+//Integers of any size: BigUint and BigInt
+/*
+add_two overflows once the answer no longer fits in an i32, and even a u128
+runs out at 39 digits. BigUint has no limit: it keeps a number as a Vec of
+u32 "limbs", least significant first, the way we write a number as a list
+of digits in base 10, except that the base is 2^32. BigInt adds a sign.
+
+    let big: BigUint = "340282366920938463463374607431768211456".parse()?;
+    let squared = &big * &big;
+    println!("{squared}");
+
+Adding is the method from school: add limb by limb, carrying into the next
+one. Each limb is added in a u64, so the carry is simply the high half.
+Subtracting borrows the same way.
+
+Multiplying the school way takes every limb of one number times every limb
+of the other, so doubling the length makes it four times slower. Karatsuba's
+trick splits each number into a high and a low half, x = x1*B + x0, and
+gets away with three half-size products instead of four:
+
+    x*y = z2*B^2 + z1*B + z0
+    z2 = x1*y1,  z0 = x0*y0,  z1 = (x1 + x0)*(y1 + y0) - z2 - z0
+
+Recursing on that makes doubling the length only three times slower. For
+short numbers the extra adding costs more than it saves, so below
+KARATSUBA_THRESHOLD limbs it's the school method again. When one number is
+more than twice as long as the other, the halves wouldn't match up, so the
+long one is multiplied a piece at a time, each piece as long as the short
+one.
+
+Long division is the school method as well, guessing each limb of the
+quotient from the top two limbs of what's left. That's Algorithm D from
+Knuth's "The Art of Computer Programming", volume 2: after shifting both
+numbers so the divisor's top limb has its high bit set, a guess is never
+more than two too big, and the rare correction is adding the divisor back.
+
+Numbers can be parsed and printed in any radix from 2 to 36, using the
+digits 0-9 and then the letters a-z like 'u32::from_str_radix'. Display and
+FromStr use base 10.
+
+The operators work the way they do for the primitive types: +, -, *, / and %
+on values or references, subtracting a bigger BigUint or dividing by zero
+panics, and division rounds toward zero, so -7 / 2 is -3 and -7 % 2 is -1.
+checked_sub and checked_div_rem return an ArithmeticError instead.
+*/
+
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign,
+};
+use std::str::FromStr;
+
+use crate::arithmetic::{ArithmeticError, Operation};
+use crate::property::{Arbitrary, Rng};
+
+/// Numbers at least this many limbs long multiply with Karatsuba's method.
+pub const KARATSUBA_THRESHOLD: usize = 32;
+
+/// A non-negative integer of any size.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigUint {
+    // least significant first, with no zero limbs at the end, so zero is
+    // an empty Vec
+    limbs: Vec<u32>,
+}
+
+/// An integer of any size, positive or negative.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    // never true for zero
+    negative: bool,
+    magnitude: BigUint,
+}
+
+/// Why a string isn't a number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseBigIntError {
+    Empty,
+    InvalidDigit,
+    /// The radix isn't between 2 and 36.
+    InvalidRadix,
+}
+
+impl fmt::Display for ParseBigIntError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseBigIntError::Empty => write!(f, "cannot parse integer from empty string"),
+            ParseBigIntError::InvalidDigit => write!(f, "invalid digit found in string"),
+            ParseBigIntError::InvalidRadix => write!(f, "radix must be between 2 and 36"),
+        }
+    }
+}
+
+impl Error for ParseBigIntError {}
+
+impl BigUint {
+    pub fn zero() -> BigUint {
+        BigUint { limbs: Vec::new() }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    fn from_limbs(mut limbs: Vec<u32>) -> BigUint {
+        trim(&mut limbs);
+        BigUint { limbs }
+    }
+
+    /// The value as a `u128`, or `None` if it's too big.
+    pub fn to_u128(&self) -> Option<u128> {
+        if self.limbs.len() > 4 {
+            return None;
+        }
+        Some(
+            self.limbs
+                .iter()
+                .rev()
+                .fold(0, |n, &limb| n << 32 | u128::from(limb)),
+        )
+    }
+
+    /// The number of bits needed to write the value, 0 for zero.
+    pub fn bits(&self) -> u64 {
+        match self.limbs.last() {
+            Some(top) => self.limbs.len() as u64 * 32 - u64::from(top.leading_zeros()),
+            None => 0,
+        }
+    }
+
+    /// `self - other`, or an error if `other` is bigger.
+    pub fn checked_sub(&self, other: &BigUint) -> Result<BigUint, ArithmeticError> {
+        if cmp_limbs(&self.limbs, &other.limbs) == Ordering::Less {
+            return Err(ArithmeticError::Overflow(Operation::Sub));
+        }
+        Ok(BigUint::from_limbs(sub_limbs(&self.limbs, &other.limbs)))
+    }
+
+    /// The quotient and remainder of `self / other`, or an error if `other`
+    /// is zero.
+    pub fn checked_div_rem(&self, other: &BigUint) -> Result<(BigUint, BigUint), ArithmeticError> {
+        if other.is_zero() {
+            return Err(ArithmeticError::DivisionByZero);
+        }
+        let (quotient, remainder) = div_rem_limbs(&self.limbs, &other.limbs);
+        Ok((
+            BigUint::from_limbs(quotient),
+            BigUint::from_limbs(remainder),
+        ))
+    }
+
+    /// The quotient and remainder of `self / other`. Panics if `other` is zero.
+    pub fn div_rem(&self, other: &BigUint) -> (BigUint, BigUint) {
+        self.checked_div_rem(other)
+            .expect("attempt to divide by zero")
+    }
+
+    /// `self` to the power `exp`, by repeated squaring.
+    pub fn pow(&self, mut exp: u32) -> BigUint {
+        let mut result = BigUint::from(1u8);
+        let mut base = self.clone();
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = &result * &base;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = &base * &base;
+            }
+        }
+        result
+    }
+
+    /// Parses digits in `radix`, which must be between 2 and 36.
+    pub fn from_str_radix(text: &str, radix: u32) -> Result<BigUint, ParseBigIntError> {
+        if !(2..=36).contains(&radix) {
+            return Err(ParseBigIntError::InvalidRadix);
+        }
+        if text.is_empty() {
+            return Err(ParseBigIntError::Empty);
+        }
+        let digits = text
+            .chars()
+            .map(|c| c.to_digit(radix).ok_or(ParseBigIntError::InvalidDigit))
+            .collect::<Result<Vec<u32>, _>>()?;
+
+        // as many digits at a time as fit in a limb
+        let (chunk_len, _) = chunk_size(radix);
+        let mut limbs = Vec::new();
+        for chunk in digits.chunks(chunk_len) {
+            let scale = radix.pow(chunk.len() as u32);
+            let value = chunk.iter().fold(0, |n, &digit| n * radix + digit);
+            mul_add_small(&mut limbs, scale, value);
+        }
+        Ok(BigUint::from_limbs(limbs))
+    }
+
+    /// The digits of the value in `radix`, which must be between 2 and 36,
+    /// using lowercase letters above 9.
+    pub fn to_str_radix(&self, radix: u32) -> String {
+        assert!((2..=36).contains(&radix), "radix must be between 2 and 36");
+        if self.is_zero() {
+            return String::from("0");
+        }
+
+        // divide off as many digits at a time as fit in a limb, lowest first
+        let (chunk_len, chunk_scale) = chunk_size(radix);
+        let mut digits = Vec::new();
+        let mut rest = self.limbs.clone();
+        while !rest.is_empty() {
+            let (quotient, mut chunk) = div_rem_small(&rest, chunk_scale);
+            rest = quotient;
+            for _ in 0..chunk_len {
+                if rest.is_empty() && chunk == 0 {
+                    break;
+                }
+                digits.push(std::char::from_digit(chunk % radix, radix).unwrap_or('?'));
+                chunk /= radix;
+            }
+        }
+        digits.iter().rev().collect()
+    }
+}
+
+impl BigInt {
+    pub fn zero() -> BigInt {
+        BigInt::default()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_zero()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// The absolute value.
+    pub fn magnitude(&self) -> &BigUint {
+        &self.magnitude
+    }
+
+    fn from_parts(negative: bool, magnitude: BigUint) -> BigInt {
+        BigInt {
+            negative: negative && !magnitude.is_zero(),
+            magnitude,
+        }
+    }
+
+    /// The value as an `i128`, or `None` if it doesn't fit.
+    pub fn to_i128(&self) -> Option<i128> {
+        let magnitude = self.magnitude.to_u128()?;
+        if self.negative {
+            0i128.checked_sub_unsigned(magnitude)
+        } else {
+            i128::try_from(magnitude).ok()
+        }
+    }
+
+    /// The quotient and remainder of `self / other`, rounding toward zero,
+    /// or an error if `other` is zero.
+    pub fn checked_div_rem(&self, other: &BigInt) -> Result<(BigInt, BigInt), ArithmeticError> {
+        let (quotient, remainder) = self.magnitude.checked_div_rem(&other.magnitude)?;
+        Ok((
+            BigInt::from_parts(self.negative != other.negative, quotient),
+            // the remainder has the sign of the dividend, as for i32
+            BigInt::from_parts(self.negative, remainder),
+        ))
+    }
+
+    /// The quotient and remainder of `self / other`, rounding toward zero.
+    /// Panics if `other` is zero.
+    pub fn div_rem(&self, other: &BigInt) -> (BigInt, BigInt) {
+        self.checked_div_rem(other)
+            .expect("attempt to divide by zero")
+    }
+
+    /// `self` to the power `exp`.
+    pub fn pow(&self, exp: u32) -> BigInt {
+        BigInt::from_parts(self.negative && exp % 2 == 1, self.magnitude.pow(exp))
+    }
+
+    /// Parses digits in `radix`, which must be between 2 and 36, after an
+    /// optional '+' or '-'.
+    pub fn from_str_radix(text: &str, radix: u32) -> Result<BigInt, ParseBigIntError> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        // "-" alone isn't a number either
+        if digits.is_empty() && !text.is_empty() {
+            return Err(ParseBigIntError::InvalidDigit);
+        }
+        let magnitude = BigUint::from_str_radix(digits, radix)?;
+        Ok(BigInt::from_parts(negative, magnitude))
+    }
+
+    /// The value in `radix`, with a '-' in front if it's negative.
+    pub fn to_str_radix(&self, radix: u32) -> String {
+        let digits = self.magnitude.to_str_radix(radix);
+        if self.negative {
+            format!("-{digits}")
+        } else {
+            digits
+        }
+    }
+
+    // `self + other` or, with `subtract`, `self - other`
+    fn add_signed(&self, other: &BigInt, subtract: bool) -> BigInt {
+        let other_negative = other.negative != subtract;
+        if self.negative == other_negative {
+            return BigInt::from_parts(self.negative, &self.magnitude + &other.magnitude);
+        }
+        // the signs differ, so the bigger magnitude wins
+        match cmp_limbs(&self.magnitude.limbs, &other.magnitude.limbs) {
+            Ordering::Less => {
+                BigInt::from_parts(other_negative, &other.magnitude - &self.magnitude)
+            }
+            _ => BigInt::from_parts(self.negative, &self.magnitude - &other.magnitude),
+        }
+    }
+}
+
+macro_rules! from_unsigned {
+    ($($t:ty)*) => {
+        $(
+            impl From<$t> for BigUint {
+                fn from(n: $t) -> BigUint {
+                    let mut n = n as u128;
+                    let mut limbs = Vec::new();
+                    while n > 0 {
+                        limbs.push(n as u32);
+                        n >>= 32;
+                    }
+                    BigUint { limbs }
+                }
+            }
+
+            impl From<$t> for BigInt {
+                fn from(n: $t) -> BigInt {
+                    BigInt::from(BigUint::from(n))
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! from_signed {
+    ($($t:ty)*) => {
+        $(
+            impl From<$t> for BigInt {
+                fn from(n: $t) -> BigInt {
+                    BigInt::from_parts(n < 0, BigUint::from((n as i128).unsigned_abs()))
+                }
+            }
+        )*
+    };
+}
+
+from_unsigned!(u8 u16 u32 u64 u128 usize);
+from_signed!(i8 i16 i32 i64 i128 isize);
+
+impl From<BigUint> for BigInt {
+    fn from(magnitude: BigUint) -> BigInt {
+        BigInt::from_parts(false, magnitude)
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &BigUint) -> Ordering {
+        cmp_limbs(&self.limbs, &other.limbs)
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &BigUint) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => self.magnitude.cmp(&other.magnitude),
+            (true, true) => other.magnitude.cmp(&self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad_integral(true, "", &self.to_str_radix(10))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad_integral(!self.negative, "", &self.magnitude.to_str_radix(10))
+    }
+}
+
+impl FromStr for BigUint {
+    type Err = ParseBigIntError;
+
+    fn from_str(text: &str) -> Result<BigUint, ParseBigIntError> {
+        BigUint::from_str_radix(text.strip_prefix('+').unwrap_or(text), 10)
+    }
+}
+
+impl FromStr for BigInt {
+    type Err = ParseBigIntError;
+
+    fn from_str(text: &str) -> Result<BigInt, ParseBigIntError> {
+        BigInt::from_str_radix(text, 10)
+    }
+}
+
+// For property tests: up to `size` / 8 limbs, often all zeros or all ones,
+// where carries and borrows happen. Shrinking drops and shrinks limbs.
+impl Arbitrary for BigUint {
+    fn arbitrary(rng: &mut Rng, size: usize) -> BigUint {
+        let len = rng.below(size as u64 / 8 + 2);
+        let limbs = (0..len)
+            .map(|_| match rng.below(4) {
+                0 => [0, u32::MAX][rng.below(2) as usize],
+                _ => rng.next_u64() as u32,
+            })
+            .collect();
+        BigUint::from_limbs(limbs)
+    }
+
+    fn shrink(&self) -> Vec<BigUint> {
+        self.limbs
+            .shrink()
+            .into_iter()
+            .map(BigUint::from_limbs)
+            .collect()
+    }
+}
+
+impl Arbitrary for BigInt {
+    fn arbitrary(rng: &mut Rng, size: usize) -> BigInt {
+        BigInt::from_parts(rng.one_in(2), BigUint::arbitrary(rng, size))
+    }
+
+    fn shrink(&self) -> Vec<BigInt> {
+        let mut shrunk = Vec::new();
+        if self.negative {
+            shrunk.push(-self);
+        }
+        for magnitude in self.magnitude.shrink() {
+            shrunk.push(BigInt::from_parts(self.negative, magnitude));
+        }
+        shrunk
+    }
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.magnitude)
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        -self.clone()
+    }
+}
+
+// Each operator is written once for two references; these fill in the
+// versions that take values, and the assigning operators.
+macro_rules! forward_ops {
+    ($t:ty, $($trait:ident $method:ident $assign_trait:ident $assign_method:ident),*) => {
+        $(
+            impl $trait<$t> for $t {
+                type Output = $t;
+
+                fn $method(self, other: $t) -> $t {
+                    (&self).$method(&other)
+                }
+            }
+
+            impl $trait<&$t> for $t {
+                type Output = $t;
+
+                fn $method(self, other: &$t) -> $t {
+                    (&self).$method(other)
+                }
+            }
+
+            impl $trait<$t> for &$t {
+                type Output = $t;
+
+                fn $method(self, other: $t) -> $t {
+                    self.$method(&other)
+                }
+            }
+
+            impl $assign_trait<$t> for $t {
+                fn $assign_method(&mut self, other: $t) {
+                    *self = (&*self).$method(&other);
+                }
+            }
+
+            impl $assign_trait<&$t> for $t {
+                fn $assign_method(&mut self, other: &$t) {
+                    *self = (&*self).$method(other);
+                }
+            }
+        )*
+    };
+}
+
+forward_ops!(
+    BigUint,
+    Add add AddAssign add_assign,
+    Sub sub SubAssign sub_assign,
+    Mul mul MulAssign mul_assign,
+    Div div DivAssign div_assign,
+    Rem rem RemAssign rem_assign
+);
+
+forward_ops!(
+    BigInt,
+    Add add AddAssign add_assign,
+    Sub sub SubAssign sub_assign,
+    Mul mul MulAssign mul_assign,
+    Div div DivAssign div_assign,
+    Rem rem RemAssign rem_assign
+);
+
+impl Add for &BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: &BigUint) -> BigUint {
+        BigUint::from_limbs(add_limbs(&self.limbs, &other.limbs))
+    }
+}
+
+impl Sub for &BigUint {
+    type Output = BigUint;
+
+    fn sub(self, other: &BigUint) -> BigUint {
+        self.checked_sub(other)
+            .expect("attempt to subtract with overflow")
+    }
+}
+
+impl Mul for &BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: &BigUint) -> BigUint {
+        BigUint::from_limbs(mul_limbs(&self.limbs, &other.limbs))
+    }
+}
+
+impl Div for &BigUint {
+    type Output = BigUint;
+
+    fn div(self, other: &BigUint) -> BigUint {
+        self.div_rem(other).0
+    }
+}
+
+impl Rem for &BigUint {
+    type Output = BigUint;
+
+    fn rem(self, other: &BigUint) -> BigUint {
+        self.div_rem(other).1
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        self.add_signed(other, false)
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self.add_signed(other, true)
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::from_parts(
+            self.negative != other.negative,
+            &self.magnitude * &other.magnitude,
+        )
+    }
+}
+
+impl Div for &BigInt {
+    type Output = BigInt;
+
+    fn div(self, other: &BigInt) -> BigInt {
+        self.div_rem(other).0
+    }
+}
+
+impl Rem for &BigInt {
+    type Output = BigInt;
+
+    fn rem(self, other: &BigInt) -> BigInt {
+        self.div_rem(other).1
+    }
+}
+
+// The functions below work on limbs, least significant first. They accept
+// zero limbs at the end, but the Vecs they return may have them too.
+
+fn trim(limbs: &mut Vec<u32>) {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+}
+
+fn cmp_limbs(a: &[u32], b: &[u32]) -> Ordering {
+    let a = &a[..a.iter().rposition(|&l| l != 0).map_or(0, |i| i + 1)];
+    let b = &b[..b.iter().rposition(|&l| l != 0).map_or(0, |i| i + 1)];
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_limbs(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut sum = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+    for (i, &limb) in long.iter().enumerate() {
+        let total = u64::from(limb) + u64::from(short.get(i).copied().unwrap_or(0)) + carry;
+        sum.push(total as u32);
+        carry = total >> 32;
+    }
+    if carry > 0 {
+        sum.push(carry as u32);
+    }
+    sum
+}
+
+// `a - b` where `a` is at least `b`
+fn sub_limbs(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = false;
+    for (i, &limb) in a.iter().enumerate() {
+        let (d, b1) = limb.overflowing_sub(b.get(i).copied().unwrap_or(0));
+        let (d, b2) = d.overflowing_sub(u32::from(borrow));
+        difference.push(d);
+        borrow = b1 || b2;
+    }
+    debug_assert!(!borrow, "subtracted a bigger number");
+    difference
+}
+
+// adds `b`, shifted up by `shift` limbs, into `sum`, which is long enough
+fn add_shifted(sum: &mut [u32], b: &[u32], shift: usize) {
+    let mut carry = 0u64;
+    let mut i = shift;
+    for &limb in b {
+        let total = u64::from(sum[i]) + u64::from(limb) + carry;
+        sum[i] = total as u32;
+        carry = total >> 32;
+        i += 1;
+    }
+    while carry > 0 {
+        let total = u64::from(sum[i]) + carry;
+        sum[i] = total as u32;
+        carry = total >> 32;
+        i += 1;
+    }
+}
+
+fn mul_limbs(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    if short.len() < KARATSUBA_THRESHOLD {
+        mul_schoolbook(a, b)
+    } else if long.len() >= 2 * short.len() {
+        mul_unbalanced(long, short)
+    } else {
+        mul_karatsuba(a, b)
+    }
+}
+
+// Karatsuba only pays when both halves are about as long, so a much longer
+// number is cut into pieces as long as the shorter one, and each piece is
+// multiplied on its own.
+fn mul_unbalanced(long: &[u32], short: &[u32]) -> Vec<u32> {
+    let mut product = vec![0u32; long.len() + short.len()];
+    for (i, piece) in long.chunks(short.len()).enumerate() {
+        let mut partial = mul_limbs(piece, short);
+        trim(&mut partial);
+        add_shifted(&mut product, &partial, i * short.len());
+    }
+    product
+}
+
+fn mul_schoolbook(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut product = vec![0u32; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate() {
+        // (2^32 - 1)^2 + 2 * (2^32 - 1) is exactly u64::MAX, so this can't
+        // overflow
+        let mut carry = 0u64;
+        for (j, &y) in b.iter().enumerate() {
+            let total = u64::from(product[i + j]) + u64::from(x) * u64::from(y) + carry;
+            product[i + j] = total as u32;
+            carry = total >> 32;
+        }
+        product[i + b.len()] = carry as u32;
+    }
+    product
+}
+
+fn mul_karatsuba(a: &[u32], b: &[u32]) -> Vec<u32> {
+    // split where both numbers have a high half, even if one is much longer
+    let half = a.len().min(b.len()) / 2;
+    let (a0, a1) = a.split_at(half);
+    let (b0, b1) = b.split_at(half);
+
+    let z0 = mul_limbs(a0, b0);
+    let z2 = mul_limbs(a1, b1);
+    let mut z1 = mul_limbs(&add_limbs(a0, a1), &add_limbs(b0, b1));
+    z1 = sub_limbs(&z1, &z0);
+    z1 = sub_limbs(&z1, &z2);
+    trim(&mut z1);
+
+    let mut product = vec![0u32; a.len() + b.len() + 1];
+    add_shifted(&mut product, &z0, 0);
+    add_shifted(&mut product, &z1, half);
+    add_shifted(&mut product, &z2, 2 * half);
+    product
+}
+
+// `limbs * scale + add`, in place
+fn mul_add_small(limbs: &mut Vec<u32>, scale: u32, add: u32) {
+    let mut carry = u64::from(add);
+    for limb in limbs.iter_mut() {
+        let total = u64::from(*limb) * u64::from(scale) + carry;
+        *limb = total as u32;
+        carry = total >> 32;
+    }
+    if carry > 0 {
+        limbs.push(carry as u32);
+    }
+}
+
+fn div_rem_small(a: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder = 0u64;
+    for (i, &limb) in a.iter().enumerate().rev() {
+        let current = remainder << 32 | u64::from(limb);
+        quotient[i] = (current / u64::from(divisor)) as u32;
+        remainder = current % u64::from(divisor);
+    }
+    trim(&mut quotient);
+    (quotient, remainder as u32)
+}
+
+// Knuth's Algorithm D; `b` must not be zero
+fn div_rem_limbs(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    let mut b = b.to_vec();
+    trim(&mut b);
+    if cmp_limbs(a, &b) == Ordering::Less {
+        return (Vec::new(), a.to_vec());
+    }
+    if b.len() == 1 {
+        let (quotient, remainder) = div_rem_small(a, b[0]);
+        return (quotient, vec![remainder]);
+    }
+
+    // shift so the top bit of the divisor is set, giving `a` an extra limb
+    let shift = b[b.len() - 1].leading_zeros();
+    let divisor = shift_left(&b, shift);
+    let mut rest = shift_left(a, shift);
+    rest.resize(a.len() + 1, 0);
+
+    let n = divisor.len();
+    let m = rest.len() - n - 1;
+    let top = u64::from(divisor[n - 1]);
+    let next = u64::from(divisor[n - 2]);
+    let base = 1u64 << 32;
+    let mut quotient = vec![0u32; m + 1];
+
+    for j in (0..=m).rev() {
+        // guess the next limb from the top two limbs of what's left
+        let numerator = u64::from(rest[j + n]) << 32 | u64::from(rest[j + n - 1]);
+        let mut guess = numerator / top;
+        let mut remainder = numerator % top;
+        while guess >= base || guess * next > (remainder << 32 | u64::from(rest[j + n - 2])) {
+            guess -= 1;
+            remainder += top;
+            if remainder >= base {
+                break;
+            }
+        }
+
+        // subtract guess * divisor from rest[j..=j + n]
+        let mut borrow = 0i64;
+        let mut carry = 0u64;
+        for i in 0..n {
+            let product = guess * u64::from(divisor[i]) + carry;
+            carry = product >> 32;
+            let difference = i64::from(rest[i + j]) - (product & 0xffff_ffff) as i64 + borrow;
+            rest[i + j] = difference as u32;
+            borrow = difference >> 32;
+        }
+        let difference = i64::from(rest[j + n]) - carry as i64 + borrow;
+        rest[j + n] = difference as u32;
+
+        // the guess was one too big after all, so add the divisor back
+        if difference < 0 {
+            guess -= 1;
+            let mut carry = 0u64;
+            for i in 0..n {
+                let total = u64::from(rest[i + j]) + u64::from(divisor[i]) + carry;
+                rest[i + j] = total as u32;
+                carry = total >> 32;
+            }
+            rest[j + n] = rest[j + n].wrapping_add(carry as u32);
+        }
+        quotient[j] = guess as u32;
+    }
+
+    rest.truncate(n);
+    (quotient, shift_right(&rest, shift))
+}
+
+// shifts by fewer than 32 bits
+fn shift_left(limbs: &[u32], shift: u32) -> Vec<u32> {
+    if shift == 0 {
+        return limbs.to_vec();
+    }
+    let mut shifted = Vec::with_capacity(limbs.len() + 1);
+    let mut carry = 0;
+    for &limb in limbs {
+        shifted.push(limb << shift | carry);
+        carry = limb >> (32 - shift);
+    }
+    if carry > 0 {
+        shifted.push(carry);
+    }
+    shifted
+}
+
+fn shift_right(limbs: &[u32], shift: u32) -> Vec<u32> {
+    if shift == 0 {
+        return limbs.to_vec();
+    }
+    let mut shifted = vec![0u32; limbs.len()];
+    for i in 0..limbs.len() {
+        let high = limbs.get(i + 1).map_or(0, |&l| l << (32 - shift));
+        shifted[i] = limbs[i] >> shift | high;
+    }
+    shifted
+}
+
+// the most digits in `radix` that always fit in a u32, and radix^that
+fn chunk_size(radix: u32) -> (usize, u32) {
+    let mut len = 1;
+    let mut scale = radix;
+    while let Some(next) = scale.checked_mul(radix) {
+        scale = next;
+        len += 1;
+    }
+    (len, scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // numbers with some limbs all ones or all zeros, which is where
+    // carries and corrections happen
+    fn limb(rng: &mut Rng) -> u32 {
+        match rng.below(8) {
+            0 => 0,
+            1 => u32::MAX,
+            2 => 1 << 31,
+            _ => rng.next_u64() as u32,
+        }
+    }
+
+    fn random_u128(rng: &mut Rng) -> u128 {
+        let limbs = rng.below(5);
+        (0..limbs).fold(0, |n, _| n << 32 | u128::from(limb(rng)))
+    }
+
+    fn random_biguint(rng: &mut Rng, max_limbs: u64) -> BigUint {
+        let len = rng.below(max_limbs + 1);
+        BigUint::from_limbs((0..len).map(|_| limb(rng)).collect())
+    }
+
+    fn big(n: u128) -> BigUint {
+        BigUint::from(n)
+    }
+
+    #[test]
+    fn agrees_with_u128() {
+        let mut rng = Rng::new(0x5eed);
+        for _ in 0..20_000 {
+            let (a, b) = (random_u128(&mut rng), random_u128(&mut rng));
+            assert_eq!((big(a) + big(b)).to_u128(), a.checked_add(b), "{a} + {b}");
+            assert_eq!(
+                big(a).checked_sub(&big(b)).ok().and_then(|d| d.to_u128()),
+                a.checked_sub(b),
+                "{a} - {b}"
+            );
+            assert_eq!((big(a) * big(b)).to_u128(), a.checked_mul(b), "{a} * {b}");
+            if b != 0 {
+                let (q, r) = big(a).div_rem(&big(b));
+                assert_eq!(
+                    (q.to_u128(), r.to_u128()),
+                    (Some(a / b), Some(a % b)),
+                    "{a} / {b}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn signed_agrees_with_i128() {
+        let mut rng = Rng::new(0xfeed);
+        for _ in 0..20_000 {
+            let a = rng.next_u64() as i64 as i128 >> rng.below(64);
+            let b = rng.next_u64() as i64 as i128 >> rng.below(64);
+            let (x, y) = (BigInt::from(a), BigInt::from(b));
+            assert_eq!((&x + &y).to_i128(), Some(a + b), "{a} + {b}");
+            assert_eq!((&x - &y).to_i128(), Some(a - b), "{a} - {b}");
+            assert_eq!((&x * &y).to_i128(), Some(a * b), "{a} * {b}");
+            assert_eq!((-&x).to_i128(), Some(-a));
+            assert_eq!(x.cmp(&y), a.cmp(&b));
+            if b != 0 {
+                assert_eq!((&x / &y).to_i128(), Some(a / b), "{a} / {b}");
+                assert_eq!((&x % &y).to_i128(), Some(a % b), "{a} % {b}");
+            }
+            assert_eq!(x.to_string(), a.to_string());
+        }
+    }
+
+    #[test]
+    fn karatsuba_agrees_with_schoolbook() {
+        let mut rng = Rng::new(0xcafe);
+        for _ in 0..40 {
+            let a: Vec<u32> = (0..KARATSUBA_THRESHOLD + rng.below(200) as usize)
+                .map(|_| limb(&mut rng))
+                .collect();
+            let b: Vec<u32> = (0..KARATSUBA_THRESHOLD + rng.below(300) as usize)
+                .map(|_| limb(&mut rng))
+                .collect();
+            let mut fast = mul_karatsuba(&a, &b);
+            let mut slow = mul_schoolbook(&a, &b);
+            trim(&mut fast);
+            trim(&mut slow);
+            assert_eq!(fast, slow);
+        }
+    }
+
+    #[test]
+    fn unbalanced_products_agree_with_schoolbook() {
+        let mut rng = Rng::new(0xf00d);
+        for (short, long) in [(32, 10_000), (40, 81), (50, 99), (33, 66)] {
+            let a: Vec<u32> = (0..short).map(|_| limb(&mut rng)).collect();
+            let b: Vec<u32> = (0..long).map(|_| limb(&mut rng)).collect();
+            let mut fast = mul_limbs(&a, &b);
+            let mut slow = mul_schoolbook(&a, &b);
+            trim(&mut fast);
+            trim(&mut slow);
+            assert_eq!(fast, slow, "{short} x {long} limbs");
+            let mut swapped = mul_limbs(&b, &a);
+            trim(&mut swapped);
+            assert_eq!(swapped, slow);
+        }
+    }
+
+    #[test]
+    fn division_undoes_multiplication() {
+        let mut rng = Rng::new(0xd1ce);
+        for _ in 0..500 {
+            let a = random_biguint(&mut rng, 80);
+            let b = random_biguint(&mut rng, 40);
+            if b.is_zero() {
+                continue;
+            }
+            let (q, r) = a.div_rem(&b);
+            assert!(r < b);
+            assert_eq!(&q * &b + &r, a);
+            assert_eq!((&a * &b) / &b, a);
+        }
+    }
+
+    #[test]
+    fn division_needing_the_add_back_step() {
+        // from Hacker's Delight: the subtraction here goes below zero, so
+        // the guess was one too big and the divisor is added back
+        let a = 0x7fff_8000_0000_0000_0000_0000_0000u128;
+        let b = 0x8000_0000_0000_0000_0000_0001u128;
+        let (q, r) = big(a).div_rem(&big(b));
+        assert_eq!(q, big(0xfffe));
+        assert_eq!(r, big(0x7fff_ffff_ffff_ffff_ffff_0002));
+        assert_eq!((a / b, a % b), (0xfffe, 0x7fff_ffff_ffff_ffff_ffff_0002));
+    }
+
+    #[test]
+    fn radix_round_trips() {
+        let mut rng = Rng::new(0xbeef);
+        for _ in 0..2_000 {
+            let n = random_u128(&mut rng);
+            let radix = 2 + rng.below(35) as u32;
+            let text = big(n).to_str_radix(radix);
+            assert_eq!(text, u128_to_radix(n, radix), "{n} in radix {radix}");
+            assert_eq!(BigUint::from_str_radix(&text, radix), Ok(big(n)));
+        }
+        let n = random_biguint(&mut rng, 100);
+        for radix in 2..=36 {
+            assert_eq!(
+                BigUint::from_str_radix(&n.to_str_radix(radix), radix),
+                Ok(n.clone())
+            );
+        }
+    }
+
+    fn u128_to_radix(mut n: u128, radix: u32) -> String {
+        let mut digits = Vec::new();
+        loop {
+            digits.push(std::char::from_digit((n % u128::from(radix)) as u32, radix).unwrap());
+            n /= u128::from(radix);
+            if n == 0 {
+                break;
+            }
+        }
+        digits.iter().rev().collect()
+    }
+
+    #[test]
+    fn known_values() {
+        let factorial = (1..=30u32).fold(BigUint::from(1u8), |n, i| n * BigUint::from(i));
+        assert_eq!(factorial.to_string(), "265252859812191058636308480000000");
+        assert_eq!(
+            BigUint::from(2u8).pow(128).to_string(),
+            "340282366920938463463374607431768211456"
+        );
+        assert_eq!(BigUint::from(2u8).pow(100).bits(), 101);
+        assert_eq!(BigInt::from(-3).pow(3), BigInt::from(-27));
+        assert_eq!(BigUint::from(255u8).to_str_radix(16), "ff");
+        assert_eq!(BigInt::from(-255).to_str_radix(2), "-11111111");
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!("0042".parse(), Ok(BigUint::from(42u8)));
+        assert_eq!("+7".parse(), Ok(BigUint::from(7u8)));
+        assert_eq!("-0".parse(), Ok(BigInt::zero()));
+        assert_eq!("-12".parse(), Ok(BigInt::from(-12)));
+        assert_eq!(BigInt::from_str_radix("-zz", 36), Ok(BigInt::from(-1295)));
+        assert_eq!("".parse::<BigUint>(), Err(ParseBigIntError::Empty));
+        assert_eq!("-".parse::<BigInt>(), Err(ParseBigIntError::InvalidDigit));
+        assert_eq!("-5".parse::<BigUint>(), Err(ParseBigIntError::InvalidDigit));
+        assert_eq!(
+            "12a".parse::<BigUint>(),
+            Err(ParseBigIntError::InvalidDigit)
+        );
+        assert_eq!(
+            BigUint::from_str_radix("1", 37),
+            Err(ParseBigIntError::InvalidRadix)
+        );
+    }
+
+    #[test]
+    fn formatting_follows_the_flags() {
+        assert_eq!(format!("{:>6}", BigUint::from(42u8)), "    42");
+        assert_eq!(format!("{:+}", BigInt::from(42)), "+42");
+        assert_eq!(format!("{:05}", BigInt::from(-42)), "-0042");
+        assert_eq!(BigUint::zero().to_string(), "0");
+    }
+
+    #[test]
+    fn operators_take_values_and_references() {
+        let mut n = BigInt::from(10);
+        n += BigInt::from(5);
+        n -= &BigInt::from(1);
+        n *= BigInt::from(-3);
+        n /= &BigInt::from(4);
+        n %= BigInt::from(7);
+        // ((10 + 5 - 1) * -3) / 4 = -10, and -10 % 7 = -3
+        assert_eq!(n, BigInt::from(-3));
+        assert_eq!(&n + BigInt::from(3), BigInt::zero());
+    }
+
+    #[test]
+    fn checked_operations() {
+        assert_eq!(
+            big(1).checked_sub(&big(2)),
+            Err(ArithmeticError::Overflow(Operation::Sub))
+        );
+        assert_eq!(
+            big(1).checked_div_rem(&BigUint::zero()),
+            Err(ArithmeticError::DivisionByZero)
+        );
+        assert_eq!(
+            BigInt::from(-7).checked_div_rem(&BigInt::from(2)),
+            Ok((BigInt::from(-3), BigInt::from(-1)))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "attempt to subtract with overflow")]
+    fn subtracting_a_bigger_number_panics() {
+        let _ = big(1) - big(2);
+    }
+
+    #[test]
+    #[should_panic(expected = "attempt to divide by zero")]
+    fn dividing_by_zero_panics() {
+        let _ = BigInt::from(1) / BigInt::zero();
+    }
+}
@@ -0,0 +1,88 @@
+//The adder test runner
+/*
+Runs adder's checks with the runner in runner.rs instead of 'cargo test',
+for when a time limit per test, a list of the slowest tests or a JUnit XML
+report is wanted:
+
+    $ adder-test --junit target/adder.xml
+    $ adder-test bigint --include-ignored --timeout 10
+
+It exits with 0 when everything passed and 101 when something failed or
+timed out, the same as 'cargo test'.
+*/
+
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
+use std::time::Duration;
+
+use adder::calc::Calculator;
+use adder::geometry::{self, Rectangle};
+use adder::runner::{self, Options, Test};
+use adder::{add_two, forall, BigUint};
+
+fn tests() -> Vec<Test> {
+    adder::tests! {
+        fn adds_two() {
+            assert_eq!(add_two(2), 4);
+            assert_eq!(add_two(-2), 0);
+        }
+
+        #[timeout(Duration::from_secs(10))]
+        fn add_two_for_any_number() {
+            forall!(|a: i32| a.checked_add(2).is_none_or(|b| add_two(a) == b));
+        }
+
+        fn calculator_follows_precedence() {
+            let mut calc = Calculator::new();
+            assert_eq!(calc.line("2 + 3 * 4 ^ 2").unwrap(), "50");
+        }
+
+        #[should_panic]
+        fn calculator_variables_need_a_let() {
+            Calculator::new().line("x + 1").unwrap();
+        }
+
+        fn big_numbers_round_trip() {
+            let n: BigUint = "123456789012345678901234567890".parse().unwrap();
+            assert_eq!(n.to_string(), "123456789012345678901234567890");
+            assert_eq!(BigUint::from_str_radix(&n.to_str_radix(36), 36).unwrap(), n);
+        }
+
+        fn packing_fills_the_container() {
+            let items = vec![Rectangle::new(5, 5); 16];
+            let placed = geometry::pack(Rectangle::new(20, 20), &items);
+            assert!(placed.iter().all(Option::is_some));
+        }
+
+        // takes a few seconds in a debug build
+        #[ignore]
+        fn factorial_of_ten_thousand() {
+            let factorial = (1..=10_000u32).fold(BigUint::from(1u32), |f, n| f * BigUint::from(n));
+            assert_eq!(factorial.to_string().len(), 35_660);
+        }
+    }
+}
+
+fn main() {
+    let options = Options::build(env::args()).unwrap_or_else(|err| {
+        eprintln!("Problem parsing arguments: {err}");
+        process::exit(1);
+    });
+
+    let report = runner::run(&tests(), &options, &mut io::stdout()).unwrap_or_else(|err| {
+        eprintln!("Application error: {err}");
+        process::exit(1);
+    });
+
+    if let Some(path) = &options.junit {
+        if let Err(err) = fs::write(path, report.to_junit("adder")) {
+            eprintln!("Couldn't write {}: {err}", path.display());
+            process::exit(1);
+        }
+    }
+
+    // a test that timed out may still be running, and this ends it too
+    process::exit(if report.success() { 0 } else { 101 });
+}
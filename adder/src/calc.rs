@@ -0,0 +1,625 @@
+//A calculator for integer expressions
+/*
+The adder binary reads one line at a time and works out what it says:
+
+    > 2 + 3 * (4 - 1)
+    11
+    > let width = 12
+    width = 12
+    > width * -2 ^ 3
+    -96
+    > 7 / 0
+    error at column 3: division by zero
+    > :history
+    1  2 + 3 * (4 - 1)
+    2  let width = 12
+    ...
+
+Working out a line happens in three steps.
+
+The tokenizer splits the text into numbers, names and symbols, and notes
+the column each one starts at, counting characters from 1. Every error
+points at one of these columns, so a mistake in a long line is easy to find.
+
+The parser turns the tokens into a tree with precedence climbing: each
+operator has a precedence, and parsing an expression only takes operators
+at or above a minimum precedence, calling itself with a higher minimum for
+the right-hand side. '^' takes the same minimum again instead, so it groups
+from the right: 2 ^ 3 ^ 2 is 2 ^ 9. From loosest to tightest:
+
+    + -        left to right
+    * / %      left to right
+    - (unary)  so -2 ^ 2 is -(2 ^ 2)
+    ^          right to left
+
+Every '(', unary '-' and '^' nests the parser one level deeper, so a line
+nested more than MAX_DEPTH levels is an error at the token that went too
+deep, instead of a stack overflow that ends the whole session.
+
+Evaluating walks the tree with the checked functions from arithmetic.rs, so
+an i64 overflow or a division by zero is an error at the operator's column
+instead of a panic or a wrong answer.
+
+'let name = expression' stores a value for later lines, and ':history'
+lists the lines entered so far.
+*/
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::arithmetic::{self, ArithmeticError};
+
+/// Something wrong with a line, and the column it starts at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalcError {
+    /// Counting characters from 1.
+    pub column: usize,
+    pub message: String,
+}
+
+impl CalcError {
+    fn new(column: usize, message: impl Into<String>) -> CalcError {
+        CalcError {
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "error at column {}: {}", self.column, self.message)
+    }
+}
+
+impl Error for CalcError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TokenKind {
+    Number(i64),
+    Name(String),
+    Let,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Caret,
+    Equals,
+    LeftParen,
+    RightParen,
+    End,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Token {
+    kind: TokenKind,
+    column: usize,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenKind::Number(n) => write!(f, "'{n}'"),
+            TokenKind::Name(name) => write!(f, "'{name}'"),
+            TokenKind::Let => write!(f, "'let'"),
+            TokenKind::Plus => write!(f, "'+'"),
+            TokenKind::Minus => write!(f, "'-'"),
+            TokenKind::Star => write!(f, "'*'"),
+            TokenKind::Slash => write!(f, "'/'"),
+            TokenKind::Percent => write!(f, "'%'"),
+            TokenKind::Caret => write!(f, "'^'"),
+            TokenKind::Equals => write!(f, "'='"),
+            TokenKind::LeftParen => write!(f, "'('"),
+            TokenKind::RightParen => write!(f, "')'"),
+            TokenKind::End => write!(f, "the end of the line"),
+        }
+    }
+}
+
+fn tokenize(line: &str) -> Result<Vec<Token>, CalcError> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let column = i + 1;
+        let c = chars[i];
+        let kind = match c {
+            _ if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '0'..='9' => {
+                let start = i;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let digits: String = chars[start..i].iter().collect();
+                let n = digits
+                    .parse()
+                    .map_err(|_| CalcError::new(column, "number is too large"))?;
+                tokens.push(Token {
+                    kind: TokenKind::Number(n),
+                    column,
+                });
+                continue;
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let name: String = chars[start..i].iter().collect();
+                let kind = if name == "let" {
+                    TokenKind::Let
+                } else {
+                    TokenKind::Name(name)
+                };
+                tokens.push(Token { kind, column });
+                continue;
+            }
+            '+' => TokenKind::Plus,
+            '-' => TokenKind::Minus,
+            '*' => TokenKind::Star,
+            '/' => TokenKind::Slash,
+            '%' => TokenKind::Percent,
+            '^' => TokenKind::Caret,
+            '=' => TokenKind::Equals,
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
+            _ => {
+                return Err(CalcError::new(
+                    column,
+                    format!("unexpected character '{c}'"),
+                ))
+            }
+        };
+        tokens.push(Token { kind, column });
+        i += 1;
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::End,
+        column: chars.len() + 1,
+    });
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+}
+
+impl BinaryOp {
+    fn from_token(kind: &TokenKind) -> Option<BinaryOp> {
+        match kind {
+            TokenKind::Plus => Some(BinaryOp::Add),
+            TokenKind::Minus => Some(BinaryOp::Sub),
+            TokenKind::Star => Some(BinaryOp::Mul),
+            TokenKind::Slash => Some(BinaryOp::Div),
+            TokenKind::Percent => Some(BinaryOp::Rem),
+            TokenKind::Caret => Some(BinaryOp::Pow),
+            _ => None,
+        }
+    }
+
+    fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Add | BinaryOp::Sub => 1,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 2,
+            BinaryOp::Pow => 4,
+        }
+    }
+
+    fn right_associative(self) -> bool {
+        self == BinaryOp::Pow
+    }
+}
+
+// between * and ^
+const UNARY_PRECEDENCE: u8 = 3;
+
+/// How deeply parentheses, unary minus and '^' can nest in one line.
+pub const MAX_DEPTH: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Number(i64),
+    Variable(String, usize),
+    // the column is the operator's, for errors
+    Negate(Box<Expr>, usize),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>, usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Statement {
+    Let(String, Expr),
+    Expr(Expr),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.next]
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.next].clone();
+        if token.kind != TokenKind::End {
+            self.next += 1;
+        }
+        token
+    }
+
+    fn expect(&mut self, kind: TokenKind, what: &str) -> Result<Token, CalcError> {
+        let token = self.advance();
+        if token.kind == kind {
+            Ok(token)
+        } else {
+            Err(CalcError::new(
+                token.column,
+                format!("expected {what}, found {}", token.kind),
+            ))
+        }
+    }
+
+    fn statement(&mut self) -> Result<Statement, CalcError> {
+        let statement = if self.peek().kind == TokenKind::Let {
+            self.advance();
+            let token = self.advance();
+            let TokenKind::Name(name) = token.kind else {
+                return Err(CalcError::new(
+                    token.column,
+                    format!("expected a name after 'let', found {}", token.kind),
+                ));
+            };
+            self.expect(TokenKind::Equals, "'='")?;
+            Statement::Let(name, self.expression(0)?)
+        } else {
+            Statement::Expr(self.expression(0)?)
+        };
+        self.expect(TokenKind::End, "an operator")?;
+        Ok(statement)
+    }
+
+    // precedence climbing: only takes operators at least `min_precedence`
+    fn expression(&mut self, min_precedence: u8) -> Result<Expr, CalcError> {
+        if self.depth == MAX_DEPTH {
+            return Err(CalcError::new(
+                self.peek().column,
+                format!("expression is nested more than {MAX_DEPTH} deep"),
+            ));
+        }
+        self.depth += 1;
+        let expr = self.climb(min_precedence);
+        self.depth -= 1;
+        expr
+    }
+
+    fn climb(&mut self, min_precedence: u8) -> Result<Expr, CalcError> {
+        let mut left = self.operand()?;
+
+        while let Some(op) = BinaryOp::from_token(&self.peek().kind) {
+            if op.precedence() < min_precedence {
+                break;
+            }
+            let column = self.advance().column;
+            let next_min = if op.right_associative() {
+                op.precedence()
+            } else {
+                op.precedence() + 1
+            };
+            let right = self.expression(next_min)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right), column);
+        }
+
+        Ok(left)
+    }
+
+    fn operand(&mut self) -> Result<Expr, CalcError> {
+        let token = self.advance();
+        match token.kind {
+            TokenKind::Number(n) => Ok(Expr::Number(n)),
+            TokenKind::Name(name) => Ok(Expr::Variable(name, token.column)),
+            TokenKind::Minus => {
+                let operand = self.expression(UNARY_PRECEDENCE)?;
+                Ok(Expr::Negate(Box::new(operand), token.column))
+            }
+            TokenKind::LeftParen => {
+                let inner = self.expression(0)?;
+                self.expect(TokenKind::RightParen, "')'")?;
+                Ok(inner)
+            }
+            kind => Err(CalcError::new(
+                token.column,
+                format!("expected a number, a name or '(', found {kind}"),
+            )),
+        }
+    }
+}
+
+fn parse(line: &str) -> Result<Statement, CalcError> {
+    let mut parser = Parser {
+        tokens: tokenize(line)?,
+        next: 0,
+        depth: 0,
+    };
+    parser.statement()
+}
+
+/// The variables and history of one calculator session.
+#[derive(Debug, Default)]
+pub struct Calculator {
+    variables: HashMap<String, i64>,
+    history: Vec<String>,
+}
+
+impl Calculator {
+    pub fn new() -> Calculator {
+        Calculator::default()
+    }
+
+    /// Works out one line and returns what to print, which is empty for a
+    /// blank line.
+    pub fn line(&mut self, line: &str) -> Result<String, CalcError> {
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            return Ok(String::new());
+        }
+        if let Some(command) = line.trim_start().strip_prefix(':') {
+            return self.command(command, line);
+        }
+
+        self.history.push(line.to_string());
+        match parse(line)? {
+            Statement::Let(name, expr) => {
+                let value = self.evaluate(&expr)?;
+                self.variables.insert(name.clone(), value);
+                Ok(format!("{name} = {value}"))
+            }
+            Statement::Expr(expr) => Ok(self.evaluate(&expr)?.to_string()),
+        }
+    }
+
+    /// The value of `expression`, without changing any variables.
+    pub fn evaluate_str(&self, expression: &str) -> Result<i64, CalcError> {
+        match parse(expression)? {
+            Statement::Expr(expr) => self.evaluate(&expr),
+            Statement::Let(..) => Err(CalcError::new(1, "expected an expression, found 'let'")),
+        }
+    }
+
+    /// The lines entered so far, not counting commands.
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    pub fn variable(&self, name: &str) -> Option<i64> {
+        self.variables.get(name).copied()
+    }
+
+    fn command(&self, command: &str, line: &str) -> Result<String, CalcError> {
+        match command.trim() {
+            "history" => Ok(self
+                .history
+                .iter()
+                .enumerate()
+                .map(|(i, line)| format!("{}  {line}", i + 1))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            other => {
+                let column = line.len() - line.trim_start().len() + 1;
+                Err(CalcError::new(
+                    column,
+                    format!("unknown command ':{other}'"),
+                ))
+            }
+        }
+    }
+
+    fn evaluate(&self, expr: &Expr) -> Result<i64, CalcError> {
+        match expr {
+            Expr::Number(n) => Ok(*n),
+            Expr::Variable(name, column) => self
+                .variables
+                .get(name)
+                .copied()
+                .ok_or_else(|| CalcError::new(*column, format!("'{name}' isn't defined"))),
+            Expr::Negate(operand, column) => {
+                let value = self.evaluate(operand)?;
+                arithmetic::checked_sub(0, value).map_err(|e| arithmetic_error(*column, e))
+            }
+            Expr::Binary(op, left, right, column) => {
+                let (a, b) = (self.evaluate(left)?, self.evaluate(right)?);
+                let result = match op {
+                    BinaryOp::Add => arithmetic::checked_add(a, b),
+                    BinaryOp::Sub => arithmetic::checked_sub(a, b),
+                    BinaryOp::Mul => arithmetic::checked_mul(a, b),
+                    BinaryOp::Div => arithmetic::checked_div(a, b),
+                    BinaryOp::Rem => checked_rem(a, b),
+                    BinaryOp::Pow => return checked_pow(a, b, *column),
+                };
+                result.map_err(|e| arithmetic_error(*column, e))
+            }
+        }
+    }
+}
+
+fn arithmetic_error(column: usize, error: ArithmeticError) -> CalcError {
+    CalcError::new(column, error.to_string())
+}
+
+fn checked_rem(a: i64, b: i64) -> Result<i64, ArithmeticError> {
+    // the remainder is whatever's left after the quotient, so it fails the
+    // same way the division does
+    let quotient = arithmetic::checked_div(a, b)?;
+    arithmetic::checked_sub(a, arithmetic::checked_mul(quotient, b)?)
+}
+
+fn checked_pow(base: i64, exp: i64, column: usize) -> Result<i64, CalcError> {
+    if exp < 0 {
+        return Err(CalcError::new(column, "negative powers aren't integers"));
+    }
+    match base {
+        // these never grow, however big the power
+        0 | 1 => Ok(if exp == 0 { 1 } else { base }),
+        -1 => Ok(if exp % 2 == 0 { 1 } else { -1 }),
+        // anything else overflows within 64 multiplications
+        _ => (0..exp).try_fold(1, |result, _| {
+            arithmetic::checked_mul(result, base).map_err(|e| arithmetic_error(column, e))
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(line: &str) -> Result<i64, CalcError> {
+        Calculator::new().evaluate_str(line)
+    }
+
+    fn error(column: usize, message: &str) -> Result<i64, CalcError> {
+        Err(CalcError::new(column, message))
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        let cases = [
+            ("2 + 3 * (4 - 1)", 11),
+            ("2 + 3 * 4 - 1", 13),
+            ("10 - 4 - 3", 3),
+            ("100 / 10 / 5", 2),
+            ("2 ^ 3 ^ 2", 512),
+            ("-2 ^ 2", -4),
+            ("(-2) ^ 2", 4),
+            ("2 * -3", -6),
+            ("--5", 5),
+            ("17 % 5 * 2", 4),
+            ("-7 / 2", -3),
+            ("-7 % 2", -1),
+            ("((((1))))", 1),
+            ("0 ^ 0", 1),
+            ("(-1) ^ 1001", -1),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(eval(line), Ok(expected), "{line}");
+        }
+    }
+
+    #[test]
+    fn errors_point_at_the_column() {
+        let cases = [
+            ("1 / 0", error(3, "division by zero")),
+            ("1 + (2 * 0) % 0", error(13, "division by zero")),
+            ("9223372036854775807 + 1", error(21, "addition overflowed")),
+            (
+                "-9223372036854775807 - 2",
+                error(22, "subtraction overflowed"),
+            ),
+            ("2 ^ 64", error(3, "multiplication overflowed")),
+            ("2 ^ -1", error(3, "negative powers aren't integers")),
+            ("99999999999999999999", error(1, "number is too large")),
+            (
+                "2 + * 3",
+                error(5, "expected a number, a name or '(', found '*'"),
+            ),
+            (
+                "(1 + 2",
+                error(7, "expected ')', found the end of the line"),
+            ),
+            ("1 2", error(3, "expected an operator, found '2'")),
+            ("2 # 3", error(3, "unexpected character '#'")),
+            ("width * 2", error(1, "'width' isn't defined")),
+            (
+                "",
+                error(
+                    1,
+                    "expected a number, a name or '(', found the end of the line",
+                ),
+            ),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(eval(line), expected, "{line}");
+        }
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error_not_a_crash() {
+        let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(eval(&nested(MAX_DEPTH - 1)), Ok(1));
+        assert_eq!(
+            eval(&nested(MAX_DEPTH)),
+            error(MAX_DEPTH + 1, "expression is nested more than 256 deep")
+        );
+        assert_eq!(
+            eval(&"(".repeat(5000)),
+            error(MAX_DEPTH + 1, "expression is nested more than 256 deep")
+        );
+        assert_eq!(
+            eval(&format!("1 + {}2", "-".repeat(200_000))),
+            error(MAX_DEPTH + 4, "expression is nested more than 256 deep")
+        );
+        assert!(eval(&vec!["2"; 1000].join("^")).is_err());
+
+        // and the session carries on
+        let mut calc = Calculator::new();
+        assert!(calc.line(&"(".repeat(5000)).is_err());
+        assert_eq!(calc.line("1 + 1"), Ok(String::from("2")));
+    }
+
+    #[test]
+    fn variables_and_history() {
+        let mut calc = Calculator::new();
+        assert_eq!(calc.line("let width = 12"), Ok(String::from("width = 12")));
+        assert_eq!(
+            calc.line("let area = width * width"),
+            Ok(String::from("area = 144"))
+        );
+        assert_eq!(calc.line("area - 44"), Ok(String::from("100")));
+        assert_eq!(calc.line("   "), Ok(String::new()));
+        assert_eq!(calc.variable("area"), Some(144));
+
+        // a failed let leaves the old value
+        assert_eq!(
+            calc.line("let width = width / 0"),
+            Err(CalcError::new(19, "division by zero"))
+        );
+        assert_eq!(calc.variable("width"), Some(12));
+        assert_eq!(
+            calc.line("let = 4"),
+            Err(CalcError::new(5, "expected a name after 'let', found '='"))
+        );
+
+        assert_eq!(
+            calc.line(":history"),
+            Ok(String::from(
+                "1  let width = 12\n2  let area = width * width\n3  area - 44\n\
+                 4  let width = width / 0\n5  let = 4"
+            ))
+        );
+        assert_eq!(
+            calc.line("  :quit"),
+            Err(CalcError::new(3, "unknown command ':quit'"))
+        );
+    }
+
+    #[test]
+    fn columns_count_characters() {
+        assert_eq!(eval("é + 1"), error(1, "'é' isn't defined"));
+        assert_eq!(eval("(é) / 0"), error(2, "'é' isn't defined"));
+        assert_eq!(eval("1 + é * 0 / 0"), error(5, "'é' isn't defined"));
+    }
+}
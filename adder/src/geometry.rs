@@ -0,0 +1,429 @@
+//Rectangles: sizes, positions and packing
+/*
+The Rectangle from chapter 5 only has a size, and 'can_hold' asks whether
+one fits inside another with room to spare. The version in the notes in
+lib.rs compared 'self.width < other.width', which is backwards: it said a
+small rectangle could hold a big one as long as it was taller. Here it's
+the book's fixed version, bigger in both directions.
+
+Asking where rectangles overlap needs them to have a place as well, so Rect
+is a Rectangle at a position, with x growing to the right and y growing
+down, like the pixels of a screen. A Rect covers the points from its left
+edge up to, but not including, its right edge, and the same from top to
+bottom. So two Rects that only share an edge don't overlap, and a Rect with
+no width or no height covers nothing. Positions and sizes are u32, and
+a Rect can't reach past u32::MAX.
+
+'pack' places many rectangles into one container without overlapping, like
+boxes in a van. Finding the best packing is NP-hard, so it uses the skyline
+method, which is quick and does well in practice. It keeps track of the
+outline of what's been placed so far, as seen from below:
+
+     _______
+    |       |____          each box goes as low as it can on this outline,
+    |       |    |__       then as far left as it can
+    |_______|_______|__
+
+Placing the tallest boxes first leaves the flattest outline, so they're
+placed in that order, and the results are given in the original order.
+*/
+
+/// A size, with no position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rectangle {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rectangle {
+    pub fn new(width: u32, height: u32) -> Rectangle {
+        Rectangle { width, height }
+    }
+
+    pub fn area(&self) -> u64 {
+        u64::from(self.width) * u64::from(self.height)
+    }
+
+    /// Whether `other` fits inside with room to spare in both directions.
+    pub fn can_hold(&self, other: &Rectangle) -> bool {
+        self.width > other.width && self.height > other.height
+    }
+}
+
+/// A point in the plane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Point {
+    pub x: u32,
+    pub y: u32,
+}
+
+/// A rectangle at a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Rect {
+    /// A rectangle with its top left corner at (`x`, `y`). Panics if it
+    /// would reach past `u32::MAX`.
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Rect {
+        assert!(
+            x.checked_add(width).is_some() && y.checked_add(height).is_some(),
+            "a Rect can't reach past u32::MAX"
+        );
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// `size` with its top left corner at `corner`.
+    pub fn at(corner: Point, size: Rectangle) -> Rect {
+        Rect::new(corner.x, corner.y, size.width, size.height)
+    }
+
+    pub fn left(&self) -> u32 {
+        self.x
+    }
+
+    pub fn top(&self) -> u32 {
+        self.y
+    }
+
+    /// The first x past the rectangle.
+    pub fn right(&self) -> u32 {
+        self.x + self.width
+    }
+
+    /// The first y past the rectangle.
+    pub fn bottom(&self) -> u32 {
+        self.y + self.height
+    }
+
+    pub fn size(&self) -> Rectangle {
+        Rectangle::new(self.width, self.height)
+    }
+
+    pub fn area(&self) -> u64 {
+        self.size().area()
+    }
+
+    /// Whether the rectangle covers no points at all.
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    pub fn contains_point(&self, point: Point) -> bool {
+        (self.left()..self.right()).contains(&point.x)
+            && (self.top()..self.bottom()).contains(&point.y)
+    }
+
+    /// Whether every point of `other` is also in this rectangle. An empty
+    /// rectangle is in any other.
+    pub fn contains(&self, other: &Rect) -> bool {
+        other.is_empty()
+            || (self.left() <= other.left()
+                && other.right() <= self.right()
+                && self.top() <= other.top()
+                && other.bottom() <= self.bottom())
+    }
+
+    /// The part the two rectangles share, or `None` if they don't overlap.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let left = self.left().max(other.left());
+        let right = self.right().min(other.right());
+        let top = self.top().max(other.top());
+        let bottom = self.bottom().min(other.bottom());
+        (left < right && top < bottom).then(|| Rect::new(left, top, right - left, bottom - top))
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    /// The smallest rectangle that contains both. An empty rectangle adds
+    /// nothing to the other.
+    pub fn union(&self, other: &Rect) -> Rect {
+        if other.is_empty() {
+            return *self;
+        }
+        if self.is_empty() {
+            return *other;
+        }
+        let left = self.left().min(other.left());
+        let top = self.top().min(other.top());
+        let right = self.right().max(other.right());
+        let bottom = self.bottom().max(other.bottom());
+        Rect::new(left, top, right - left, bottom - top)
+    }
+}
+
+// one flat piece of the skyline: from x, `width` wide, at height y
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+/// Places each of `items` inside a `container` with its top left corner at
+/// (0, 0), with none of them overlapping. Returns where each one went, in
+/// the same order, or `None` for the ones there was no room for.
+pub fn pack(container: Rectangle, items: &[Rectangle]) -> Vec<Option<Rect>> {
+    let mut placed = vec![None; items.len()];
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    // tallest first, then widest
+    order.sort_by_key(|&i| std::cmp::Reverse((items[i].height, items[i].width)));
+
+    let mut skyline = vec![Segment {
+        x: 0,
+        y: 0,
+        width: container.width,
+    }];
+
+    for i in order {
+        let item = items[i];
+        if item.width > container.width || item.height > container.height {
+            continue;
+        }
+        // an empty box takes no room, so it can go anywhere
+        if item.width == 0 || item.height == 0 {
+            placed[i] = Some(Rect::new(0, 0, item.width, item.height));
+            continue;
+        }
+        if let Some((x, y)) = lowest_position(&skyline, container, item) {
+            placed[i] = Some(Rect::new(x, y, item.width, item.height));
+            raise(&mut skyline, x, y + item.height, item.width);
+        }
+    }
+
+    placed
+}
+
+// The lowest place on the skyline where `item` fits, leftmost on a tie.
+fn lowest_position(
+    skyline: &[Segment],
+    container: Rectangle,
+    item: Rectangle,
+) -> Option<(u32, u32)> {
+    let mut best: Option<(u32, u32)> = None;
+
+    for (start, segment) in skyline.iter().enumerate() {
+        let x = segment.x;
+        if u64::from(x) + u64::from(item.width) > u64::from(container.width) {
+            break;
+        }
+        // the box rests on the highest segment under it
+        let right = x + item.width;
+        let y = skyline[start..]
+            .iter()
+            .take_while(|s| s.x < right)
+            .map(|s| s.y)
+            .max()
+            .unwrap_or(segment.y);
+        if u64::from(y) + u64::from(item.height) > u64::from(container.height) {
+            continue;
+        }
+        if best.is_none_or(|(_, best_y)| y < best_y) {
+            best = Some((x, y));
+        }
+    }
+
+    best
+}
+
+// Raises the skyline to `y` from `x`, `width` wide.
+fn raise(skyline: &mut Vec<Segment>, x: u32, y: u32, width: u32) {
+    let right = x + width;
+    let mut updated = Vec::with_capacity(skyline.len() + 2);
+    for &segment in skyline.iter() {
+        let end = segment.x + segment.width;
+        // the parts of the segment left and right of the new one survive
+        if segment.x < x {
+            let kept = end.min(x) - segment.x;
+            updated.push(Segment {
+                width: kept,
+                ..segment
+            });
+        }
+        if segment.x < right
+            && x < end
+            && updated.last().is_none_or(|s: &Segment| s.x + s.width <= x)
+        {
+            updated.push(Segment { x, y, width });
+        }
+        if end > right {
+            let start = segment.x.max(right);
+            updated.push(Segment {
+                x: start,
+                y: segment.y,
+                width: end - start,
+            });
+        }
+    }
+
+    // neighbours at the same height are one segment
+    updated.dedup_by(|next, previous| {
+        if previous.y == next.y && previous.x + previous.width == next.x {
+            previous.width += next.width;
+            true
+        } else {
+            false
+        }
+    });
+    *skyline = updated;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::property::Rng;
+
+    #[test]
+    fn larger_can_hold_smaller() {
+        let larger = Rectangle::new(8, 7);
+        let smaller = Rectangle::new(5, 1);
+        assert!(larger.can_hold(&smaller));
+        assert!(!smaller.can_hold(&larger));
+    }
+
+    #[test]
+    fn can_hold_needs_room_both_ways() {
+        let cases = [
+            ((8, 7), (5, 1), true),
+            // taller but narrower was wrongly accepted before
+            ((5, 9), (8, 7), false),
+            ((8, 7), (8, 1), false),
+            ((8, 7), (1, 7), false),
+            ((8, 7), (0, 0), true),
+        ];
+        for ((w1, h1), (w2, h2), expected) in cases {
+            assert_eq!(
+                Rectangle::new(w1, h1).can_hold(&Rectangle::new(w2, h2)),
+                expected,
+                "{w1}x{h1} holding {w2}x{h2}"
+            );
+        }
+    }
+
+    #[test]
+    fn intersection_and_union() {
+        let a = Rect::new(0, 0, 10, 10);
+        let b = Rect::new(5, 5, 10, 10);
+        assert_eq!(a.intersection(&b), Some(Rect::new(5, 5, 5, 5)));
+        assert_eq!(a.union(&b), Rect::new(0, 0, 15, 15));
+
+        // sharing an edge isn't overlapping
+        let beside = Rect::new(10, 0, 4, 4);
+        assert_eq!(a.intersection(&beside), None);
+        assert!(!a.intersects(&beside));
+        assert_eq!(a.union(&beside), Rect::new(0, 0, 14, 10));
+
+        let inside = Rect::new(2, 3, 4, 5);
+        assert_eq!(a.intersection(&inside), Some(inside));
+        assert_eq!(a.union(&inside), a);
+        assert!(a.contains(&inside));
+        assert!(!inside.contains(&a));
+
+        let empty = Rect::new(50, 50, 0, 3);
+        assert_eq!(a.union(&empty), a);
+        assert!(a.contains(&empty));
+        assert!(!empty.intersects(&empty));
+    }
+
+    #[test]
+    fn points_on_the_far_edges_are_outside() {
+        let rect = Rect::at(Point { x: 2, y: 2 }, Rectangle::new(3, 2));
+        let inside = [(2, 2), (4, 3), (3, 2)];
+        let outside = [(5, 2), (2, 4), (1, 2), (0, 0)];
+        for (x, y) in inside {
+            assert!(rect.contains_point(Point { x, y }), "({x}, {y})");
+        }
+        for (x, y) in outside {
+            assert!(!rect.contains_point(Point { x, y }), "({x}, {y})");
+        }
+        assert!(!Rect::new(1, 1, 0, 5).contains_point(Point { x: 1, y: 1 }));
+    }
+
+    #[test]
+    #[should_panic(expected = "can't reach past u32::MAX")]
+    fn rects_stay_in_range() {
+        Rect::new(u32::MAX, 0, 1, 1);
+    }
+
+    // checks everything a packing promises, returning how many were placed
+    fn check_packing(container: Rectangle, items: &[Rectangle], placed: &[Option<Rect>]) -> usize {
+        let bounds = Rect::new(0, 0, container.width, container.height);
+        assert_eq!(placed.len(), items.len());
+        let rects: Vec<Rect> = placed.iter().flatten().copied().collect();
+        for (item, rect) in items.iter().zip(placed) {
+            if let Some(rect) = rect {
+                assert_eq!(rect.size(), *item);
+                assert!(bounds.contains(rect), "{rect:?} is outside the container");
+            }
+        }
+        for (i, a) in rects.iter().enumerate() {
+            for b in &rects[i + 1..] {
+                assert!(!a.intersects(b), "{a:?} overlaps {b:?}");
+            }
+        }
+        rects.len()
+    }
+
+    #[test]
+    fn packs_a_perfect_fit() {
+        // 4 columns of 25 and one row of 10 on top fill 100x35 exactly
+        let container = Rectangle::new(100, 35);
+        let mut items = vec![Rectangle::new(25, 25); 4];
+        items.extend(vec![Rectangle::new(10, 10); 10]);
+        let placed = pack(container, &items);
+        assert_eq!(check_packing(container, &items, &placed), items.len());
+    }
+
+    #[test]
+    fn leaves_out_what_doesnt_fit() {
+        let container = Rectangle::new(10, 10);
+        let items = [
+            Rectangle::new(11, 1),
+            Rectangle::new(10, 6),
+            Rectangle::new(10, 6),
+            Rectangle::new(10, 4),
+            Rectangle::new(0, 3),
+        ];
+        let placed = pack(container, &items);
+        assert_eq!(placed[0], None);
+        assert_eq!(placed.iter().filter(|p| p.is_none()).count(), 2);
+        assert_eq!(check_packing(container, &items, &placed), 3);
+    }
+
+    #[test]
+    fn random_packings_never_overlap() {
+        let mut rng = Rng::new(0x9e37_79b9_7f4a_7c15);
+        let mut next = |limit: u32| rng.below(u64::from(limit)) as u32;
+
+        for _ in 0..200 {
+            let container = Rectangle::new(1 + next(200), 1 + next(200));
+            let items: Vec<Rectangle> = (0..next(60))
+                .map(|_| Rectangle::new(next(60), next(60)))
+                .collect();
+            let placed = pack(container, &items);
+            let count = check_packing(container, &items, &placed);
+
+            // the area used can't be more than there is
+            let used: u64 = placed.iter().flatten().map(Rect::area).sum();
+            assert!(used <= container.area());
+            // and anything that fits on its own into an empty container
+            // is placed when it's the only item
+            if let Some(&item) = items.first() {
+                let fits = item.width <= container.width && item.height <= container.height;
+                assert_eq!(pack(container, &[item])[0].is_some(), fits);
+            }
+            assert!(count <= items.len());
+        }
+    }
+}
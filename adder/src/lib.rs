@@ -240,14 +240,63 @@ mod tests {
 }
 */
 
+//The adder library
+/*
+Everything above is kept as notes from working through chapter 11. What the
+crate offers now:
+
+    add_two                the function the integration test checks
+    arithmetic             checked, saturating and wrapping add, sub, mul and
+                           div for every integer type (see arithmetic.rs)
+    bigint                 BigUint and BigInt, integers with no size limit
+                           (see bigint.rs)
+    calc                   the expression calculator that the adder binary
+                           runs (see calc.rs and main.rs)
+    geometry               rectangles that overlap, contain and hold one
+                           another, and packing many into one (see
+                           geometry.rs, which also fixes the backwards
+                           comparison in can_hold above)
+    property               property tests: rules checked against random
+                           values, with the forall! macro (see property.rs)
+    runner                 a test runner with time limits and JUnit reports,
+                           used by the adder-test binary (see runner.rs and
+                           bin/adder-test.rs)
+
+Unit tests live in a tests module next to the code, and integration tests
+in the tests directory use the crate from outside, like any other crate
+would (see tests/integration_test.rs).
+*/
+
+pub mod arithmetic;
+pub mod bigint;
+pub mod calc;
+pub mod geometry;
+pub mod property;
+pub mod runner;
+
+pub use arithmetic::{ArithmeticError, Integer, Operation};
+pub use bigint::{BigInt, BigUint, ParseBigIntError};
+pub use geometry::{Point, Rect, Rectangle};
+
+/// Adds two to `a`.
+pub fn add_two(a: i32) -> i32 {
+    a + 2
+}
+
 #[cfg(test)]
 mod tests {
-    #[test]() {
-        fn it_works() {
-            let result = 2 + 2;
-            assert_eq!(result, 4);
-        }
-    }
-}
+    use super::*;
 
+    #[test]
+    fn it_works() {
+        let result = 2 + 2;
+        assert_eq!(result, 4);
+    }
 
+    #[test]
+    fn it_adds_two() {
+        assert_eq!(4, add_two(2));
+        assert_eq!(102, add_two(100));
+        assert_eq!(0, add_two(-2));
+    }
+}
@@ -0,0 +1,46 @@
+//The adder calculator
+/*
+Reads expressions from standard input, one per line, and prints their
+values (see calc.rs for what a line can say). Errors go to standard error
+with the column they were found at, and the calculator carries on with the
+next line, so a whole script of lines can be piped in:
+
+    $ printf 'let x = 6\nx * 7\n' | adder
+    x = 6
+    42
+
+The '> ' prompt is only shown when typing in a terminal.
+*/
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use adder::calc::Calculator;
+
+fn main() -> io::Result<()> {
+    let stdin = io::stdin();
+    let interactive = stdin.is_terminal();
+    let mut stdout = io::stdout();
+    let mut calc = Calculator::new();
+
+    loop {
+        if interactive {
+            write!(stdout, "> ")?;
+            stdout.flush()?;
+        }
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+
+        match calc.line(&line) {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => writeln!(stdout, "{output}")?,
+            Err(err) => eprintln!("{err}"),
+        }
+    }
+
+    if interactive {
+        writeln!(stdout)?;
+    }
+    Ok(())
+}
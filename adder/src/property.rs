@@ -0,0 +1,646 @@
+//Property tests: checking a rule against many random values
+/*
+A test like 'add_two(2) == 4' checks one value somebody thought of. A
+property test states a rule that should hold for every value, like
+'a + b == b + a', and checks it against a hundred random ones instead,
+which finds the cases nobody thought of: zero, the largest value, an empty
+string.
+
+    forall!(|a: u8, b: u8| wrapping_add(a, b) == wrapping_add(b, a));
+
+Random values that break a rule are usually big and noisy, so when one is
+found it's shrunk: smaller versions of it are tried, and any that still
+break the rule replace it, until none do. What's reported is then a minimal
+counterexample such as 'a = 1, b = 255' instead of two numbers in the
+millions.
+
+The pieces:
+
+    Rng         a small generator seeded with a number, so a run can be
+                repeated exactly by giving it the same seed
+    Arbitrary   how to make a random value of a type and how to shrink one,
+                implemented here for the integer types, bool, char, String,
+                Vec, Option and tuples of up to four values
+    check       runs a property and returns what it found
+    forall!     runs a property inside a test and panics with a report
+
+Each run uses the seed in the ADDER_SEED environment variable, or a fixed
+one if that isn't set, so a failure seen once can be seen again. Values
+start small and are allowed to grow as the run goes on, so simple
+counterexamples tend to turn up first.
+
+A property either returns a bool or a Result<(), String> whose error says
+what went wrong. A panic inside one, from an assert! or an overflow, counts
+as the property not holding, with the panic message as the reason, so it's
+shrunk like any other failure. Those panics aren't printed while checking;
+only the report at the end is.
+
+The module is public so other packages here can use it too, by depending on
+adder or by including this file with #[path].
+*/
+
+use std::cell::Cell;
+use std::env;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+/// A xorshift* random number generator. The same seed gives the same
+/// numbers every time.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // xorshift gets stuck at zero, so mix the seed into a non-zero state
+        let state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ 0x2545_f491_4f6c_dd1d;
+        Rng {
+            state: if state == 0 { 1 } else { state },
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A number from 0 up to, but not including, `n`. Panics if `n` is 0.
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "below(0) has no numbers to choose from");
+        self.next_u64() % n
+    }
+
+    /// True one time in `n`.
+    pub fn one_in(&mut self, n: u64) -> bool {
+        self.below(n) == 0
+    }
+}
+
+/// A type whose values can be made up at random and made smaller.
+pub trait Arbitrary: Clone + fmt::Debug {
+    /// A random value. `size` says roughly how big it may be: how far from
+    /// zero a number, how long a string or vector.
+    fn arbitrary(rng: &mut Rng, size: usize) -> Self;
+
+    /// Values a little simpler than this one, simplest first. Empty when
+    /// there's nothing simpler.
+    fn shrink(&self) -> Vec<Self> {
+        Vec::new()
+    }
+}
+
+macro_rules! arbitrary_unsigned {
+    ($($t:ty)*) => {
+        $(
+            impl Arbitrary for $t {
+                fn arbitrary(rng: &mut Rng, size: usize) -> $t {
+                    match rng.below(10) {
+                        0 => [0, 1, <$t>::MAX][rng.below(3) as usize],
+                        1 | 2 => rng.next_u64() as $t,
+                        _ => rng.below(size as u64 + 1) as $t,
+                    }
+                }
+
+                fn shrink(&self) -> Vec<$t> {
+                    // toward zero: 0, then halfway, three quarters, ...,
+                    // one less, halving the step but rounding it up so
+                    // nothing is skipped
+                    let mut shrunk = Vec::new();
+                    let mut step = *self;
+                    while step > 0 {
+                        shrunk.push(*self - step);
+                        if step == 1 {
+                            break;
+                        }
+                        step = step / 2 + step % 2;
+                    }
+                    shrunk
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! arbitrary_signed {
+    ($($t:ty)*) => {
+        $(
+            impl Arbitrary for $t {
+                fn arbitrary(rng: &mut Rng, size: usize) -> $t {
+                    match rng.below(10) {
+                        0 => [0, 1, -1, <$t>::MIN, <$t>::MAX][rng.below(5) as usize],
+                        1 | 2 => rng.next_u64() as $t,
+                        _ => {
+                            let n = rng.below(size as u64 + 1) as $t;
+                            if rng.one_in(2) { n.wrapping_neg() } else { n }
+                        }
+                    }
+                }
+
+                fn shrink(&self) -> Vec<$t> {
+                    let mut shrunk = Vec::new();
+                    // a positive number is simpler than a negative one
+                    if *self < 0 && *self != <$t>::MIN {
+                        shrunk.push(-*self);
+                    }
+                    // the same steps as for unsigned numbers; they have the
+                    // same sign as the value, so this can't overflow
+                    let mut step = *self;
+                    while step != 0 {
+                        shrunk.push(*self - step);
+                        if step == 1 || step == -1 {
+                            break;
+                        }
+                        step = step / 2 + step % 2;
+                    }
+                    shrunk
+                }
+            }
+        )*
+    };
+}
+
+arbitrary_unsigned!(u8 u16 u32 u64 u128 usize);
+arbitrary_signed!(i8 i16 i32 i64 i128 isize);
+
+impl Arbitrary for bool {
+    fn arbitrary(rng: &mut Rng, _size: usize) -> bool {
+        rng.one_in(2)
+    }
+
+    fn shrink(&self) -> Vec<bool> {
+        if *self {
+            vec![false]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+impl Arbitrary for char {
+    fn arbitrary(rng: &mut Rng, _size: usize) -> char {
+        match rng.below(10) {
+            // mostly letters and digits, with some punctuation and spaces
+            0..=5 => {
+                let plain = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+                char::from(plain[rng.below(plain.len() as u64) as usize])
+            }
+            6 => [' ', '\t', '\n', '.', ',', '-', '"', '\\'][rng.below(8) as usize],
+            // and anything else at all, including letters that aren't ASCII
+            _ => loop {
+                if let Some(c) = char::from_u32(rng.below(0x11_0000) as u32) {
+                    break c;
+                }
+            },
+        }
+    }
+
+    fn shrink(&self) -> Vec<char> {
+        ['a', 'b', 'A', '0', ' ']
+            .into_iter()
+            .take_while(|&c| c != *self)
+            .collect()
+    }
+}
+
+impl<T: Arbitrary> Arbitrary for Vec<T> {
+    fn arbitrary(rng: &mut Rng, size: usize) -> Vec<T> {
+        let len = rng.below(size as u64 + 1) as usize;
+        (0..len).map(|_| T::arbitrary(rng, size)).collect()
+    }
+
+    fn shrink(&self) -> Vec<Vec<T>> {
+        let mut shrunk = Vec::new();
+        // shorter first: nothing, either half, then without each element
+        if !self.is_empty() {
+            shrunk.push(Vec::new());
+        }
+        if self.len() > 1 {
+            let half = self.len() / 2;
+            shrunk.push(self[..half].to_vec());
+            shrunk.push(self[half..].to_vec());
+        }
+        if self.len() > 1 {
+            for i in 0..self.len() {
+                let mut without = self.clone();
+                without.remove(i);
+                shrunk.push(without);
+            }
+        }
+        // then the same length with one element simpler
+        for (i, element) in self.iter().enumerate() {
+            for simpler in element.shrink() {
+                let mut changed = self.clone();
+                changed[i] = simpler;
+                shrunk.push(changed);
+            }
+        }
+        shrunk
+    }
+}
+
+impl Arbitrary for String {
+    fn arbitrary(rng: &mut Rng, size: usize) -> String {
+        Vec::<char>::arbitrary(rng, size).into_iter().collect()
+    }
+
+    fn shrink(&self) -> Vec<String> {
+        let chars: Vec<char> = self.chars().collect();
+        chars
+            .shrink()
+            .into_iter()
+            .map(|chars| chars.into_iter().collect())
+            .collect()
+    }
+}
+
+impl<T: Arbitrary> Arbitrary for Option<T> {
+    fn arbitrary(rng: &mut Rng, size: usize) -> Option<T> {
+        if rng.one_in(5) {
+            None
+        } else {
+            Some(T::arbitrary(rng, size))
+        }
+    }
+
+    fn shrink(&self) -> Vec<Option<T>> {
+        match self {
+            None => Vec::new(),
+            Some(value) => std::iter::once(None)
+                .chain(value.shrink().into_iter().map(Some))
+                .collect(),
+        }
+    }
+}
+
+macro_rules! arbitrary_tuple {
+    ($(($($name:ident $index:tt),+))*) => {
+        $(
+            impl<$($name: Arbitrary),+> Arbitrary for ($($name,)+) {
+                fn arbitrary(rng: &mut Rng, size: usize) -> ($($name,)+) {
+                    ($($name::arbitrary(rng, size),)+)
+                }
+
+                // one value at a time, leaving the others as they are
+                fn shrink(&self) -> Vec<($($name,)+)> {
+                    let mut shrunk = Vec::new();
+                    $(
+                        for simpler in self.$index.shrink() {
+                            let mut changed = self.clone();
+                            changed.$index = simpler;
+                            shrunk.push(changed);
+                        }
+                    )+
+                    shrunk
+                }
+            }
+        )*
+    };
+}
+
+arbitrary_tuple! {
+    (A 0)
+    (A 0, B 1)
+    (A 0, B 1, C 2)
+    (A 0, B 1, C 2, D 3)
+}
+
+/// What a property says about one value.
+pub trait Outcome {
+    /// `Err` with a reason if the property doesn't hold.
+    fn into_result(self) -> Result<(), String>;
+}
+
+impl Outcome for bool {
+    fn into_result(self) -> Result<(), String> {
+        if self {
+            Ok(())
+        } else {
+            Err(String::from("the property returned false"))
+        }
+    }
+}
+
+impl Outcome for Result<(), String> {
+    fn into_result(self) -> Result<(), String> {
+        self
+    }
+}
+
+/// How a property is checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// How many random values to try.
+    pub cases: usize,
+    pub seed: u64,
+    /// How big values may grow by the last case.
+    pub max_size: usize,
+    /// How many times a counterexample may be made smaller before giving up.
+    pub max_shrinks: usize,
+}
+
+impl Config {
+    /// The seed ADDER_SEED holds, if it's set and a number.
+    pub fn seed_from_env() -> Option<u64> {
+        env::var("ADDER_SEED").ok()?.trim().parse().ok()
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            cases: 100,
+            seed: Config::seed_from_env().unwrap_or(0x5eed),
+            max_size: 100,
+            max_shrinks: 1000,
+        }
+    }
+}
+
+/// A value a property doesn't hold for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure<T> {
+    pub seed: u64,
+    /// Which case found it, counting from 1.
+    pub case: usize,
+    /// The value as it was first found.
+    pub original: T,
+    /// The value after shrinking.
+    pub minimal: T,
+    pub shrinks: usize,
+    /// Why the property doesn't hold for `minimal`.
+    pub reason: String,
+}
+
+impl<T: fmt::Debug> fmt::Display for Failure<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "property failed at case {} with seed {} (set ADDER_SEED={} to repeat it)",
+            self.case, self.seed, self.seed
+        )?;
+        writeln!(f, "minimal counterexample: {:?}", self.minimal)?;
+        writeln!(
+            f,
+            "  after {} shrinks from: {:?}",
+            self.shrinks, self.original
+        )?;
+        write!(f, "{}", self.reason)
+    }
+}
+
+/// Tries `property` on `config.cases` random values, and on the first it
+/// doesn't hold for, shrinks that value as far as it will go.
+pub fn check<T, O, P>(config: Config, property: P) -> Result<(), Failure<T>>
+where
+    T: Arbitrary,
+    O: Outcome,
+    P: Fn(T) -> O,
+{
+    let mut rng = Rng::new(config.seed);
+    for case in 0..config.cases {
+        // grow from nothing up to max_size over the run
+        let size = config.max_size * case / config.cases.max(2).saturating_sub(1);
+        let value = T::arbitrary(&mut rng, size);
+        if let Err(reason) = run(&property, value.clone()) {
+            let (minimal, reason, shrinks) = shrink(&config, &property, value.clone(), reason);
+            return Err(Failure {
+                seed: config.seed,
+                case: case + 1,
+                original: value,
+                minimal,
+                shrinks,
+                reason,
+            });
+        }
+    }
+    Ok(())
+}
+
+// Keeps taking the first simpler value that still fails.
+fn shrink<T, O, P>(
+    config: &Config,
+    property: &P,
+    mut value: T,
+    mut reason: String,
+) -> (T, String, usize)
+where
+    T: Arbitrary,
+    O: Outcome,
+    P: Fn(T) -> O,
+{
+    let mut shrinks = 0;
+    'simpler: while shrinks < config.max_shrinks {
+        for candidate in value.shrink() {
+            if let Err(why) = run(property, candidate.clone()) {
+                value = candidate;
+                reason = why;
+                shrinks += 1;
+                continue 'simpler;
+            }
+        }
+        break;
+    }
+    (value, reason, shrinks)
+}
+
+thread_local! {
+    // set while a property runs on this thread, so its panics aren't printed
+    static CHECKING: Cell<bool> = const { Cell::new(false) };
+}
+
+// Runs the property on one value, turning a panic into the reason it failed.
+fn run<T, O, P>(property: &P, value: T) -> Result<(), String>
+where
+    O: Outcome,
+    P: Fn(T) -> O,
+{
+    quiet_panics();
+    CHECKING.with(|c| c.set(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(|| property(value)));
+    CHECKING.with(|c| c.set(false));
+    match result {
+        Ok(outcome) => outcome.into_result(),
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| String::from("Box<dyn Any>"));
+            Err(format!("panicked: {message}"))
+        }
+    }
+}
+
+// Installs, once, a panic hook that says nothing while a property runs and
+// leaves other panics to the hook there was.
+fn quiet_panics() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !CHECKING.with(|c| c.get()) {
+                previous(info);
+            }
+        }));
+    });
+}
+
+/// Like `check`, but panics with the report if the property fails.
+pub fn assert_forall<T, O, P>(config: Config, property: P)
+where
+    T: Arbitrary,
+    O: Outcome,
+    P: Fn(T) -> O,
+{
+    if let Err(failure) = check(config, property) {
+        panic!("{failure}");
+    }
+}
+
+/// Checks a property for random values of each argument, panicking with a
+/// minimal counterexample if it fails. A `Config` can be given first.
+///
+/// ```
+/// use adder::forall;
+/// use adder::property::Config;
+///
+/// forall!(|a: u32, b: u32| a.wrapping_add(b) == b.wrapping_add(a));
+/// forall!(Config { cases: 500, ..Config::default() }, |s: String| {
+///     s.chars().rev().collect::<String>().chars().count() == s.chars().count()
+/// });
+/// ```
+#[macro_export]
+macro_rules! forall {
+    // this one comes first: a closure would also parse as the config
+    (|$($arg:ident: $ty:ty),+ $(,)?| $body:expr) => {
+        $crate::forall!($crate::property::Config::default(), |$($arg: $ty),+| $body)
+    };
+    ($config:expr, |$($arg:ident: $ty:ty),+ $(,)?| $body:expr) => {
+        $crate::property::assert_forall($config, |($($arg,)+): ($($ty,)+)| $body)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config {
+            seed: 0x5eed,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let mut a = Rng::new(7);
+        let mut b = Rng::new(7);
+        let first: Vec<u64> = (0..10).map(|_| a.next_u64()).collect();
+        assert_eq!(first, (0..10).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(
+            first,
+            (0..10).map(|_| Rng::new(8).next_u64()).collect::<Vec<_>>()
+        );
+        assert!(Rng::new(0).next_u64() != 0);
+        assert!((0..1000).all(|_| a.below(6) < 6));
+    }
+
+    #[test]
+    fn integers_shrink_toward_zero() {
+        assert_eq!(100u8.shrink(), vec![0, 50, 75, 87, 93, 96, 98, 99]);
+        assert_eq!(3u8.shrink(), vec![0, 1, 2]);
+        assert_eq!(0u32.shrink(), Vec::<u32>::new());
+        assert_eq!((-9i32).shrink(), vec![9, 0, -4, -6, -7, -8]);
+        assert_eq!(i8::MIN.shrink()[0], 0);
+        assert!(i64::MIN.shrink().iter().all(|&n| n != i64::MIN));
+        assert!(i64::MAX.shrink().iter().all(|&n| n != i64::MAX));
+    }
+
+    #[test]
+    fn finds_the_smallest_number() {
+        // fails for everything from 1000 up; the smallest is 1000 itself
+        let failure = check(config(), |n: u64| n < 1000).unwrap_err();
+        assert_eq!(failure.minimal, 1000);
+        assert!(failure.original >= 1000);
+
+        let failure = check(config(), |n: i32| n > -50).unwrap_err();
+        assert_eq!(failure.minimal, -50);
+    }
+
+    #[test]
+    fn finds_the_shortest_vector() {
+        let failure = check(config(), |v: Vec<u8>| !v.contains(&7)).unwrap_err();
+        assert_eq!(failure.minimal, vec![7]);
+
+        let failure = check(config(), |v: Vec<i32>| v.iter().all(|&n| n < 10)).unwrap_err();
+        assert_eq!(failure.minimal, vec![10]);
+    }
+
+    #[test]
+    fn finds_the_simplest_string() {
+        let failure = check(config(), |s: String| s.chars().count() < 3).unwrap_err();
+        assert_eq!(failure.minimal, "aaa");
+    }
+
+    #[test]
+    fn tuples_shrink_each_value() {
+        let failure = check(config(), |(a, b): (u16, u16)| a < 10 || b < 20).unwrap_err();
+        assert_eq!((failure.minimal.0, failure.minimal.1), (10, 20));
+    }
+
+    #[test]
+    fn reports_seed_value_and_reason() {
+        let failure = check(config(), |n: u8| {
+            if n % 2 == 1 {
+                Err(format!("{n} is odd"))
+            } else {
+                Ok(())
+            }
+        })
+        .unwrap_err();
+        assert_eq!(failure.minimal, 1);
+        assert_eq!(failure.reason, "1 is odd");
+        let report = failure.to_string();
+        assert!(report.contains("seed 24301"), "{report}");
+        assert!(report.contains("minimal counterexample: 1\n"), "{report}");
+    }
+
+    #[test]
+    fn panics_are_failures_that_shrink() {
+        let failure = check(config(), |v: Vec<u8>| {
+            assert!(v.len() < 3, "{} is too long", v.len());
+            true
+        })
+        .unwrap_err();
+        assert_eq!(failure.minimal, vec![0, 0, 0]);
+        assert_eq!(failure.reason, "panicked: 3 is too long");
+
+        let failure = check(config(), |(a, b): (u8, u8)| {
+            let _ = a.checked_add(b).expect("overflow");
+            true
+        })
+        .unwrap_err();
+        assert_eq!(failure.minimal.0 as u16 + failure.minimal.1 as u16, 256);
+        assert_eq!(failure.reason, "panicked: overflow");
+    }
+
+    #[test]
+    fn passing_properties_pass() {
+        assert_eq!(check(config(), |(a, b): (u8, u8)| a.max(b) >= a), Ok(()));
+        forall!(|v: Vec<u8>| v.len() < 1000);
+        forall!(config(), |a: i8, b: i8, c: i8| a
+            .wrapping_add(b)
+            .wrapping_add(c)
+            == a.wrapping_add(b.wrapping_add(c)));
+    }
+
+    #[test]
+    #[should_panic(expected = "minimal counterexample: (0, 1)")]
+    fn forall_panics_with_the_report() {
+        forall!(|a: u32, b: u32| a >= b);
+    }
+}
@@ -0,0 +1,768 @@
+//A test runner of our own
+/*
+'cargo test' builds a harness around every #[test] function: it runs them
+on several threads, skips #[ignore]d ones, takes a filter, and has options
+like --test-threads and --show-output. This module is a small harness of
+the same kind that a binary can use, for places that need something the
+built-in one doesn't give: a time limit for each test, a summary of which
+tests were slow, and a JUnit XML report, which is what CI tools read.
+
+Tests are registered with the tests! macro. Attributes on a test become
+calls on its Test, so the ones it knows are the methods Test has:
+
+    let tests = adder::tests! {
+        fn adds_two() {
+            assert_eq!(add_two(2), 4);
+        }
+
+        #[ignore]
+        #[timeout(Duration::from_secs(30))]
+        fn the_slow_one() { ... }
+
+        #[should_panic]
+        fn divides_by_zero() { ... }
+    };
+
+A pool of --test-threads workers takes tests off a shared queue. Each test
+runs on a thread of its own, and its worker waits for it only as long as
+the test's timeout. A test that runs too long can't be stopped from outside
+its thread, so it's reported as timed out and left running, and the worker
+moves on; the process ends when the report is done, taking any such
+threads with it. Until then more tests than --test-threads may be running,
+and the summary says so when that happened.
+
+A failing test panics, as in 'cargo test'. The panic message is kept for
+the report instead of being printed as it happens, and the same goes for
+panics on purpose in #[should_panic] tests. What tests print isn't
+captured, so it shows up as it happens, and --show-output is accepted but
+changes nothing.
+
+The options are the familiar ones:
+
+    adder-test [FILTER]... [--exact] [--ignored | --include-ignored]
+               [--test-threads N] [--timeout SECONDS] [--junit PATH]
+               [--show-output]
+*/
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, Once};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a test may run unless it says otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How many of the slowest tests the summary lists.
+const SLOWEST: usize = 5;
+
+/// A test function and how to run it.
+#[derive(Debug, Clone)]
+pub struct Test {
+    pub name: String,
+    pub run: fn(),
+    pub ignored: bool,
+    pub should_panic: bool,
+    /// `None` for the runner's default.
+    pub timeout: Option<Duration>,
+}
+
+impl Test {
+    pub fn new(name: &str, run: fn()) -> Test {
+        Test {
+            name: String::from(name),
+            run,
+            ignored: false,
+            should_panic: false,
+            timeout: None,
+        }
+    }
+
+    /// Only run with --ignored or --include-ignored.
+    pub fn ignore(mut self) -> Test {
+        self.ignored = true;
+        self
+    }
+
+    /// Passes only if the test panics.
+    pub fn should_panic(mut self) -> Test {
+        self.should_panic = true;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Test {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+/// Registers test functions, returning a `Vec<Test>` in the order they're
+/// written. Each attribute, such as `#[ignore]` or `#[timeout(d)]`, calls
+/// the method of `Test` with that name, except doc comments, which stay on
+/// the function.
+#[macro_export]
+macro_rules! tests {
+    // one test at a time, sorting its attributes into docs and calls
+    (@test [$($doc:tt)*] [$($call:tt)*] $name:ident $body:block; [doc = $text:expr] $($rest:tt)*) => {
+        $crate::tests!(@test [$($doc)* #[doc = $text]] [$($call)*] $name $body; $($rest)*)
+    };
+    (@test [$($doc:tt)*] [$($call:tt)*] $name:ident $body:block; [$attr:ident $(($($arg:expr),*))?] $($rest:tt)*) => {
+        $crate::tests!(@test [$($doc)*] [$($call)* .$attr($($($arg),*)?)] $name $body; $($rest)*)
+    };
+    (@test [$($doc:tt)*] [$($call:tt)*] $name:ident $body:block;) => {{
+        $($doc)*
+        fn $name() $body
+        $crate::runner::Test::new(stringify!($name), $name) $($call)*
+    }};
+    ($($(#[$($attr:tt)*])* fn $name:ident() $body:block)*) => {
+        vec![$($crate::tests!(@test [] [] $name $body; $([$($attr)*])*)),*]
+    };
+}
+
+/// Which tests to run with respect to `#[ignore]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Ignored {
+    /// Skip ignored tests, the default.
+    #[default]
+    Skip,
+    /// Run only the ignored tests (--ignored).
+    Only,
+    /// Run everything (--include-ignored).
+    Include,
+}
+
+/// What the runner was asked to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    /// Run only tests whose names contain one of these, or all if empty.
+    pub filters: Vec<String>,
+    /// The filters have to match whole names.
+    pub exact: bool,
+    pub ignored: Ignored,
+    pub threads: usize,
+    pub timeout: Duration,
+    /// Where to write a JUnit report, if anywhere.
+    pub junit: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            filters: Vec::new(),
+            exact: false,
+            ignored: Ignored::Skip,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            timeout: DEFAULT_TIMEOUT,
+            junit: None,
+        }
+    }
+}
+
+impl Options {
+    /// Reads the options from command line arguments, the first of which
+    /// is the program's name.
+    pub fn build(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        args.next();
+        let mut options = Options::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--exact" => options.exact = true,
+                "--ignored" => options.ignored = Ignored::Only,
+                "--include-ignored" => options.ignored = Ignored::Include,
+                "--test-threads" => {
+                    options.threads = match args.next().map(|n| n.parse()) {
+                        Some(Ok(n)) if n > 0 => n,
+                        _ => return Err(String::from("--test-threads needs a positive number")),
+                    }
+                }
+                "--timeout" => {
+                    // too many seconds for a Duration is as bad as none
+                    let timeout = match args.next().map(|s| s.parse::<f64>()) {
+                        Some(Ok(s)) if s > 0.0 => Duration::try_from_secs_f64(s).ok(),
+                        _ => None,
+                    };
+                    options.timeout = match timeout {
+                        Some(timeout) => timeout,
+                        None => return Err(String::from("--timeout needs a number of seconds")),
+                    }
+                }
+                // output isn't captured, so it's always shown already
+                "--show-output" => {}
+                "--junit" => match args.next() {
+                    Some(path) => options.junit = Some(PathBuf::from(path)),
+                    None => return Err(String::from("--junit needs a file to write")),
+                },
+                flag if flag.starts_with("--") => return Err(format!("unknown option {flag}")),
+                _ => options.filters.push(arg),
+            }
+        }
+        Ok(options)
+    }
+
+    fn selects(&self, test: &Test) -> bool {
+        let named = self.filters.is_empty()
+            || self.filters.iter().any(|filter| {
+                if self.exact {
+                    test.name == *filter
+                } else {
+                    test.name.contains(filter.as_str())
+                }
+            });
+        named && (test.ignored || self.ignored != Ignored::Only)
+    }
+}
+
+/// How a test went.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    /// Why it failed: the panic message, or why not panicking was wrong.
+    Failed(String),
+    Ignored,
+    TimedOut(Duration),
+}
+
+/// One test's outcome and how long it took.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestResult {
+    pub name: String,
+    pub outcome: Outcome,
+    pub duration: Duration,
+}
+
+/// Everything a run found, in the order the tests were registered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub results: Vec<TestResult>,
+    /// How many tests the filters left out.
+    pub filtered_out: usize,
+    pub duration: Duration,
+}
+
+impl Report {
+    fn count(&self, matches: fn(&Outcome) -> bool) -> usize {
+        self.results.iter().filter(|r| matches(&r.outcome)).count()
+    }
+
+    pub fn passed(&self) -> usize {
+        self.count(|o| *o == Outcome::Passed)
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(|o| matches!(o, Outcome::Failed(_)))
+    }
+
+    pub fn ignored(&self) -> usize {
+        self.count(|o| *o == Outcome::Ignored)
+    }
+
+    pub fn timed_out(&self) -> usize {
+        self.count(|o| matches!(o, Outcome::TimedOut(_)))
+    }
+
+    /// True if nothing failed or timed out.
+    pub fn success(&self) -> bool {
+        self.failed() == 0 && self.timed_out() == 0
+    }
+
+    /// The failures, the totals and the slowest tests, like the end of
+    /// 'cargo test' output.
+    pub fn write_summary(&self, out: &mut impl Write) -> io::Result<()> {
+        let failures: Vec<&TestResult> = self
+            .results
+            .iter()
+            .filter(|r| matches!(r.outcome, Outcome::Failed(_) | Outcome::TimedOut(_)))
+            .collect();
+        if !failures.is_empty() {
+            writeln!(out, "\nfailures:")?;
+            for result in &failures {
+                match &result.outcome {
+                    Outcome::Failed(message) => {
+                        writeln!(out, "\n---- {} ----\n{message}", result.name)?
+                    }
+                    Outcome::TimedOut(limit) => writeln!(
+                        out,
+                        "\n---- {} ----\ntimed out after {}",
+                        result.name,
+                        seconds(*limit)
+                    )?,
+                    _ => {}
+                }
+            }
+        }
+
+        let mut slowest: Vec<&TestResult> = self
+            .results
+            .iter()
+            .filter(|r| r.outcome != Outcome::Ignored)
+            .collect();
+        slowest.sort_by_key(|r| std::cmp::Reverse(r.duration));
+        if !slowest.is_empty() {
+            writeln!(out, "\nslowest tests:")?;
+            for result in slowest.iter().take(SLOWEST) {
+                writeln!(out, "    {:>9}  {}", seconds(result.duration), result.name)?;
+            }
+        }
+
+        // their threads can't be stopped, so they took up a thread each on
+        // top of --test-threads until the end
+        let abandoned = self.timed_out();
+        if abandoned > 0 {
+            writeln!(
+                out,
+                "\nnote: {abandoned} timed out test{} kept running alongside the others, \
+                 so more tests than --test-threads may have run at once",
+                if abandoned == 1 { "" } else { "s" }
+            )?;
+        }
+
+        writeln!(
+            out,
+            "\ntest result: {}. {} passed; {} failed; {} timed out; {} ignored; {} filtered out; finished in {}",
+            if self.success() { "ok" } else { "FAILED" },
+            self.passed(),
+            self.failed(),
+            self.timed_out(),
+            self.ignored(),
+            self.filtered_out,
+            seconds(self.duration),
+        )
+    }
+
+    /// The run as a JUnit XML report, one testsuite called `suite`.
+    pub fn to_junit(&self, suite: &str) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let totals = format!(
+            "tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\"",
+            self.results.len(),
+            self.failed(),
+            self.timed_out(),
+            self.ignored(),
+            self.duration.as_secs_f64()
+        );
+        let suite = escape(suite);
+        writeln!(xml, "<testsuites name=\"{suite}\" {totals}>").unwrap();
+        writeln!(xml, "  <testsuite name=\"{suite}\" {totals}>").unwrap();
+        for result in &self.results {
+            write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{suite}\" time=\"{:.3}\"",
+                escape(&result.name),
+                result.duration.as_secs_f64()
+            )
+            .unwrap();
+            match &result.outcome {
+                Outcome::Passed => xml.push_str("/>\n"),
+                Outcome::Ignored => xml.push_str(">\n      <skipped/>\n    </testcase>\n"),
+                Outcome::Failed(message) => {
+                    let first = message.lines().next().unwrap_or_default();
+                    writeln!(
+                        xml,
+                        ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>",
+                        escape(first),
+                        escape(message)
+                    )
+                    .unwrap();
+                }
+                Outcome::TimedOut(limit) => {
+                    writeln!(
+                        xml,
+                        ">\n      <error type=\"timeout\" message=\"timed out after {}\"/>\n    </testcase>",
+                        seconds(*limit)
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+}
+
+fn seconds(duration: Duration) -> String {
+    format!("{:.3}s", duration.as_secs_f64())
+}
+
+// Makes text safe inside XML attributes and elements. Control characters
+// other than tabs and newlines aren't allowed in XML at all, so they're
+// written as '?'.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => escaped.push('?'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+thread_local! {
+    // set on threads running a test, so the panic hook knows to keep quiet
+    static PANIC_MESSAGE: RefCell<Option<String>> = const { RefCell::new(None) };
+    static IN_TEST: RefCell<bool> = const { RefCell::new(false) };
+}
+
+// Installs, once, a panic hook that keeps the message of a panic in a test
+// thread for the report and leaves other panics to the hook there was.
+fn capture_panics() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if IN_TEST.with(|t| *t.borrow()) {
+                let location = info
+                    .location()
+                    .map(|l| format!(" at {}:{}", l.file(), l.line()))
+                    .unwrap_or_default();
+                let payload = info.payload();
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| String::from("Box<dyn Any>"));
+                PANIC_MESSAGE
+                    .with(|m| *m.borrow_mut() = Some(format!("panicked{location}:\n{message}")));
+            } else {
+                previous(info);
+            }
+        }));
+    });
+}
+
+// Runs one test on a thread of its own, waiting at most `timeout`.
+fn run_one(test: &Test, timeout: Duration) -> (Outcome, Duration) {
+    let (send, receive) = mpsc::channel();
+    let run = test.run;
+    let should_panic = test.should_panic;
+    let start = Instant::now();
+    let spawned = thread::Builder::new()
+        .name(test.name.clone())
+        .spawn(move || {
+            IN_TEST.with(|t| *t.borrow_mut() = true);
+            let panicked = panic::catch_unwind(AssertUnwindSafe(run)).is_err();
+            let message = PANIC_MESSAGE.with(|m| m.borrow_mut().take());
+            let outcome = match (panicked, should_panic) {
+                (false, false) | (true, true) => Outcome::Passed,
+                (true, false) => Outcome::Failed(message.unwrap_or_default()),
+                (false, true) => Outcome::Failed(String::from("the test didn't panic as expected")),
+            };
+            // the runner may have given up waiting
+            let _ = send.send(outcome);
+        });
+    if let Err(err) = spawned {
+        return (
+            Outcome::Failed(format!("couldn't start a thread: {err}")),
+            start.elapsed(),
+        );
+    }
+    match receive.recv_timeout(timeout) {
+        Ok(outcome) => (outcome, start.elapsed()),
+        Err(_) => (Outcome::TimedOut(timeout), start.elapsed()),
+    }
+}
+
+/// Runs the tests `options` selects, writing a line to `out` as each one
+/// finishes, and returns the report.
+pub fn run(tests: &[Test], options: &Options, out: &mut impl Write) -> io::Result<Report> {
+    capture_panics();
+    let start = Instant::now();
+
+    let selected: Vec<(usize, &Test)> = tests
+        .iter()
+        .filter(|test| options.selects(test))
+        .enumerate()
+        .collect();
+    let filtered_out = tests.len() - selected.len();
+    writeln!(
+        out,
+        "\nrunning {} test{}",
+        selected.len(),
+        if selected.len() == 1 { "" } else { "s" }
+    )?;
+
+    let queue = Arc::new(Mutex::new(
+        selected.iter().copied().collect::<VecDeque<_>>(),
+    ));
+    let mut results: Vec<Option<TestResult>> = vec![None; selected.len()];
+    let (send, receive) = mpsc::channel();
+
+    thread::scope(|scope| -> io::Result<()> {
+        for _ in 0..options.threads.min(selected.len()) {
+            let queue = Arc::clone(&queue);
+            let send = send.clone();
+            scope.spawn(move || loop {
+                let next = queue.lock().unwrap().pop_front();
+                let Some((index, test)) = next else { break };
+                let (outcome, duration) = if test.ignored && options.ignored == Ignored::Skip {
+                    (Outcome::Ignored, Duration::ZERO)
+                } else {
+                    run_one(test, test.timeout.unwrap_or(options.timeout))
+                };
+                let result = TestResult {
+                    name: test.name.clone(),
+                    outcome,
+                    duration,
+                };
+                if send.send((index, result)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(send);
+
+        for (index, result) in receive {
+            let status = match result.outcome {
+                Outcome::Passed => "ok",
+                Outcome::Failed(_) => "FAILED",
+                Outcome::Ignored => "ignored",
+                Outcome::TimedOut(_) => "TIMED OUT",
+            };
+            writeln!(out, "test {} ... {status}", result.name)?;
+            results[index] = Some(result);
+        }
+        Ok(())
+    })?;
+
+    let report = Report {
+        results: results.into_iter().flatten().collect(),
+        filtered_out,
+        duration: start.elapsed(),
+    };
+    report.write_summary(out)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(args: &[&str]) -> Result<Options, String> {
+        Options::build(
+            std::iter::once("adder-test")
+                .chain(args.iter().copied())
+                .map(String::from),
+        )
+    }
+
+    fn sample() -> Vec<Test> {
+        crate::tests! {
+            fn adds() {
+                assert_eq!(crate::add_two(2), 4);
+            }
+
+            fn fails() {
+                assert_eq!(crate::add_two(2), 5, "two & two <is> four");
+            }
+
+            #[ignore]
+            fn slow() {
+                thread::sleep(Duration::from_millis(10));
+            }
+
+            /// Panics, which is what it should do.
+            #[should_panic]
+            fn panics() {
+                panic!("on purpose");
+            }
+
+            #[should_panic]
+            fn doesnt_panic() {}
+
+            #[timeout(Duration::from_millis(50))]
+            fn hangs() {
+                thread::sleep(Duration::from_secs(5));
+            }
+        }
+    }
+
+    fn names(report: &Report) -> Vec<&str> {
+        report.results.iter().map(|r| r.name.as_str()).collect()
+    }
+
+    #[test]
+    fn registers_with_attributes() {
+        let tests = sample();
+        let names: Vec<&str> = tests.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["adds", "fails", "slow", "panics", "doesnt_panic", "hangs"]
+        );
+        assert!(tests[2].ignored && !tests[0].ignored);
+        assert!(tests[3].should_panic);
+        assert_eq!(tests[5].timeout, Some(Duration::from_millis(50)));
+    }
+
+    #[test]
+    fn reads_options() {
+        let parsed = options(&[
+            "add",
+            "--exact",
+            "--include-ignored",
+            "--test-threads",
+            "3",
+            "--timeout",
+            "1.5",
+            "--junit",
+            "out.xml",
+            "x",
+        ])
+        .unwrap();
+        assert_eq!(parsed.filters, ["add", "x"]);
+        assert!(parsed.exact);
+        assert_eq!(parsed.ignored, Ignored::Include);
+        assert_eq!(parsed.threads, 3);
+        assert_eq!(parsed.timeout, Duration::from_millis(1500));
+        assert_eq!(parsed.junit, Some(PathBuf::from("out.xml")));
+
+        assert_eq!(options(&[]).unwrap().ignored, Ignored::Skip);
+        assert!(options(&["--test-threads", "0"]).is_err());
+        assert!(options(&["--timeout"]).is_err());
+        for seconds in ["1e20", "0", "-1", "inf", "NaN"] {
+            assert_eq!(
+                options(&["--timeout", seconds]),
+                Err(String::from("--timeout needs a number of seconds")),
+                "{seconds}"
+            );
+        }
+        assert_eq!(options(&["--show-output"]), options(&[]));
+        assert_eq!(
+            options(&["--format", "json"]),
+            Err(String::from("unknown option --format"))
+        );
+    }
+
+    #[test]
+    fn runs_everything_and_keeps_order() {
+        let parsed = options(&["--test-threads", "4"]).unwrap();
+        let mut out = Vec::new();
+        let report = run(&sample(), &parsed, &mut out).unwrap();
+        assert_eq!(
+            names(&report),
+            ["adds", "fails", "slow", "panics", "doesnt_panic", "hangs"]
+        );
+
+        let outcomes: Vec<&Outcome> = report.results.iter().map(|r| &r.outcome).collect();
+        assert_eq!(outcomes[0], &Outcome::Passed);
+        assert!(matches!(outcomes[1], Outcome::Failed(m) if m.contains("two & two <is> four")));
+        assert_eq!(outcomes[2], &Outcome::Ignored);
+        assert_eq!(outcomes[3], &Outcome::Passed);
+        assert_eq!(
+            outcomes[4],
+            &Outcome::Failed(String::from("the test didn't panic as expected"))
+        );
+        assert_eq!(outcomes[5], &Outcome::TimedOut(Duration::from_millis(50)));
+        assert!(report.results[5].duration < Duration::from_secs(5));
+        assert!(!report.success());
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("\nrunning 6 tests\n"), "{out}");
+        assert!(out.contains("test hangs ... TIMED OUT\n"), "{out}");
+        assert!(
+            out.contains("---- fails ----\npanicked at src/runner.rs:"),
+            "{out}"
+        );
+        assert!(out.contains("slowest tests:"), "{out}");
+        assert!(
+            out.contains("note: 1 timed out test kept running alongside the others"),
+            "{out}"
+        );
+        assert!(
+            out.contains("test result: FAILED. 2 passed; 2 failed; 1 timed out; 1 ignored; 0 filtered out; finished in "),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn filters_and_ignored() {
+        let mut out = Vec::new();
+        let report = run(&sample(), &options(&["panic"]).unwrap(), &mut out).unwrap();
+        assert_eq!(names(&report), ["panics", "doesnt_panic"]);
+        assert_eq!(report.filtered_out, 4);
+
+        let report = run(
+            &sample(),
+            &options(&["--exact", "adds", "panics"]).unwrap(),
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(names(&report), ["adds", "panics"]);
+        assert!(report.success());
+
+        let report = run(&sample(), &options(&["--ignored"]).unwrap(), &mut out).unwrap();
+        assert_eq!(names(&report), ["slow"]);
+        assert_eq!(report.results[0].outcome, Outcome::Passed);
+
+        let report = run(
+            &sample(),
+            &options(&["--include-ignored", "slow"]).unwrap(),
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(report.passed(), 1);
+        // nothing timed out, so nothing was left running
+        assert!(!String::from_utf8(out).unwrap().contains("note:"));
+    }
+
+    #[test]
+    fn junit_report() {
+        let ms = Duration::from_millis;
+        let report = Report {
+            results: vec![
+                TestResult {
+                    name: String::from("adds"),
+                    outcome: Outcome::Passed,
+                    duration: ms(1),
+                },
+                TestResult {
+                    name: String::from("fails"),
+                    outcome: Outcome::Failed(String::from(
+                        "panicked at x.rs:1:\n\"a\" < b & c\u{1}",
+                    )),
+                    duration: ms(20),
+                },
+                TestResult {
+                    name: String::from("slow"),
+                    outcome: Outcome::Ignored,
+                    duration: ms(0),
+                },
+                TestResult {
+                    name: String::from("hangs"),
+                    outcome: Outcome::TimedOut(ms(500)),
+                    duration: ms(501),
+                },
+            ],
+            filtered_out: 0,
+            duration: ms(530),
+        };
+        let expected = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="adder" tests="4" failures="1" errors="1" skipped="1" time="0.530">
+  <testsuite name="adder" tests="4" failures="1" errors="1" skipped="1" time="0.530">
+    <testcase name="adds" classname="adder" time="0.001"/>
+    <testcase name="fails" classname="adder" time="0.020">
+      <failure message="panicked at x.rs:1:">panicked at x.rs:1:
+&quot;a&quot; &lt; b &amp; c?</failure>
+    </testcase>
+    <testcase name="slow" classname="adder" time="0.000">
+      <skipped/>
+    </testcase>
+    <testcase name="hangs" classname="adder" time="0.501">
+      <error type="timeout" message="timed out after 0.500s"/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#;
+        assert_eq!(report.to_junit("adder"), expected);
+    }
+}
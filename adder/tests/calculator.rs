@@ -0,0 +1,117 @@
+//Driving the adder calculator binary
+/*
+Cargo builds the binaries of a package before its integration tests and
+tells them where with the CARGO_BIN_EXE_<name> environment variable, so a
+test can run the real program, pipe a script into it and look at what comes
+out, just like a user at a shell would.
+*/
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+mod common;
+
+use common::TempDir;
+
+// runs the calculator with `script` as its input, returning what it wrote
+// to standard output and standard error
+fn run(script: &str) -> (String, String) {
+    common::setup();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_adder"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("the adder binary should start");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(script.as_bytes())
+        .unwrap();
+    text(child.wait_with_output().unwrap())
+}
+
+// runs the calculator with a file as its input, like 'adder < script.txt'
+fn run_file(script: &Path) -> (String, String) {
+    common::setup();
+    let output = Command::new(env!("CARGO_BIN_EXE_adder"))
+        .stdin(File::open(script).unwrap())
+        .output()
+        .expect("the adder binary should start");
+    text(output)
+}
+
+fn text(output: Output) -> (String, String) {
+    assert!(output.status.success(), "exited with {}", output.status);
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn evaluates_each_line() {
+    let (stdout, stderr) = run("2 + 3 * (4 - 1)\n\n2 ^ 10\n-7 / 2\n");
+    assert_eq!(stdout, "11\n1024\n-3\n");
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn keeps_variables_between_lines() {
+    let (stdout, stderr) = run("let width = 12\nlet height = width / 4\nwidth * height\n");
+    assert_eq!(stdout, "width = 12\nheight = 3\n36\n");
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn reports_errors_and_carries_on() {
+    let (stdout, stderr) = run("1 / 0\n2 +\nsize * 2\n9223372036854775807 * 2\n4 * 4\n");
+    assert_eq!(stdout, "16\n");
+    assert_eq!(
+        stderr,
+        "error at column 3: division by zero\n\
+         error at column 4: expected a number, a name or '(', found the end of the line\n\
+         error at column 1: 'size' isn't defined\n\
+         error at column 21: multiplication overflowed\n"
+    );
+}
+
+#[test]
+fn lists_the_history() {
+    let (stdout, stderr) = run("let x = 2\nx + 1\nx / 0\n:history\n:nope\n");
+    assert_eq!(stdout, "x = 2\n3\n1  let x = 2\n2  x + 1\n3  x / 0\n");
+    assert_eq!(
+        stderr,
+        "error at column 3: division by zero\nerror at column 1: unknown command ':nope'\n"
+    );
+}
+
+#[test]
+fn handles_a_last_line_without_a_newline() {
+    let (stdout, _) = run("6 * 7");
+    assert_eq!(stdout, "42\n");
+}
+
+#[test]
+fn a_whole_session_matches_the_golden_file() {
+    let dir = TempDir::new("session");
+    let script = dir.write(
+        "session.txt",
+        "let price = 1299\n\
+         let quantity = 3\n\
+         price * quantity\n\
+         price * quantity / 100\n\
+         price * quantity % 100\n\
+         2 ^ 62 + 2 ^ 62\n\
+         (price - \n\
+         :history\n",
+    );
+    let (stdout, stderr) = run_file(&script);
+    common::assert_golden(
+        "calculator_session.txt",
+        &format!("{stdout}--- stderr ---\n{stderr}"),
+    );
+}
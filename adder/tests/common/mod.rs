@@ -0,0 +1,188 @@
+//Shared setup for the integration tests
+/*
+Each file in tests/ is compiled as its own crate, so code they share can't
+go in a file like tests/common.rs: cargo would treat that as one more test
+crate and run it. A directory with a mod.rs is left alone, and each test
+crate that needs it says 'mod common;'. A package without its own copy can
+borrow this one with '#[path = "../../adder/tests/common/mod.rs"] mod common;'.
+
+It has three helpers:
+
+    TempDir         a scratch directory for files a test reads or writes,
+                    removed again when the test is done, even if it fails
+
+    assert_golden   compares output with a file in tests/golden. When they
+                    differ, the output is written next to it with '.actual'
+                    added to the name, so 'diff' shows what changed, and
+                    when the change is right, that file can replace the
+                    golden one. Setting UPDATE_GOLDEN=1 does that for every
+                    test at once.
+
+    cases!          writes one #[test] per row of a table, all checked by
+                    the same function, so a failing row is named in the
+                    test output instead of hiding among the others
+
+Not every test crate uses every helper, which would make the rest warn as
+unused, hence the allows below.
+*/
+#![allow(dead_code, unused_macros)]
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Setup that every integration test needs before it runs.
+pub fn setup() {
+    // nothing to prepare yet, but every test already calls this, so
+    // anything added here runs for all of them
+}
+
+/// A directory under the system temp directory, removed when dropped.
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// A new empty directory. `name` only helps tell them apart; tests
+    /// running at the same time each get their own.
+    pub fn new(name: &str) -> TempDir {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let n = COUNT.fetch_add(1, Ordering::Relaxed);
+        let path = env::temp_dir().join(format!("adder-test-{name}-{}-{n}", process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).expect("the temp directory should be writable");
+        TempDir { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The path of `relative` inside the directory.
+    pub fn join(&self, relative: &str) -> PathBuf {
+        self.path.join(relative)
+    }
+
+    /// Writes a file, making any directories it needs, and returns its path.
+    pub fn write(&self, relative: &str, contents: &str) -> PathBuf {
+        let path = self.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    pub fn read(&self, relative: &str) -> String {
+        fs::read_to_string(self.join(relative)).unwrap()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Where the golden files are: tests/golden in the package being tested.
+pub fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+}
+
+/// Panics unless `actual` is the same as the file `name` in tests/golden.
+pub fn assert_golden(name: &str, actual: &str) {
+    if let Err(message) = check_golden(&golden_dir().join(name), actual) {
+        panic!("{message}");
+    }
+}
+
+/// Compares `actual` with the file at `golden`. If they differ, writes
+/// `actual` to the same path with ".actual" added and says where, unless
+/// UPDATE_GOLDEN is set, when the golden file is replaced instead.
+pub fn check_golden(golden: &Path, actual: &str) -> Result<(), String> {
+    let actual_path = actual_path(golden);
+    let expected = fs::read_to_string(golden).ok();
+    if expected.as_deref() == Some(actual) {
+        // left over from an earlier failure
+        let _ = fs::remove_file(&actual_path);
+        return Ok(());
+    }
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(golden, actual).map_err(|e| format!("{}: {e}", golden.display()))?;
+        let _ = fs::remove_file(&actual_path);
+        return Ok(());
+    }
+
+    fs::write(&actual_path, actual).map_err(|e| format!("{}: {e}", actual_path.display()))?;
+    Err(match expected {
+        Some(expected) => format!(
+            "output differs from {golden} at line {line}; it was written to {actual}\n\
+             (run 'diff {golden} {actual}', or UPDATE_GOLDEN=1 to accept it)",
+            golden = golden.display(),
+            actual = actual_path.display(),
+            line = first_difference(&expected, actual),
+        ),
+        None => format!(
+            "there's no golden file {}; the output was written to {}",
+            golden.display(),
+            actual_path.display()
+        ),
+    })
+}
+
+fn actual_path(golden: &Path) -> PathBuf {
+    let mut name = golden.as_os_str().to_owned();
+    name.push(".actual");
+    PathBuf::from(name)
+}
+
+// the first line number, counting from 1, where the two texts differ
+fn first_difference(expected: &str, actual: &str) -> usize {
+    let mut expected = expected.lines();
+    let mut actual = actual.lines();
+    let mut line = 1;
+    while let (Some(e), Some(a)) = (expected.next(), actual.next()) {
+        if e != a {
+            break;
+        }
+        line += 1;
+    }
+    line
+}
+
+/// Writes one test per row, each calling the check function with that
+/// row's values after `setup`:
+///
+/// ```ignore
+/// common::cases! {
+///     fn check(input: i32, expected: i32) {
+///         assert_eq!(adder::add_two(input), expected);
+///     }
+///     zero: (0, 2),
+///     negative: (-5, -3),
+/// }
+/// ```
+macro_rules! cases {
+    (
+        fn $check:ident($($arg:ident: $ty:ty),* $(,)?) $body:block
+        $($name:ident: ($($value:expr),* $(,)?)),* $(,)?
+    ) => {
+        fn $check($($arg: $ty),*) $body
+
+        $(
+            #[test]
+            fn $name() {
+                $crate::common::setup();
+                $check($($value),*);
+            }
+        )*
+    };
+}
+
+#[allow(unused_imports)]
+pub(crate) use cases;
@@ -0,0 +1,70 @@
+//Tests of the shared test harness in tests/common
+/*
+The helpers in common are only any use if they fail when they should, so
+they get tests of their own.
+*/
+
+use std::fs;
+
+mod common;
+
+use common::{check_golden, TempDir};
+
+#[test]
+fn temp_dirs_are_separate_and_cleaned_up() {
+    let path;
+    {
+        let a = TempDir::new("harness");
+        let b = TempDir::new("harness");
+        assert_ne!(a.path(), b.path());
+
+        let file = a.write("nested/dir/notes.txt", "hello");
+        assert!(file.starts_with(a.path()));
+        assert_eq!(a.read("nested/dir/notes.txt"), "hello");
+        assert!(!b.join("nested").exists());
+        path = a.path().to_path_buf();
+    }
+    assert!(!path.exists());
+}
+
+#[test]
+fn golden_mismatch_writes_an_actual_file() {
+    let dir = TempDir::new("golden");
+    let golden = dir.write("out.txt", "one\ntwo\nthree\n");
+    let actual = dir.join("out.txt.actual");
+
+    let message = check_golden(&golden, "one\n2\nthree\n").unwrap_err();
+    assert!(message.contains("at line 2"), "{message}");
+    assert_eq!(dir.read("out.txt.actual"), "one\n2\nthree\n");
+    // the golden file itself is left alone
+    assert_eq!(dir.read("out.txt"), "one\ntwo\nthree\n");
+
+    // once the output is right again, the .actual file goes away
+    assert_eq!(check_golden(&golden, "one\ntwo\nthree\n"), Ok(()));
+    assert!(!actual.exists());
+}
+
+#[test]
+fn missing_golden_file_is_a_failure() {
+    let dir = TempDir::new("missing");
+    let golden = dir.join("new.txt");
+    let message = check_golden(&golden, "output\n").unwrap_err();
+    assert!(message.contains("there's no golden file"), "{message}");
+    assert_eq!(
+        fs::read_to_string(dir.join("new.txt.actual")).unwrap(),
+        "output\n"
+    );
+}
+
+mod table {
+    use super::common;
+
+    common::cases! {
+        fn check(text: &str, words: usize) {
+            assert_eq!(text.split_whitespace().count(), words);
+        }
+        empty: ("", 0),
+        one: ("adder", 1),
+        spaces: ("  two   words ", 2),
+    }
+}
@@ -0,0 +1,67 @@
+//Integration tests for the adder crate
+/*
+These use adder the way another crate would: only its public API is in
+reach, through 'use adder::...'.
+*/
+
+use adder::arithmetic::{self, ArithmeticError, Operation};
+
+mod common;
+
+// an integration test of a function in the adder crate
+#[test]
+fn it_adds_two() {
+    common::setup();
+    assert_eq!(4, adder::add_two(2));
+}
+
+common::cases! {
+    fn adds_two(input: i32, expected: i32) {
+        assert_eq!(adder::add_two(input), expected);
+    }
+    adds_two_to_zero: (0, 2),
+    adds_two_to_a_negative: (-5, -3),
+    adds_two_to_a_large_number: (i32::MAX - 2, i32::MAX),
+}
+
+#[test]
+fn checked_arithmetic_reports_overflow() {
+    common::setup();
+    assert_eq!(arithmetic::checked_add(200u8, 55), Ok(255));
+    assert_eq!(
+        arithmetic::checked_add(200u8, 56),
+        Err(ArithmeticError::Overflow(Operation::Add))
+    );
+    assert_eq!(
+        arithmetic::checked_div(10i32, 0),
+        Err(ArithmeticError::DivisionByZero)
+    );
+}
+
+#[test]
+fn the_three_kinds_of_overflow() {
+    common::setup();
+    let (a, b) = (i16::MAX, 2);
+    assert!(arithmetic::checked_mul(a, b).is_err());
+    assert_eq!(arithmetic::saturating_mul(a, b), i16::MAX);
+    assert_eq!(arithmetic::wrapping_mul(a, b), -2);
+}
+
+#[test]
+fn errors_work_with_the_question_mark() {
+    fn average(values: &[u64]) -> Result<u64, Box<dyn std::error::Error>> {
+        let mut total = 0u64;
+        for &value in values {
+            total = arithmetic::checked_add(total, value)?;
+        }
+        Ok(arithmetic::checked_div(total, values.len() as u64)?)
+    }
+
+    common::setup();
+    assert_eq!(average(&[2, 4, 9]).unwrap(), 5);
+    assert_eq!(average(&[]).unwrap_err().to_string(), "division by zero");
+    assert_eq!(
+        average(&[u64::MAX, 1]).unwrap_err().to_string(),
+        "addition overflowed"
+    );
+}
@@ -0,0 +1,129 @@
+//Properties of adder's operations
+/*
+The same rules the integration test checks for a few values, checked with
+forall! for many. Addition and multiplication should be commutative
+(a + b == b + a) and associative ((a + b) + c == a + (b + c)), whichever
+way overflow is handled, as long as both sides have an answer.
+
+add_two itself overflows for the largest numbers, and the second test
+checks that the panic is reported as a counterexample like any other.
+
+Saturating addition of signed numbers is the exception: (100 + 100) + -100
+saturates to 127 first and ends at 27 in an i8, while 100 + (100 + -100) is
+100. The last test checks that the counterexample found for it is as simple
+as it can be.
+*/
+
+use adder::arithmetic::*;
+use adder::property::{check, Arbitrary, Config};
+use adder::{add_two, forall, BigInt, BigUint};
+
+mod common;
+
+#[test]
+fn add_two_adds_two() {
+    common::setup();
+    // wherever a + 2 has an answer, add_two gives it
+    forall!(|a: i32| a.checked_add(2).is_none_or(|b| add_two(a) == b));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+fn add_two_overflows_at_the_top() {
+    common::setup();
+    let config = Config {
+        cases: 1000,
+        ..Config::default()
+    };
+    // the overflow panics, and that's shrunk to the first number it happens for
+    let failure = check(config, |a: i32| add_two(add_two(a)) == a + 4)
+        .expect_err("add_two overflows like + does");
+    assert_eq!(failure.minimal, i32::MAX - 3);
+    assert!(failure.reason.contains("overflow"), "{}", failure.reason);
+}
+
+#[test]
+fn wrapping_is_commutative_and_associative() {
+    common::setup();
+    forall!(|a: u64, b: u64| wrapping_add(a, b) == wrapping_add(b, a)
+        && wrapping_mul(a, b) == wrapping_mul(b, a));
+    forall!(|a: i16, b: i16, c: i16| {
+        wrapping_add(wrapping_add(a, b), c) == wrapping_add(a, wrapping_add(b, c))
+            && wrapping_mul(wrapping_mul(a, b), c) == wrapping_mul(a, wrapping_mul(b, c))
+    });
+}
+
+#[test]
+fn checked_is_commutative_and_associative() {
+    common::setup();
+    forall!(|a: i32, b: i32| checked_add(a, b) == checked_add(b, a)
+        && checked_mul(a, b) == checked_mul(b, a));
+    // with signed numbers one side can overflow on the way to an answer
+    // the other side reaches, but when both have one, it's the same
+    forall!(|a: i8, b: i8, c: i8| {
+        let left = checked_add(a, b).and_then(|ab| checked_add(ab, c));
+        let right = checked_add(b, c).and_then(|bc| checked_add(a, bc));
+        left.is_err() || right.is_err() || left == right
+    });
+    // adding unsigned numbers only grows them, so both sides overflow or
+    // neither does (multiplying doesn't: 0 * (128 * 2) overflows in a u8)
+    forall!(|a: u8, b: u8, c: u8| {
+        let left = checked_add(a, b).and_then(|ab| checked_add(ab, c));
+        let right = checked_add(b, c).and_then(|bc| checked_add(a, bc));
+        if left == right {
+            Ok(())
+        } else {
+            Err(format!("{left:?} != {right:?}"))
+        }
+    });
+}
+
+#[test]
+fn saturating_unsigned_is_commutative_and_associative() {
+    common::setup();
+    forall!(
+        |a: u16, b: u16| saturating_add(a, b) == saturating_add(b, a)
+            && saturating_mul(a, b) == saturating_mul(b, a)
+    );
+    forall!(|a: u32, b: u32, c: u32| {
+        saturating_add(saturating_add(a, b), c) == saturating_add(a, saturating_add(b, c))
+    });
+}
+
+#[test]
+fn big_integers_are_commutative_and_associative() {
+    common::setup();
+    forall!(|a: BigUint, b: BigUint| &a + &b == &b + &a && &a * &b == &b * &a);
+    forall!(|a: BigInt, b: BigInt, c: BigInt| {
+        (&a + &b) + &c == &a + (&b + &c) && (&a * &b) * &c == &a * (&b * &c)
+    });
+    // and multiplying distributes over adding
+    forall!(|a: BigInt, b: BigInt, c: BigInt| &a * (&b + &c) == &a * &b + &a * &c);
+}
+
+#[test]
+fn saturating_signed_is_not_associative() {
+    common::setup();
+    let config = Config {
+        cases: 1000,
+        ..Config::default()
+    };
+    let failure = check(config, |(a, b, c): (i8, i8, i8)| {
+        saturating_add(saturating_add(a, b), c) == saturating_add(a, saturating_add(b, c))
+    })
+    .expect_err("saturating i8 addition isn't associative");
+
+    // shrinking stops where making any one number simpler makes it pass
+    let (a, b, c) = failure.minimal;
+    let holds = |a: i8, b: i8, c: i8| {
+        saturating_add(saturating_add(a, b), c) == saturating_add(a, saturating_add(b, c))
+    };
+    assert!(!holds(a, b, c));
+    for (a, b, c) in failure.minimal.shrink() {
+        assert!(
+            holds(a, b, c),
+            "{:?} was simpler and still failed",
+            (a, b, c)
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//Driving the adder-test binary
+/*
+Runs the real adder-test program the way CI would, and checks its exit code
+and the JUnit report it leaves behind. What each option does is tested in
+runner.rs; this checks that they reach the program.
+*/
+
+use std::process::{Command, Output};
+
+mod common;
+
+use common::TempDir;
+
+fn adder_test(args: &[&str]) -> Output {
+    common::setup();
+    Command::new(env!("CARGO_BIN_EXE_adder-test"))
+        .args(args)
+        .output()
+        .expect("the adder-test binary should start")
+}
+
+#[test]
+fn passes_and_writes_junit() {
+    let dir = TempDir::new("runner");
+    let junit = dir.join("report.xml");
+    let output = adder_test(&["--junit", junit.to_str().unwrap()]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{stdout}");
+    assert!(stdout.contains("test adds_two ... ok\n"), "{stdout}");
+    assert!(
+        stdout.contains("test factorial_of_ten_thousand ... ignored\n"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("test result: ok."), "{stdout}");
+
+    let xml = dir.read("report.xml");
+    assert!(xml.starts_with("<?xml"), "{xml}");
+    assert!(
+        xml.contains(
+            "<testsuite name=\"adder\" tests=\"7\" failures=\"0\" errors=\"0\" skipped=\"1\""
+        ),
+        "{xml}"
+    );
+    assert!(
+        xml.contains("<testcase name=\"adds_two\" classname=\"adder\""),
+        "{xml}"
+    );
+}
+
+#[test]
+fn a_timeout_fails_the_run() {
+    let output = adder_test(&["factorial", "--include-ignored", "--timeout", "0.01"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(output.status.code(), Some(101), "{stdout}");
+    assert!(stdout.contains("running 1 test\n"), "{stdout}");
+    assert!(
+        stdout.contains("test factorial_of_ten_thousand ... TIMED OUT\n"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("note: 1 timed out test kept running alongside the others"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn rejects_unknown_options() {
+    let output = adder_test(&["--format", "json"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Problem parsing arguments: unknown option --format\n"
+    );
+}
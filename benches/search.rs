@@ -0,0 +1,114 @@
+//Benchmark: single-threaded vs multi-threaded directory search
+/*
+Generates a corpus of text files under the system temp directory, then times
+searching it with one thread and with several. Run it with:
+
+    cargo bench --bench search
+
+The corpus size can be changed with BENCH_FILES and BENCH_LINES.
+*/
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use minigrep::{parallel, search_path, Config};
+
+const WORDS: [&str; 16] = [
+    "nobody", "somebody", "frog", "bog", "dreary", "public", "tell", "name", "livelong", "day",
+    "admiring", "banish", "pair", "know", "you", "too",
+];
+const RUNS: usize = 5;
+
+fn main() {
+    let files = env_number("BENCH_FILES", 400);
+    let lines = env_number("BENCH_LINES", 2_000);
+    let corpus = generate_corpus(files, lines);
+    println!(
+        "corpus: {files} files x {lines} lines in {}",
+        corpus.display()
+    );
+
+    let mut thread_counts = vec![1, 2, 4, parallel::default_threads()];
+    thread_counts.sort();
+    thread_counts.dedup();
+
+    let baseline = time_search(&corpus, 1);
+    for threads in thread_counts {
+        let time = if threads == 1 {
+            baseline
+        } else {
+            time_search(&corpus, threads)
+        };
+        println!(
+            "threads {:>2}: {:>8.2?} (x{:.2})",
+            threads,
+            time,
+            baseline.as_secs_f64() / time.as_secs_f64()
+        );
+    }
+
+    fs::remove_dir_all(&corpus).unwrap();
+}
+
+fn env_number(name: &str, default: usize) -> usize {
+    env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+// the median of a few runs, so one slow run doesn't skew the result
+fn time_search(corpus: &Path, threads: usize) -> Duration {
+    let config = Config {
+        query: String::from("admiring frog"),
+        file_path: corpus.display().to_string(),
+        threads,
+        ..Config::default()
+    };
+
+    let mut times: Vec<Duration> = (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            search_path(&config, &mut io::sink()).unwrap();
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+    times[RUNS / 2]
+}
+
+fn generate_corpus(files: usize, lines: usize) -> PathBuf {
+    let dir = env::temp_dir().join(format!("minigrep-bench-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    // a small linear congruential generator keeps the corpus the same every run
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next_word = || {
+        seed = seed
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        WORDS[(seed >> 33) as usize % WORDS.len()]
+    };
+
+    for f in 0..files {
+        let sub = dir.join(format!("dir{:02}", f % 16));
+        fs::create_dir_all(&sub).unwrap();
+
+        let mut text = String::new();
+        for _ in 0..lines {
+            for w in 0..8 {
+                if w > 0 {
+                    text.push(' ');
+                }
+                text.push_str(next_word());
+            }
+            text.push('\n');
+        }
+        fs::write(sub.join(format!("file{f:04}.txt")), text).unwrap();
+    }
+
+    dir
+}
@@ -0,0 +1,265 @@
+//The back of house
+/*
+The kitchen. The Appetizer enum is the chapter 7 example of a public enum,
+moved here from lib.rs when back_of_house got a file of its own. The
+appetizers the kitchen really makes are the ones on the menu (see menu.rs),
+under Category::Appetizer.
+
+Breakfast is the chapter's example of a struct with a private field: guests
+choose their toast, but the chef chooses the fruit. It used to always be
+peaches; now it's the first fruit on the menu that's in season.
+
+cook_order and fix_incorrect_order were the book's example of 'super':
+
+    fn fix_incorrect_order() {
+        cook_order();
+        super::deliver_order();
+    }
+
+They still call each other that way, but now they move a real Order along
+(see front_of_house/serving.rs). Cooking an order starts it and has it ready
+some minutes later. Fixing one sends it back, with a reason for the history,
+changes what was wrong with it, cooks it again and has it delivered to the
+table.
+*/
+
+use crate::front_of_house::serving::{Order, OrderError, OrderState, PriceList};
+use crate::hosting::Minutes;
+use crate::menu::{Category, Menu, Season};
+
+pub enum Appetizer {
+    Soup,
+    Salad,
+}
+
+/// A breakfast, with the toast the guest chose and fruit the chef chose.
+pub struct Breakfast {
+    pub toast: String,
+    // None when no fruit on the menu is in season
+    seasonal_fruit: Option<String>,
+}
+
+impl Breakfast {
+    /// A summer breakfast with `toast`.
+    pub fn summer(toast: &str, menu: &Menu) -> Breakfast {
+        Breakfast::in_season(toast, Season::Summer, menu)
+    }
+
+    /// A breakfast with `toast` and the first fruit on `menu` that's
+    /// available in `season`.
+    pub fn in_season(toast: &str, season: Season, menu: &Menu) -> Breakfast {
+        let fruit = menu
+            .query()
+            .category(Category::Fruit)
+            .available_in(season)
+            .names()
+            .first()
+            .map(|name| name.to_string());
+        Breakfast {
+            toast: String::from(toast),
+            seasonal_fruit: fruit,
+        }
+    }
+
+    /// Whether there's fruit with it. Which fruit is up to the chef.
+    pub fn comes_with_fruit(&self) -> bool {
+        self.seasonal_fruit.is_some()
+    }
+}
+
+/// What was wrong with an order and how to put it right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Correction<'a> {
+    pub reason: &'a str,
+    /// Items to take off the order, and how many of each.
+    pub remove: &'a [(&'a str, u32)],
+    /// Items to put on it instead.
+    pub add: &'a [(&'a str, u32)],
+}
+
+/// Cooks `order`, starting at `at` unless it's already cooking, so that
+/// it's ready `minutes` later.
+pub fn cook_order(order: &mut Order, at: Minutes, minutes: Minutes) -> Result<(), OrderError> {
+    if order.state() != OrderState::Cooking {
+        order.start_cooking(at)?;
+    }
+    order.finish_cooking(at + minutes)
+}
+
+/// Sends back a ready or served order, corrects it, cooks it again for
+/// `minutes` and delivers it.
+pub fn fix_incorrect_order<M: PriceList + ?Sized>(
+    order: &mut Order,
+    menu: &M,
+    correction: Correction,
+    at: Minutes,
+    minutes: Minutes,
+) -> Result<(), OrderError> {
+    order.send_back(correction.reason, at)?;
+    order.correct(menu, correction.remove, correction.add, at)?;
+    cook_order(order, at, minutes)?;
+    super::deliver_order(order, at + minutes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::front_of_house::serving::{self, take_order, Cents, EventKind};
+
+    #[test]
+    fn breakfast_fruit_comes_from_the_menu() {
+        let menu: Menu = "[fruit]\nPeaches | 3.50 | summer\nApples | 3 | autumn, winter\n"
+            .parse()
+            .unwrap();
+        let mut meal = Breakfast::summer("Rye", &menu);
+        meal.toast = String::from("Wheat");
+        assert_eq!(meal.toast, "Wheat");
+        assert_eq!(meal.seasonal_fruit.as_deref(), Some("Peaches"));
+
+        let meal = Breakfast::in_season("Rye", Season::Winter, &menu);
+        assert_eq!(meal.seasonal_fruit.as_deref(), Some("Apples"));
+        let meal = Breakfast::in_season("Rye", Season::Spring, &menu);
+        assert_eq!(meal.seasonal_fruit, None);
+        assert!(!meal.comes_with_fruit());
+    }
+
+    const MENU: [(&str, Cents); 3] = [("soup", 650), ("salad", 800), ("toast", 250)];
+
+    #[test]
+    fn cooking_makes_an_order_ready() {
+        let mut order = take_order(1, &[("soup", 1)], &MENU, 0).unwrap();
+        cook_order(&mut order, 2, 12).unwrap();
+        assert_eq!(order.state(), OrderState::Ready);
+        assert_eq!(order.history().last().unwrap().at, 14);
+        // it's been cooked already
+        assert!(cook_order(&mut order, 15, 5).is_err());
+    }
+
+    #[test]
+    fn fixing_a_served_order() {
+        let mut order = take_order(7, &[("soup", 2), ("toast", 1)], &MENU, 0).unwrap();
+        cook_order(&mut order, 1, 10).unwrap();
+        serving::serve_order(&mut order, 12).unwrap();
+
+        let correction = Correction {
+            reason: "one soup should have been a salad",
+            remove: &[("soup", 1)],
+            add: &[("salad", 1)],
+        };
+        fix_incorrect_order(&mut order, &MENU, correction, 15, 8).unwrap();
+        assert_eq!(order.state(), OrderState::Served);
+        assert_eq!(order.total(), 650 + 800 + 250);
+
+        let history: Vec<String> = order.history()[5..].iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            history,
+            [
+                "  12 min  served",
+                "  15 min  sent back: one soup should have been a salad",
+                "  15 min  removed 1 x soup",
+                "  15 min  added 1 x salad",
+                "  23 min  ready",
+                "  23 min  served",
+            ]
+        );
+        assert_eq!(serving::take_payment(&mut order, 1700, 40), Ok(0));
+    }
+
+    #[test]
+    fn fixing_a_ready_order_before_it_goes_out() {
+        let mut order = take_order(2, &[("toast", 1)], &MENU, 0).unwrap();
+        cook_order(&mut order, 0, 4).unwrap();
+        let burnt = Correction {
+            reason: "burnt",
+            remove: &[],
+            add: &[],
+        };
+        fix_incorrect_order(&mut order, &MENU, burnt, 5, 4).unwrap();
+        assert_eq!(order.state(), OrderState::Served);
+        assert_eq!(order.total(), 250);
+    }
+
+    #[test]
+    fn only_cooked_orders_can_be_fixed() {
+        let mut order = take_order(3, &[("salad", 1)], &MENU, 0).unwrap();
+        let correction = Correction {
+            reason: "no dressing",
+            remove: &[],
+            add: &[],
+        };
+        assert_eq!(
+            fix_incorrect_order(&mut order, &MENU, correction, 1, 5),
+            Err(OrderError::IllegalTransition {
+                from: OrderState::Placed,
+                action: serving::Action::SendBack,
+            })
+        );
+
+        // and corrections outside fixing are refused
+        order.start_cooking(2).unwrap();
+        assert!(order.correct(&MENU, &[("salad", 1)], &[], 3).is_err());
+        assert!(matches!(
+            order.history().last().unwrap().kind,
+            EventKind::Refused(_)
+        ));
+        assert_eq!(order.total(), 800);
+    }
+
+    #[test]
+    fn a_fix_needs_items_that_are_on_the_order() {
+        let mut order = take_order(4, &[("soup", 1)], &MENU, 0).unwrap();
+        cook_order(&mut order, 0, 5).unwrap();
+        let correction = Correction {
+            reason: "wrong dish",
+            remove: &[("salad", 1)],
+            add: &[],
+        };
+        assert_eq!(
+            fix_incorrect_order(&mut order, &MENU, correction, 6, 5),
+            Err(OrderError::NotOnOrder(String::from("salad")))
+        );
+        // it's back in the kitchen, where it can still be made right
+        assert_eq!(order.state(), OrderState::Cooking);
+        assert_eq!(order.total(), 650);
+        cook_order(&mut order, 6, 5).unwrap();
+        assert_eq!(order.state(), OrderState::Ready);
+    }
+
+    #[test]
+    fn a_fix_is_made_in_full_or_not_at_all() {
+        let mut order = take_order(5, &[("soup", 2), ("toast", 1)], &MENU, 0).unwrap();
+        cook_order(&mut order, 0, 5).unwrap();
+        let correction = Correction {
+            reason: "wrong sides",
+            remove: &[("toast", 1), ("toast", 1)],
+            add: &[("salad", 1)],
+        };
+        let before = order.items().to_vec();
+        assert_eq!(
+            fix_incorrect_order(&mut order, &MENU, correction, 6, 5),
+            Err(OrderError::NotOnOrder(String::from("toast")))
+        );
+        // the first toast is still on it, and no salad was added
+        assert_eq!(order.items(), before);
+        let history = order.history();
+        assert!(matches!(
+            history[history.len() - 2].kind,
+            EventKind::SentBack { .. }
+        ));
+        assert!(matches!(
+            history[history.len() - 1].kind,
+            EventKind::Refused(OrderError::NotOnOrder(_))
+        ));
+
+        // an addition that fails undoes the removals too
+        let correction = Correction {
+            reason: "wrong sides",
+            remove: &[("toast", 1)],
+            add: &[("lobster", 1)],
+        };
+        assert!(order
+            .correct(&MENU, correction.remove, correction.add, 7)
+            .is_err());
+        assert_eq!(order.items(), before);
+    }
+}
@@ -0,0 +1,12 @@
+//The front of house
+/*
+Where the guests are. The book's front_of_house only had empty functions in
+it; now each part of it is a module in its own file under
+src/front_of_house/, the way the end of chapter 7 splits a crate up:
+
+    hosting     the waitlist at the door and the tables in the dining room
+    serving     taking orders, bringing them out and taking payment
+*/
+
+pub mod hosting;
+pub mod serving;
@@ -0,0 +1,615 @@
+//Hosting: the waitlist and the floor plan
+/*
+The host keeps a list of the parties waiting at the door and knows which
+tables in the dining room are free. 'add_to_waitlist' puts a party on the
+list, and 'seat_at_table' takes the next one that can sit down and gives it
+a table.
+
+The waitlist is served in order of priority, VIPs first, then reservations,
+then walk-ins, and within a priority in the order they arrived. When the
+party at the front is too big for any free table, a smaller party behind it
+may be seated first instead of leaving a table empty. Times are counted in
+minutes from when the doors open.
+
+Two parties can give the same name, so every Party gets its own PartyId
+when it's made, and the waitlist and the floor plan tell parties apart by
+that instead of by name.
+
+Choosing a table:
+
+    one table       the smallest free table the party fits at, so a couple
+                    doesn't take a table for six
+    pushed together large parties get tables in the same row pushed
+                    together. Where two tables meet, the chairs at their
+                    ends have to go, so each join loses SEATS_LOST_PER_JOIN
+                    seats: two tables for four seat six, not eight. The
+                    fewest tables that fit are used, then the run with the
+                    fewest empty seats.
+
+A party that's bigger than any table or row could ever seat stays on the
+waitlist; 'FloorPlan::could_seat' says whether that's the case.
+*/
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Minutes since the doors opened.
+pub type Minutes = u32;
+
+/// How many seats are lost where two tables are pushed together.
+pub const SEATS_LOST_PER_JOIN: u32 = 2;
+
+/// Who gets seated first, the highest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    #[default]
+    WalkIn,
+    Reservation,
+    Vip,
+}
+
+/// Tells parties apart, even ones with the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PartyId(u32);
+
+/// A group of guests who sit together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Party {
+    id: PartyId,
+    pub name: String,
+    pub size: u32,
+    pub arrived: Minutes,
+    pub priority: Priority,
+}
+
+impl Party {
+    /// A walk-in party. Panics if `size` is 0.
+    pub fn new(name: &str, size: u32, arrived: Minutes) -> Party {
+        static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+        assert!(size > 0, "a party has at least one guest");
+        Party {
+            id: PartyId(NEXT_ID.fetch_add(1, Ordering::Relaxed)),
+            name: String::from(name),
+            size,
+            arrived,
+            priority: Priority::WalkIn,
+        }
+    }
+
+    pub fn with_priority(mut self, priority: Priority) -> Party {
+        self.priority = priority;
+        self
+    }
+
+    pub fn id(&self) -> PartyId {
+        self.id
+    }
+}
+
+/// The parties waiting for a table, in the order they'll be seated.
+#[derive(Debug, Clone, Default)]
+pub struct Waitlist {
+    parties: Vec<Party>,
+}
+
+impl Waitlist {
+    pub fn new() -> Waitlist {
+        Waitlist::default()
+    }
+
+    /// Adds `party` behind everyone of the same or a higher priority who
+    /// arrived no later.
+    pub fn add(&mut self, party: Party) {
+        let position = self
+            .parties
+            .iter()
+            .position(|p| {
+                (p.priority, std::cmp::Reverse(p.arrived))
+                    < (party.priority, std::cmp::Reverse(party.arrived))
+            })
+            .unwrap_or(self.parties.len());
+        self.parties.insert(position, party);
+    }
+
+    pub fn len(&self) -> usize {
+        self.parties.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parties.is_empty()
+    }
+
+    /// The parties in the order they'll be seated.
+    pub fn iter(&self) -> impl Iterator<Item = &Party> {
+        self.parties.iter()
+    }
+
+    /// Where the party is in the line, counting from 0.
+    pub fn position(&self, id: PartyId) -> Option<usize> {
+        self.parties.iter().position(|p| p.id == id)
+    }
+
+    /// Takes the party off the list, say when they give up.
+    pub fn remove(&mut self, id: PartyId) -> Option<Party> {
+        let position = self.position(id)?;
+        Some(self.parties.remove(position))
+    }
+
+    /// The parties waiting under `name`, in the order they'll be seated.
+    pub fn named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Party> {
+        self.parties.iter().filter(move |p| p.name == name)
+    }
+
+    /// How long the party that's waited longest has waited by `now`.
+    pub fn longest_wait(&self, now: Minutes) -> Option<Minutes> {
+        self.parties
+            .iter()
+            .map(|p| now.saturating_sub(p.arrived))
+            .max()
+    }
+}
+
+/// Puts `party` on the waitlist.
+pub fn add_to_waitlist(waitlist: &mut Waitlist, party: Party) {
+    waitlist.add(party);
+}
+
+/// A table in the dining room.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub number: u32,
+    pub capacity: u32,
+    /// Tables in the same row, in order of number, stand side by side and
+    /// can be pushed together. `None` for a table standing on its own.
+    pub row: Option<u32>,
+}
+
+impl Table {
+    pub fn new(number: u32, capacity: u32) -> Table {
+        Table {
+            number,
+            capacity,
+            row: None,
+        }
+    }
+
+    pub fn in_row(mut self, row: u32) -> Table {
+        self.row = Some(row);
+        self
+    }
+}
+
+/// Where a party sat down.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Seating {
+    pub id: PartyId,
+    pub party: String,
+    pub size: u32,
+    /// The table numbers, more than one when they were pushed together.
+    pub tables: Vec<u32>,
+    /// How many seats the tables have together.
+    pub seats: u32,
+}
+
+/// The tables and who's sitting at them.
+#[derive(Debug, Clone)]
+pub struct FloorPlan {
+    tables: Vec<Table>,
+    // table number to the party sitting there and its name
+    occupied: HashMap<u32, (PartyId, String)>,
+}
+
+impl FloorPlan {
+    /// Panics if two tables have the same number.
+    pub fn new(mut tables: Vec<Table>) -> FloorPlan {
+        tables.sort_by_key(|t| t.number);
+        assert!(
+            tables
+                .windows(2)
+                .all(|pair| pair[0].number != pair[1].number),
+            "every table needs its own number"
+        );
+        FloorPlan {
+            tables,
+            occupied: HashMap::new(),
+        }
+    }
+
+    pub fn tables(&self) -> &[Table] {
+        &self.tables
+    }
+
+    /// The party at table `number`, if anyone's there.
+    pub fn occupant(&self, number: u32) -> Option<&str> {
+        self.occupied.get(&number).map(|(_, name)| name.as_str())
+    }
+
+    /// Whether the party is sitting at any table.
+    pub fn is_seated(&self, id: PartyId) -> bool {
+        self.occupied.values().any(|(party, _)| *party == id)
+    }
+
+    pub fn is_free(&self, number: u32) -> bool {
+        !self.occupied.contains_key(&number)
+    }
+
+    /// How many tables are free.
+    pub fn free_tables(&self) -> usize {
+        self.tables.len() - self.occupied.len()
+    }
+
+    /// Whether a party of `size` fits somewhere when every table is free.
+    pub fn could_seat(&self, size: u32) -> bool {
+        self.choose(size, |_| true).is_some()
+    }
+
+    /// The tables a party of `size` would get now, without seating them.
+    pub fn best_fit(&self, size: u32) -> Option<Vec<u32>> {
+        self.choose(size, |t| self.is_free(t.number))
+            .map(|(tables, _)| tables)
+    }
+
+    /// Seats `party` at the best free table or tables, if there are any
+    /// and they aren't sitting down already.
+    pub fn seat(&mut self, party: &Party) -> Option<Seating> {
+        if self.is_seated(party.id) {
+            return None;
+        }
+        let (tables, seats) = self.choose(party.size, |t| self.is_free(t.number))?;
+        for &number in &tables {
+            self.occupied.insert(number, (party.id, party.name.clone()));
+        }
+        Some(Seating {
+            id: party.id,
+            party: party.name.clone(),
+            size: party.size,
+            tables,
+            seats,
+        })
+    }
+
+    /// Frees the tables the party was at, returning them.
+    pub fn leave(&mut self, id: PartyId) -> Vec<u32> {
+        let mut freed: Vec<u32> = self
+            .occupied
+            .iter()
+            .filter(|(_, (party, _))| *party == id)
+            .map(|(&number, _)| number)
+            .collect();
+        freed.sort_unstable();
+        for number in &freed {
+            self.occupied.remove(number);
+        }
+        freed
+    }
+
+    // The best tables among those `usable` says can be used, and how many
+    // seats they have together.
+    fn choose(&self, size: u32, usable: impl Fn(&Table) -> bool) -> Option<(Vec<u32>, u32)> {
+        let single = self
+            .tables
+            .iter()
+            .filter(|t| usable(t) && t.capacity >= size)
+            .min_by_key(|t| (t.capacity, t.number));
+        if let Some(table) = single {
+            return Some((vec![table.number], table.capacity));
+        }
+
+        // runs of usable tables side by side in a row; for each start, the
+        // shortest run that's big enough, since a longer one only adds
+        // empty seats. The best is (fewest tables, fewest empty seats,
+        // lowest number).
+        let mut best: Option<(usize, u32, Vec<u32>)> = None;
+        for row in self.rows() {
+            for start in 0..row.len() {
+                let mut seats = 0;
+                for (joined, table) in row[start..].iter().enumerate() {
+                    if !usable(table) {
+                        break;
+                    }
+                    seats += table.capacity;
+                    if joined > 0 {
+                        seats = seats.saturating_sub(SEATS_LOST_PER_JOIN);
+                    }
+                    if seats >= size {
+                        let count = joined + 1;
+                        let waste = seats - size;
+                        let better = best.as_ref().is_none_or(|(c, w, tables)| {
+                            (count, waste, row[start].number) < (*c, *w, tables[0])
+                        });
+                        if better {
+                            let tables = row[start..=start + joined]
+                                .iter()
+                                .map(|t| t.number)
+                                .collect();
+                            best = Some((count, waste, tables));
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+        best.map(|(_, waste, tables)| (tables, size + waste))
+    }
+
+    // the tables of each row, in order of number
+    fn rows(&self) -> Vec<Vec<&Table>> {
+        let mut rows: Vec<(u32, Vec<&Table>)> = Vec::new();
+        for table in &self.tables {
+            let Some(row) = table.row else { continue };
+            match rows.iter_mut().find(|(r, _)| *r == row) {
+                Some((_, tables)) => tables.push(table),
+                None => rows.push((row, vec![table])),
+            }
+        }
+        rows.into_iter().map(|(_, tables)| tables).collect()
+    }
+}
+
+/// Seats the first party on the waitlist that fits somewhere now, taking
+/// them off the list. `None` if nobody waiting can be seated.
+pub fn seat_at_table(waitlist: &mut Waitlist, floor: &mut FloorPlan) -> Option<Seating> {
+    let position = waitlist
+        .parties
+        .iter()
+        .position(|p| floor.best_fit(p.size).is_some())?;
+    let party = waitlist.parties.remove(position);
+    floor.seat(&party)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use adder::property::Rng;
+
+    // two tables for two on their own, a row of four for four, two for six
+    // and a row of three for four
+    fn dining_room() -> FloorPlan {
+        FloorPlan::new(vec![
+            Table::new(1, 2),
+            Table::new(2, 2),
+            Table::new(3, 2),
+            Table::new(4, 2),
+            Table::new(5, 4).in_row(1),
+            Table::new(6, 4).in_row(1),
+            Table::new(7, 4).in_row(1),
+            Table::new(8, 4).in_row(1),
+            Table::new(9, 6),
+            Table::new(10, 6),
+            Table::new(11, 4).in_row(2),
+            Table::new(12, 4).in_row(2),
+            Table::new(13, 4).in_row(2),
+        ])
+    }
+
+    fn names(waitlist: &Waitlist) -> Vec<&str> {
+        waitlist.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn waitlist_order() {
+        let mut waitlist = Waitlist::new();
+        let ada = Party::new("Ada", 2, 0);
+        let cy = Party::new("Cy", 3, 2).with_priority(Priority::Reservation);
+        add_to_waitlist(&mut waitlist, ada.clone());
+        add_to_waitlist(&mut waitlist, Party::new("Bo", 4, 1));
+        add_to_waitlist(&mut waitlist, cy.clone());
+        add_to_waitlist(
+            &mut waitlist,
+            Party::new("Di", 2, 3).with_priority(Priority::Vip),
+        );
+        add_to_waitlist(
+            &mut waitlist,
+            Party::new("Ed", 2, 4).with_priority(Priority::Reservation),
+        );
+        // arrived earlier but added late, as when the host catches up
+        add_to_waitlist(&mut waitlist, Party::new("Fay", 5, 0));
+        assert_eq!(names(&waitlist), ["Di", "Cy", "Ed", "Ada", "Fay", "Bo"]);
+
+        assert_eq!(waitlist.position(ada.id()), Some(3));
+        assert_eq!(waitlist.longest_wait(10), Some(10));
+        assert_eq!(waitlist.remove(cy.id()).map(|p| p.size), Some(3));
+        assert_eq!(waitlist.remove(cy.id()), None);
+        assert_eq!(waitlist.len(), 5);
+    }
+
+    #[test]
+    fn parties_with_the_same_name_are_kept_apart() {
+        let mut waitlist = Waitlist::new();
+        let first = Party::new("Smith", 2, 0);
+        let second = Party::new("Smith", 4, 1);
+        assert_ne!(first.id(), second.id());
+        add_to_waitlist(&mut waitlist, first.clone());
+        add_to_waitlist(&mut waitlist, second.clone());
+        assert_eq!(waitlist.named("Smith").count(), 2);
+
+        // the second Smiths give up, and the first are still waiting
+        assert_eq!(waitlist.remove(second.id()).map(|p| p.size), Some(4));
+        assert_eq!(waitlist.position(first.id()), Some(0));
+
+        let mut floor = dining_room();
+        assert_eq!(floor.seat(&first).unwrap().tables, [1]);
+        assert_eq!(floor.seat(&second).unwrap().tables, [5]);
+        // a party can't be seated twice
+        assert_eq!(floor.seat(&first), None);
+
+        assert_eq!(floor.leave(first.id()), [1]);
+        assert_eq!(floor.occupant(5), Some("Smith"));
+        assert!(floor.is_seated(second.id()));
+        assert_eq!(floor.leave(second.id()), [5]);
+        assert_eq!(floor.free_tables(), floor.tables().len());
+    }
+
+    #[test]
+    fn the_smallest_table_that_fits() {
+        let mut floor = dining_room();
+        assert_eq!(floor.best_fit(1), Some(vec![1]));
+        assert_eq!(floor.best_fit(3), Some(vec![5]));
+        assert_eq!(floor.best_fit(5), Some(vec![9]));
+
+        let seating = floor.seat(&Party::new("Ada", 2, 0)).unwrap();
+        assert_eq!(seating.tables, [1]);
+        assert_eq!(floor.occupant(1), Some("Ada"));
+        assert_eq!(floor.best_fit(2), Some(vec![2]));
+    }
+
+    #[test]
+    fn large_parties_push_tables_together() {
+        let mut floor = dining_room();
+        // 4 + 4 - 2 = 6 isn't enough, 4 + 4 + 4 - 4 = 8 is
+        let big = Party::new("Big", 8, 0);
+        let seating = floor.seat(&big).unwrap();
+        assert_eq!(seating.tables, [5, 6, 7]);
+        assert_eq!(seating.seats, 8);
+
+        // row 1 has only table 8 left, so this takes row 2
+        let seating = floor.seat(&Party::new("Bigger", 7, 0)).unwrap();
+        assert_eq!(seating.tables, [11, 12, 13]);
+
+        // nothing left to push together
+        assert_eq!(floor.best_fit(8), None);
+        assert!(floor.could_seat(10));
+        assert!(!floor.could_seat(11));
+
+        assert_eq!(floor.leave(big.id()), [5, 6, 7]);
+        assert_eq!(floor.best_fit(10), Some(vec![5, 6, 7, 8]));
+    }
+
+    #[test]
+    fn merging_skips_occupied_tables() {
+        let mut floor = dining_room();
+        floor.seat(&Party::new("Six", 6, 0)).unwrap();
+        floor.seat(&Party::new("Six again", 6, 0)).unwrap();
+        // the four-tops: 6 in the middle of row 1 is taken
+        let middle = Party::new("Middle", 4, 0);
+        assert_eq!(floor.seat(&middle).unwrap().tables, [5]);
+        assert_eq!(floor.seat(&Party::new("Next", 4, 0)).unwrap().tables, [6]);
+        // 7 and 8 are together; 11, 12 and 13 are too but waste more
+        assert_eq!(floor.best_fit(6), Some(vec![7, 8]));
+        floor.leave(middle.id());
+        assert_eq!(floor.best_fit(6), Some(vec![7, 8]));
+    }
+
+    #[test]
+    fn smaller_parties_go_ahead_of_one_that_doesnt_fit() {
+        let mut floor = FloorPlan::new(vec![Table::new(1, 2), Table::new(2, 6)]);
+        let mut waitlist = Waitlist::new();
+        let already_here = Party::new("Already here", 5, 0);
+        floor.seat(&already_here).unwrap();
+        add_to_waitlist(&mut waitlist, Party::new("Four", 4, 1));
+        add_to_waitlist(&mut waitlist, Party::new("Two", 2, 2));
+
+        let seating = seat_at_table(&mut waitlist, &mut floor).unwrap();
+        assert_eq!((seating.party.as_str(), seating.tables), ("Two", vec![1]));
+        assert_eq!(seat_at_table(&mut waitlist, &mut floor), None);
+        assert_eq!(names(&waitlist), ["Four"]);
+
+        floor.leave(already_here.id());
+        let seating = seat_at_table(&mut waitlist, &mut floor).unwrap();
+        assert_eq!(seating.tables, [2]);
+        assert!(waitlist.is_empty());
+    }
+
+    #[test]
+    fn a_busy_evening() {
+        // seeded, so the evening is the same every run
+        let mut rng = Rng::new(7);
+        let mut floor = dining_room();
+        let mut waitlist = Waitlist::new();
+
+        // four hours of arrivals, mostly small parties, a few big ones
+        let mut arrivals = Vec::new();
+        for minute in 0..240 {
+            if rng.below(3) == 0 {
+                let size = match rng.below(20) {
+                    0 => 8 + rng.below(3) as u32,
+                    1..=3 => 5 + rng.below(2) as u32,
+                    _ => 1 + rng.below(4) as u32,
+                };
+                let priority = match rng.below(10) {
+                    0 => Priority::Vip,
+                    1..=3 => Priority::Reservation,
+                    _ => Priority::WalkIn,
+                };
+                let name = format!("party {}", arrivals.len());
+                arrivals.push(Party::new(&name, size, minute).with_priority(priority));
+            }
+        }
+        assert!(arrivals.len() > 60);
+
+        let mut arriving = arrivals.iter().peekable();
+        let mut dining: Vec<(Minutes, Seating)> = Vec::new();
+        let mut seated: Vec<(Party, Minutes, Seating)> = Vec::new();
+        let mut most_waiting = 0;
+
+        for now in 0.. {
+            while let Some(party) = arriving.next_if(|p| p.arrived == now) {
+                add_to_waitlist(&mut waitlist, party.clone());
+            }
+            // meals take longer for bigger parties
+            dining.retain(|(done, seating)| {
+                if *done == now {
+                    assert!(!floor.leave(seating.id).is_empty());
+                    false
+                } else {
+                    true
+                }
+            });
+            most_waiting = most_waiting.max(waitlist.len());
+
+            while let Some(seating) = seat_at_table(&mut waitlist, &mut floor) {
+                let party = arrivals.iter().find(|p| p.id() == seating.id).unwrap();
+                assert!(seating.seats >= party.size, "{seating:?}");
+                for &table in &seating.tables {
+                    assert_eq!(floor.occupant(table), Some(party.name.as_str()));
+                }
+                dining.push((now + 40 + 5 * party.size, seating.clone()));
+                seated.push((party.clone(), now, seating));
+            }
+
+            // nobody at a table is sitting anywhere else
+            let at_tables: usize = dining.iter().map(|(_, s)| s.tables.len()).sum();
+            assert_eq!(at_tables, floor.tables().len() - floor.free_tables());
+
+            if arriving.peek().is_none() && waitlist.is_empty() && dining.is_empty() {
+                break;
+            }
+            assert!(now < 24 * 60, "the evening never ended");
+        }
+
+        // everyone ate, and it was busy enough that people had to wait
+        assert_eq!(seated.len(), arrivals.len());
+        assert!(most_waiting >= 5, "only {most_waiting} ever waited");
+        assert!(seated.iter().any(|(_, _, s)| s.tables.len() > 1));
+
+        // merged tables were side by side in one row
+        for (_, _, seating) in &seated {
+            let tables: Vec<&Table> = seating
+                .tables
+                .iter()
+                .map(|n| floor.tables().iter().find(|t| t.number == *n).unwrap())
+                .collect();
+            if tables.len() > 1 {
+                assert!(tables
+                    .windows(2)
+                    .all(|pair| pair[0].row.is_some() && pair[0].row == pair[1].row));
+            }
+        }
+
+        // VIPs waited less than walk-ins on average
+        let average_wait = |priority: Priority| {
+            let waits: Vec<Minutes> = seated
+                .iter()
+                .filter(|(p, _, _)| p.priority == priority)
+                .map(|(p, at, _)| at - p.arrived)
+                .collect();
+            waits.iter().sum::<Minutes>() as f64 / waits.len().max(1) as f64
+        };
+        assert!(
+            average_wait(Priority::Vip) <= average_wait(Priority::WalkIn),
+            "VIPs waited {} minutes, walk-ins {}",
+            average_wait(Priority::Vip),
+            average_wait(Priority::WalkIn)
+        );
+    }
+}
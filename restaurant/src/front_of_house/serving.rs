@@ -0,0 +1,751 @@
+//Serving: orders from the table to the bill
+/*
+The book's serving module had take_order, serve_order and take_payment, all
+empty. Here they work on an Order: the items a table asked for, priced from
+a menu, and where the order has got to.
+
+An order goes through the same steps every time:
+
+    Placed --> Cooking --> Ready --> Served --> Paid
+                  ^          |         |
+                  +----------+---------+
+                      sent back
+
+The waiter takes the order, the kitchen cooks it (see back_of_house.rs),
+the waiter serves it and takes the payment. A dish that comes out wrong,
+before or after it reaches the table, is sent back and cooked again. Each
+step is a method that only works from the right state, so serving an order
+that's still cooking, or taking payment twice, is an OrderError instead of
+something that quietly happens. Items can only be changed before cooking
+starts, unless the kitchen is fixing a wrong order.
+
+Every step is written to the order's history with the time it happened,
+and so is every step that was refused, so afterwards there's a record of
+what happened to the order and who tried what when.
+
+Prices are whole cents, so adding them up never loses a fraction of one,
+and times are minutes since opening, as in hosting. An order can only come
+to as much as a Cents can hold; adding more than that is refused as
+TooLarge, so an order's total can always be worked out.
+*/
+
+use std::error::Error;
+use std::fmt;
+
+use super::hosting::Minutes;
+
+/// An amount of money in cents.
+pub type Cents = u32;
+
+/// `cents` written in dollars, like "$12.50".
+pub fn dollars(cents: Cents) -> String {
+    format!("${}.{:02}", cents / 100, cents % 100)
+}
+
+/// Anything that knows the price of the items on a menu.
+pub trait PriceList {
+    /// The price of `item`, or `None` if it isn't on the menu.
+    fn price_of(&self, item: &str) -> Option<Cents>;
+}
+
+impl PriceList for [(&str, Cents)] {
+    fn price_of(&self, item: &str) -> Option<Cents> {
+        self.iter()
+            .find(|(name, _)| *name == item)
+            .map(|&(_, price)| price)
+    }
+}
+
+impl<const N: usize> PriceList for [(&str, Cents); N] {
+    fn price_of(&self, item: &str) -> Option<Cents> {
+        self.as_slice().price_of(item)
+    }
+}
+
+/// Where an order has got to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OrderState {
+    Placed,
+    Cooking,
+    Ready,
+    Served,
+    Paid,
+}
+
+impl fmt::Display for OrderState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            OrderState::Placed => "placed",
+            OrderState::Cooking => "cooking",
+            OrderState::Ready => "ready",
+            OrderState::Served => "served",
+            OrderState::Paid => "paid",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Something that can be done to an order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    ChangeItems,
+    StartCooking,
+    FinishCooking,
+    Serve,
+    SendBack,
+    Pay,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Action::ChangeItems => "change the items of",
+            Action::StartCooking => "start cooking",
+            Action::FinishCooking => "finish cooking",
+            Action::Serve => "serve",
+            Action::SendBack => "send back",
+            Action::Pay => "pay for",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl Action {
+    // the state the action moves an order to from `from`, if it's allowed
+    fn next(self, from: OrderState) -> Option<OrderState> {
+        use OrderState::*;
+        match (self, from) {
+            (Action::ChangeItems, Placed) => Some(Placed),
+            (Action::StartCooking, Placed) => Some(Cooking),
+            (Action::FinishCooking, Cooking) => Some(Ready),
+            (Action::Serve, Ready) => Some(Served),
+            (Action::SendBack, Ready | Served) => Some(Cooking),
+            (Action::Pay, Served) => Some(Paid),
+            _ => None,
+        }
+    }
+}
+
+/// Why an order couldn't be taken or moved on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrderError {
+    /// The action isn't allowed in the order's state.
+    IllegalTransition {
+        from: OrderState,
+        action: Action,
+    },
+    UnknownItem(String),
+    /// An order needs at least one item, and an item a quantity of one or more.
+    NothingOrdered,
+    /// The item isn't on the order, or not that many of it.
+    NotOnOrder(String),
+    Underpaid {
+        due: Cents,
+        paid: Cents,
+    },
+    /// The order would come to more than can be counted in `Cents`.
+    TooLarge,
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OrderError::IllegalTransition { from, action } => {
+                write!(f, "can't {action} an order that's {from}")
+            }
+            OrderError::UnknownItem(item) => write!(f, "{item} isn't on the menu"),
+            OrderError::NothingOrdered => write!(f, "nothing was ordered"),
+            OrderError::NotOnOrder(item) => write!(f, "{item} isn't on the order"),
+            OrderError::Underpaid { due, paid } => write!(
+                f,
+                "{} is due but only {} was paid",
+                dollars(*due),
+                dollars(*paid)
+            ),
+            OrderError::TooLarge => write!(
+                f,
+                "the order would come to more than {}",
+                dollars(Cents::MAX)
+            ),
+        }
+    }
+}
+
+impl Error for OrderError {}
+
+/// One item on an order and how many of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineItem {
+    pub item: String,
+    pub quantity: u32,
+    /// The price of one when it was ordered.
+    pub unit_price: Cents,
+}
+
+impl LineItem {
+    /// What the line comes to, if that fits in `Cents`.
+    pub fn total(&self) -> Result<Cents, OrderError> {
+        self.unit_price
+            .checked_mul(self.quantity)
+            .ok_or(OrderError::TooLarge)
+    }
+}
+
+// What `items` come to, if that fits in `Cents`.
+fn total(items: &[LineItem]) -> Result<Cents, OrderError> {
+    items.iter().try_fold(0, |sum: Cents, line| {
+        sum.checked_add(line.total()?).ok_or(OrderError::TooLarge)
+    })
+}
+
+// Puts `quantity` of `item` on `items` at its price on `menu`, changing
+// nothing if it can't.
+fn add_line<M: PriceList + ?Sized>(
+    items: &mut Vec<LineItem>,
+    menu: &M,
+    item: &str,
+    quantity: u32,
+) -> Result<(), OrderError> {
+    if quantity == 0 {
+        return Err(OrderError::NothingOrdered);
+    }
+    let price = menu
+        .price_of(item)
+        .ok_or_else(|| OrderError::UnknownItem(String::from(item)))?;
+    let more = price.checked_mul(quantity).ok_or(OrderError::TooLarge)?;
+    total(items)?
+        .checked_add(more)
+        .ok_or(OrderError::TooLarge)?;
+    match items
+        .iter_mut()
+        .find(|line| line.item == item && line.unit_price == price)
+    {
+        Some(line) => {
+            line.quantity = line
+                .quantity
+                .checked_add(quantity)
+                .ok_or(OrderError::TooLarge)?
+        }
+        None => items.push(LineItem {
+            item: String::from(item),
+            quantity,
+            unit_price: price,
+        }),
+    }
+    Ok(())
+}
+
+// Takes `quantity` of `item` off `items`, changing nothing if it can't.
+fn remove_line(items: &mut Vec<LineItem>, item: &str, quantity: u32) -> Result<(), OrderError> {
+    let index = items
+        .iter()
+        .position(|line| line.item == item && line.quantity >= quantity)
+        .ok_or_else(|| OrderError::NotOnOrder(String::from(item)))?;
+    items[index].quantity -= quantity;
+    if items[index].quantity == 0 {
+        items.remove(index);
+    }
+    Ok(())
+}
+
+/// What happened to an order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventKind {
+    Placed {
+        table: u32,
+    },
+    ItemAdded {
+        item: String,
+        quantity: u32,
+    },
+    ItemRemoved {
+        item: String,
+        quantity: u32,
+    },
+    CookingStarted,
+    Ready,
+    Served,
+    SentBack {
+        reason: String,
+    },
+    Paid {
+        total: Cents,
+        paid: Cents,
+    },
+    /// An action that was refused, and why.
+    Refused(OrderError),
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventKind::Placed { table } => write!(f, "placed for table {table}"),
+            EventKind::ItemAdded { item, quantity } => write!(f, "added {quantity} x {item}"),
+            EventKind::ItemRemoved { item, quantity } => {
+                write!(f, "removed {quantity} x {item}")
+            }
+            EventKind::CookingStarted => write!(f, "cooking started"),
+            EventKind::Ready => write!(f, "ready"),
+            EventKind::Served => write!(f, "served"),
+            EventKind::SentBack { reason } => write!(f, "sent back: {reason}"),
+            EventKind::Paid { total, paid } => {
+                write!(f, "paid {} for {}", dollars(*paid), dollars(*total))
+            }
+            EventKind::Refused(err) => write!(f, "refused: {err}"),
+        }
+    }
+}
+
+/// An entry in an order's history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub at: Minutes,
+    pub kind: EventKind,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:>4} min  {}", self.at, self.kind)
+    }
+}
+
+/// A table's order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Order {
+    table: u32,
+    items: Vec<LineItem>,
+    state: OrderState,
+    history: Vec<Event>,
+}
+
+impl Order {
+    /// A new order for `table` with nothing on it yet.
+    pub fn new(table: u32, at: Minutes) -> Order {
+        Order {
+            table,
+            items: Vec::new(),
+            state: OrderState::Placed,
+            history: vec![Event {
+                at,
+                kind: EventKind::Placed { table },
+            }],
+        }
+    }
+
+    pub fn table(&self) -> u32 {
+        self.table
+    }
+
+    pub fn state(&self) -> OrderState {
+        self.state
+    }
+
+    pub fn items(&self) -> &[LineItem] {
+        &self.items
+    }
+
+    /// Everything that happened to the order, oldest first.
+    pub fn history(&self) -> &[Event] {
+        &self.history
+    }
+
+    /// What the order comes to.
+    pub fn total(&self) -> Cents {
+        // adding items checks the total still fits, so it always does
+        total(&self.items).expect("an order's total fits in Cents")
+    }
+
+    /// Adds `quantity` of `item` at its price on `menu`. Only before
+    /// cooking starts.
+    pub fn add_item<M: PriceList + ?Sized>(
+        &mut self,
+        menu: &M,
+        item: &str,
+        quantity: u32,
+        at: Minutes,
+    ) -> Result<(), OrderError> {
+        self.allowed(Action::ChangeItems, at)?;
+        self.change_item(menu, item, quantity, at)
+    }
+
+    /// Takes `quantity` of `item` off the order. Only before cooking starts.
+    pub fn remove_item(
+        &mut self,
+        item: &str,
+        quantity: u32,
+        at: Minutes,
+    ) -> Result<(), OrderError> {
+        self.allowed(Action::ChangeItems, at)?;
+        self.take_off(item, quantity, at)
+    }
+
+    pub fn start_cooking(&mut self, at: Minutes) -> Result<(), OrderError> {
+        if self.items.is_empty() {
+            return Err(self.refuse(OrderError::NothingOrdered, at));
+        }
+        self.advance(Action::StartCooking, EventKind::CookingStarted, at)
+    }
+
+    pub fn finish_cooking(&mut self, at: Minutes) -> Result<(), OrderError> {
+        self.advance(Action::FinishCooking, EventKind::Ready, at)
+    }
+
+    pub fn serve(&mut self, at: Minutes) -> Result<(), OrderError> {
+        self.advance(Action::Serve, EventKind::Served, at)
+    }
+
+    /// Sends a ready or served order back to the kitchen to be made again.
+    pub fn send_back(&mut self, reason: &str, at: Minutes) -> Result<(), OrderError> {
+        let kind = EventKind::SentBack {
+            reason: String::from(reason),
+        };
+        self.advance(Action::SendBack, kind, at)
+    }
+
+    /// Settles the bill with `paid`, returning the change.
+    pub fn pay(&mut self, paid: Cents, at: Minutes) -> Result<Cents, OrderError> {
+        self.allowed(Action::Pay, at)?;
+        let total = self.total();
+        if paid < total {
+            return Err(self.refuse(OrderError::Underpaid { due: total, paid }, at));
+        }
+        self.advance(Action::Pay, EventKind::Paid { total, paid }, at)?;
+        Ok(paid - total)
+    }
+
+    /// Changes the items of an order that's been sent back, which is when
+    /// the kitchen may correct it. If any of the changes can't be made,
+    /// none of them are.
+    pub(crate) fn correct<M: PriceList + ?Sized>(
+        &mut self,
+        menu: &M,
+        remove: &[(&str, u32)],
+        add: &[(&str, u32)],
+        at: Minutes,
+    ) -> Result<(), OrderError> {
+        if self.state != OrderState::Cooking || !self.was_sent_back() {
+            return Err(self.refuse(
+                OrderError::IllegalTransition {
+                    from: self.state,
+                    action: Action::ChangeItems,
+                },
+                at,
+            ));
+        }
+        let mut items = self.items.clone();
+        let changed = remove
+            .iter()
+            .try_for_each(|&(item, quantity)| remove_line(&mut items, item, quantity))
+            .and_then(|()| {
+                add.iter()
+                    .try_for_each(|&(item, quantity)| add_line(&mut items, menu, item, quantity))
+            });
+        if let Err(err) = changed {
+            return Err(self.refuse(err, at));
+        }
+
+        self.items = items;
+        for &(item, quantity) in remove {
+            self.record(
+                EventKind::ItemRemoved {
+                    item: String::from(item),
+                    quantity,
+                },
+                at,
+            );
+        }
+        for &(item, quantity) in add {
+            self.record(
+                EventKind::ItemAdded {
+                    item: String::from(item),
+                    quantity,
+                },
+                at,
+            );
+        }
+        Ok(())
+    }
+
+    // whether the last thing that moved the order on was sending it back
+    fn was_sent_back(&self) -> bool {
+        self.history
+            .iter()
+            .rev()
+            .find(|e| {
+                !matches!(
+                    e.kind,
+                    EventKind::Refused(_)
+                        | EventKind::ItemAdded { .. }
+                        | EventKind::ItemRemoved { .. }
+                )
+            })
+            .is_some_and(|e| matches!(e.kind, EventKind::SentBack { .. }))
+    }
+
+    fn change_item<M: PriceList + ?Sized>(
+        &mut self,
+        menu: &M,
+        item: &str,
+        quantity: u32,
+        at: Minutes,
+    ) -> Result<(), OrderError> {
+        if let Err(err) = add_line(&mut self.items, menu, item, quantity) {
+            return Err(self.refuse(err, at));
+        }
+        self.record(
+            EventKind::ItemAdded {
+                item: String::from(item),
+                quantity,
+            },
+            at,
+        );
+        Ok(())
+    }
+
+    fn take_off(&mut self, item: &str, quantity: u32, at: Minutes) -> Result<(), OrderError> {
+        if let Err(err) = remove_line(&mut self.items, item, quantity) {
+            return Err(self.refuse(err, at));
+        }
+        self.record(
+            EventKind::ItemRemoved {
+                item: String::from(item),
+                quantity,
+            },
+            at,
+        );
+        Ok(())
+    }
+
+    // checks that `action` is allowed now, recording it if it isn't
+    fn allowed(&mut self, action: Action, at: Minutes) -> Result<OrderState, OrderError> {
+        match action.next(self.state) {
+            Some(next) => Ok(next),
+            None => Err(self.refuse(
+                OrderError::IllegalTransition {
+                    from: self.state,
+                    action,
+                },
+                at,
+            )),
+        }
+    }
+
+    fn advance(&mut self, action: Action, kind: EventKind, at: Minutes) -> Result<(), OrderError> {
+        self.state = self.allowed(action, at)?;
+        self.record(kind, at);
+        Ok(())
+    }
+
+    fn refuse(&mut self, err: OrderError, at: Minutes) -> OrderError {
+        self.record(EventKind::Refused(err.clone()), at);
+        err
+    }
+
+    fn record(&mut self, kind: EventKind, at: Minutes) {
+        self.history.push(Event { at, kind });
+    }
+}
+
+/// Takes a table's order: each item on `menu` and how many of it.
+pub fn take_order<M: PriceList + ?Sized>(
+    table: u32,
+    items: &[(&str, u32)],
+    menu: &M,
+    at: Minutes,
+) -> Result<Order, OrderError> {
+    if items.is_empty() {
+        return Err(OrderError::NothingOrdered);
+    }
+    let mut order = Order::new(table, at);
+    for &(item, quantity) in items {
+        order.add_item(menu, item, quantity, at)?;
+    }
+    Ok(order)
+}
+
+/// Brings a ready order to its table.
+pub fn serve_order(order: &mut Order, at: Minutes) -> Result<(), OrderError> {
+    order.serve(at)
+}
+
+/// Takes `paid` for a served order, returning the change.
+pub fn take_payment(order: &mut Order, paid: Cents, at: Minutes) -> Result<Cents, OrderError> {
+    order.pay(paid, at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MENU: [(&str, Cents); 4] = [
+        ("soup", 650),
+        ("salad", 800),
+        ("pancakes", 1150),
+        ("coffee", 300),
+    ];
+
+    fn illegal(from: OrderState, action: Action) -> OrderError {
+        OrderError::IllegalTransition { from, action }
+    }
+
+    #[test]
+    fn from_placed_to_paid() {
+        let mut order = take_order(4, &[("soup", 2), ("coffee", 1)], &MENU, 0).unwrap();
+        assert_eq!(order.total(), 2 * 650 + 300);
+        order.add_item(&MENU, "coffee", 1, 2).unwrap();
+        assert_eq!(order.items().len(), 2);
+        assert_eq!(dollars(order.total()), "$19.00");
+
+        order.start_cooking(3).unwrap();
+        order.finish_cooking(15).unwrap();
+        serve_order(&mut order, 16).unwrap();
+        assert_eq!(take_payment(&mut order, 2000, 50), Ok(100));
+        assert_eq!(order.state(), OrderState::Paid);
+
+        let history: Vec<String> = order.history().iter().map(Event::to_string).collect();
+        assert_eq!(
+            history,
+            [
+                "   0 min  placed for table 4",
+                "   0 min  added 2 x soup",
+                "   0 min  added 1 x coffee",
+                "   2 min  added 1 x coffee",
+                "   3 min  cooking started",
+                "  15 min  ready",
+                "  16 min  served",
+                "  50 min  paid $20.00 for $19.00",
+            ]
+        );
+    }
+
+    #[test]
+    fn steps_out_of_order_are_refused() {
+        let mut order = take_order(1, &[("salad", 1)], &MENU, 0).unwrap();
+        assert_eq!(
+            serve_order(&mut order, 1),
+            Err(illegal(OrderState::Placed, Action::Serve))
+        );
+        assert_eq!(
+            take_payment(&mut order, 800, 1),
+            Err(illegal(OrderState::Placed, Action::Pay))
+        );
+        assert_eq!(
+            order.finish_cooking(1),
+            Err(illegal(OrderState::Placed, Action::FinishCooking))
+        );
+
+        order.start_cooking(2).unwrap();
+        assert_eq!(
+            order.add_item(&MENU, "soup", 1, 3),
+            Err(illegal(OrderState::Cooking, Action::ChangeItems))
+        );
+        assert_eq!(
+            order.start_cooking(3),
+            Err(illegal(OrderState::Cooking, Action::StartCooking))
+        );
+        order.finish_cooking(10).unwrap();
+        order.serve(11).unwrap();
+        order.pay(800, 40).unwrap();
+        assert_eq!(
+            order.pay(800, 41),
+            Err(illegal(OrderState::Paid, Action::Pay))
+        );
+        assert_eq!(
+            order.send_back("cold", 42),
+            Err(illegal(OrderState::Paid, Action::SendBack))
+        );
+
+        // nothing refused changed the order, and all of it was written down
+        assert_eq!(order.state(), OrderState::Paid);
+        assert_eq!(order.total(), 800);
+        let refused: Vec<String> = order
+            .history()
+            .iter()
+            .filter(|e| matches!(e.kind, EventKind::Refused(_)))
+            .map(|e| e.kind.to_string())
+            .collect();
+        assert_eq!(refused.len(), 7);
+        assert_eq!(refused[0], "refused: can't serve an order that's placed");
+        assert_eq!(refused[6], "refused: can't send back an order that's paid");
+    }
+
+    #[test]
+    fn bad_orders() {
+        assert_eq!(
+            take_order(1, &[], &MENU, 0),
+            Err(OrderError::NothingOrdered)
+        );
+        assert_eq!(
+            take_order(1, &[("soup", 1), ("lobster", 1)], &MENU, 0),
+            Err(OrderError::UnknownItem(String::from("lobster")))
+        );
+        assert_eq!(
+            take_order(1, &[("soup", 0)], &MENU, 0),
+            Err(OrderError::NothingOrdered)
+        );
+
+        let mut order = Order::new(2, 0);
+        assert_eq!(order.start_cooking(1), Err(OrderError::NothingOrdered));
+        order.add_item(&MENU, "soup", 2, 1).unwrap();
+        assert_eq!(
+            order.remove_item("soup", 3, 2),
+            Err(OrderError::NotOnOrder(String::from("soup")))
+        );
+        order.remove_item("soup", 2, 2).unwrap();
+        assert!(order.items().is_empty());
+    }
+
+    #[test]
+    fn underpaying_leaves_the_bill_open() {
+        let mut order = take_order(3, &[("pancakes", 2)], &MENU, 0).unwrap();
+        order.start_cooking(1).unwrap();
+        order.finish_cooking(9).unwrap();
+        order.serve(10).unwrap();
+        let err = order.pay(2000, 30).unwrap_err();
+        assert_eq!(err.to_string(), "$23.00 is due but only $20.00 was paid");
+        assert_eq!(order.state(), OrderState::Served);
+        assert_eq!(order.pay(2500, 31), Ok(200));
+    }
+
+    #[test]
+    fn orders_too_large_to_add_up_are_refused() {
+        assert_eq!(
+            take_order(1, &[("soup", 7_000_000)], &MENU, 0),
+            Err(OrderError::TooLarge)
+        );
+        assert_eq!(
+            take_order(1, &[("soup", 6_000_000), ("salad", 1_000_000)], &MENU, 0),
+            Err(OrderError::TooLarge)
+        );
+
+        // free items can't overflow the quantity either
+        let free = [("water", 0)];
+        let mut order = take_order(2, &[("water", u32::MAX)], &free, 0).unwrap();
+        assert_eq!(
+            order.add_item(&free, "water", 1, 1),
+            Err(OrderError::TooLarge)
+        );
+        assert_eq!(order.items()[0].quantity, u32::MAX);
+        assert_eq!(order.total(), 0);
+
+        let line = LineItem {
+            item: String::from("soup"),
+            quantity: 7_000_000,
+            unit_price: 650,
+        };
+        assert_eq!(line.total(), Err(OrderError::TooLarge));
+        assert_eq!(
+            OrderError::TooLarge.to_string(),
+            "the order would come to more than $42949672.95"
+        );
+    }
+
+    #[test]
+    fn prices_are_kept_from_when_ordered() {
+        let mut order = take_order(5, &[("coffee", 1)], &MENU, 0).unwrap();
+        // coffee went up while they were deciding on a second
+        order.add_item(&[("coffee", 350)], "coffee", 1, 5).unwrap();
+        assert_eq!(order.items().len(), 2);
+        assert_eq!(order.total(), 650);
+    }
+}
@@ -213,16 +213,19 @@ We only need the 'pub' before the 'enum' keyword as shown in the example below.
 */
 
 //Designating an enum as public makes all its variants public
+//(back_of_house has since moved to src/back_of_house.rs)
+/*
 mod back_of_house {
     pub enum Appetizer {
         Soup,
         Salad,
     }
 }
+*/
 
 pub fn eat_at_restaurant() {
-    let order1 = back_of_house::Appetizer::Soup;
-    let order2 = back_of_house::Appetizer::Salad;
+    let _order1 = back_of_house::Appetizer::Soup;
+    let _order2 = back_of_house::Appetizer::Salad;
 }
 
 /*
@@ -533,4 +536,37 @@ These paths can be brought into scope with a use statement so you can use a
 shorter path for multiple uses of the item in that scope. 
 Module code is private by default, but you can make definitions public by 
 adding the pub keyword.
-*/
\ No newline at end of file
+*/
+
+//The restaurant library
+/*
+Everything above is kept as notes from working through chapter 7, with only
+the Appetizer example left uncommented. The modules that do something now
+live in their own files, the way the chapter ends:
+
+    front_of_house::hosting     the waitlist and the tables, and seating
+                                parties at them (see front_of_house/hosting.rs)
+    front_of_house::serving     orders, from taking them to being paid
+                                (see front_of_house/serving.rs)
+    back_of_house               the kitchen, which cooks orders and fixes
+                                wrong ones (see back_of_house.rs)
+    menu                        what's served, loaded from a file like
+                                menu.txt, and queries on it (see menu.rs)
+
+'hosting' is re-exported, so it's also restaurant::hosting.
+*/
+
+pub mod back_of_house;
+pub mod front_of_house;
+pub mod menu;
+
+pub use crate::front_of_house::hosting;
+
+use crate::front_of_house::serving::{self, Order, OrderError};
+use crate::hosting::Minutes;
+
+// the kitchen hands fixed orders to the waiters through this, as in the
+// book's fix_incorrect_order
+fn deliver_order(order: &mut Order, at: Minutes) -> Result<(), OrderError> {
+    serving::serve_order(order, at)
+}
@@ -0,0 +1,608 @@
+//The menu
+/*
+What the restaurant serves: each item's name, which part of the menu it's
+in, its price, the seasons it's available in, the allergens it contains
+and whether it's vegetarian or vegan. Before this, the menu was spread over
+the code: Appetizer had Soup and Salad and nothing else, and
+Breakfast::summer always came with peaches.
+
+A menu is kept in a plain text file, one item to a line, under a heading
+for its category. Fields are separated by '|'; the ones at the end can be
+left off. A '#' starts a comment.
+
+    [breakfast]
+    # name         | price | seasons        | allergens          | diet
+    Pancakes       | 11.50 | all            | gluten, dairy, egg | vegetarian
+    Fruit porridge | 8     | autumn, winter | gluten             | vegan
+
+    [fruit]
+    Peaches        | 3.50  | summer
+
+    price       dollars, with up to two figures of cents
+    seasons     'all', or any of spring, summer, autumn (or fall), winter
+    allergens   any of gluten, dairy, egg, nuts, peanuts, soy, fish,
+                shellfish, sesame; none when left empty
+    diet        vegetarian or vegan; vegan items are vegetarian too
+
+A mistake in the file is reported with its line number, so it can be found
+and fixed. Prices are kept in cents (see serving.rs), and a Menu is a
+PriceList, so orders can be taken from it.
+
+Queries pick items out of a menu, one condition at a time:
+
+    menu.query()
+        .category(Category::Breakfast)
+        .vegetarian()
+        .available_in(Season::Summer)
+        .items()
+*/
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::front_of_house::serving::{Cents, PriceList};
+
+/// A part of the menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    Breakfast,
+    Appetizer,
+    Main,
+    Side,
+    Fruit,
+    Dessert,
+    Drink,
+}
+
+impl FromStr for Category {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Category, String> {
+        match text.to_lowercase().as_str() {
+            "breakfast" => Ok(Category::Breakfast),
+            "appetizer" | "appetizers" => Ok(Category::Appetizer),
+            "main" | "mains" => Ok(Category::Main),
+            "side" | "sides" => Ok(Category::Side),
+            "fruit" => Ok(Category::Fruit),
+            "dessert" | "desserts" => Ok(Category::Dessert),
+            "drink" | "drinks" => Ok(Category::Drink),
+            _ => Err(format!("unknown category '{text}'")),
+        }
+    }
+}
+
+/// A season of the year.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    pub const ALL: [Season; 4] = [
+        Season::Spring,
+        Season::Summer,
+        Season::Autumn,
+        Season::Winter,
+    ];
+}
+
+impl FromStr for Season {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Season, String> {
+        match text.to_lowercase().as_str() {
+            "spring" => Ok(Season::Spring),
+            "summer" => Ok(Season::Summer),
+            "autumn" | "fall" => Ok(Season::Autumn),
+            "winter" => Ok(Season::Winter),
+            _ => Err(format!("unknown season '{text}'")),
+        }
+    }
+}
+
+/// Something some guests can't eat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Allergen {
+    Gluten,
+    Dairy,
+    Egg,
+    Nuts,
+    Peanuts,
+    Soy,
+    Fish,
+    Shellfish,
+    Sesame,
+}
+
+impl FromStr for Allergen {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Allergen, String> {
+        match text.to_lowercase().as_str() {
+            "gluten" => Ok(Allergen::Gluten),
+            "dairy" => Ok(Allergen::Dairy),
+            "egg" | "eggs" => Ok(Allergen::Egg),
+            "nuts" => Ok(Allergen::Nuts),
+            "peanuts" => Ok(Allergen::Peanuts),
+            "soy" => Ok(Allergen::Soy),
+            "fish" => Ok(Allergen::Fish),
+            "shellfish" => Ok(Allergen::Shellfish),
+            "sesame" => Ok(Allergen::Sesame),
+            _ => Err(format!("unknown allergen '{text}'")),
+        }
+    }
+}
+
+/// What kind of diet an item suits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub enum Diet {
+    #[default]
+    Any,
+    Vegetarian,
+    Vegan,
+}
+
+/// One thing on the menu.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MenuItem {
+    pub name: String,
+    pub category: Category,
+    pub price: Cents,
+    /// The seasons it's served in.
+    pub seasons: Vec<Season>,
+    pub allergens: Vec<Allergen>,
+    pub diet: Diet,
+}
+
+impl MenuItem {
+    pub fn available_in(&self, season: Season) -> bool {
+        self.seasons.contains(&season)
+    }
+
+    pub fn contains(&self, allergen: Allergen) -> bool {
+        self.allergens.contains(&allergen)
+    }
+
+    pub fn is_vegetarian(&self) -> bool {
+        self.diet >= Diet::Vegetarian
+    }
+
+    pub fn is_vegan(&self) -> bool {
+        self.diet == Diet::Vegan
+    }
+}
+
+/// A mistake in a menu file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseMenuError {
+    /// Counting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseMenuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseMenuError {}
+
+/// Why a menu file couldn't be loaded.
+#[derive(Debug)]
+pub enum MenuError {
+    Io(io::Error),
+    Parse(ParseMenuError),
+}
+
+impl fmt::Display for MenuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MenuError::Io(err) => write!(f, "{err}"),
+            MenuError::Parse(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for MenuError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MenuError::Io(err) => Some(err),
+            MenuError::Parse(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for MenuError {
+    fn from(err: io::Error) -> MenuError {
+        MenuError::Io(err)
+    }
+}
+
+impl From<ParseMenuError> for MenuError {
+    fn from(err: ParseMenuError) -> MenuError {
+        MenuError::Parse(err)
+    }
+}
+
+/// Everything the restaurant serves, in the order of the menu file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Menu {
+    items: Vec<MenuItem>,
+}
+
+impl Menu {
+    /// Reads a menu file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Menu, MenuError> {
+        let text = fs::read_to_string(path)?;
+        Ok(text.parse()?)
+    }
+
+    pub fn items(&self) -> &[MenuItem] {
+        &self.items
+    }
+
+    /// The item called `name`, ignoring case.
+    pub fn get(&self, name: &str) -> Option<&MenuItem> {
+        self.items
+            .iter()
+            .find(|item| item.name.eq_ignore_ascii_case(name))
+    }
+
+    /// A query matching every item, to be narrowed down.
+    pub fn query(&self) -> Query<'_> {
+        Query {
+            menu: self,
+            conditions: Vec::new(),
+        }
+    }
+}
+
+impl PriceList for Menu {
+    fn price_of(&self, item: &str) -> Option<Cents> {
+        self.get(item).map(|item| item.price)
+    }
+}
+
+impl FromStr for Menu {
+    type Err = ParseMenuError;
+
+    fn from_str(text: &str) -> Result<Menu, ParseMenuError> {
+        let mut menu = Menu::default();
+        let mut category = None;
+
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: String| ParseMenuError {
+                line: index + 1,
+                message,
+            };
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(heading) = line.strip_prefix('[') {
+                let name = heading
+                    .strip_suffix(']')
+                    .ok_or_else(|| error(String::from("a heading needs a closing ']'")))?;
+                category = Some(name.trim().parse().map_err(error)?);
+                continue;
+            }
+
+            let category = category.ok_or_else(|| {
+                error(String::from("an item needs a [category] heading above it"))
+            })?;
+            let item = parse_item(line, category).map_err(error)?;
+            if menu.get(&item.name).is_some() {
+                return Err(error(format!("'{}' is already on the menu", item.name)));
+            }
+            menu.items.push(item);
+        }
+
+        Ok(menu)
+    }
+}
+
+// name | price | seasons | allergens | diet
+fn parse_item(line: &str, category: Category) -> Result<MenuItem, String> {
+    let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+    if fields.len() > 5 {
+        return Err(format!(
+            "an item has at most 5 fields, not {}",
+            fields.len()
+        ));
+    }
+    let field = |i: usize| fields.get(i).copied().unwrap_or_default();
+
+    let name = field(0);
+    if name.is_empty() {
+        return Err(String::from("an item needs a name"));
+    }
+    if fields.len() < 2 {
+        return Err(format!("'{name}' needs a price"));
+    }
+    let price = parse_price(field(1))?;
+
+    let seasons = match field(2) {
+        "" | "all" => Season::ALL.to_vec(),
+        list => parse_list(list)?,
+    };
+    let allergens = parse_list(field(3))?;
+    let diet = match field(4).to_lowercase().as_str() {
+        "" => Diet::Any,
+        "vegetarian" => Diet::Vegetarian,
+        "vegan" => Diet::Vegan,
+        other => return Err(format!("unknown diet '{other}'")),
+    };
+
+    Ok(MenuItem {
+        name: String::from(name),
+        category,
+        price,
+        seasons,
+        allergens,
+        diet,
+    })
+}
+
+// a comma separated list, without repeats; empty when the text is
+fn parse_list<T: FromStr<Err = String> + PartialEq>(text: &str) -> Result<Vec<T>, String> {
+    let mut list = Vec::new();
+    for word in text.split(',').map(str::trim).filter(|w| !w.is_empty()) {
+        let value = word.parse()?;
+        if !list.contains(&value) {
+            list.push(value);
+        }
+    }
+    Ok(list)
+}
+
+// "11.50", "11.5" or "11" dollars, to cents
+fn parse_price(text: &str) -> Result<Cents, String> {
+    let invalid = || format!("'{text}' isn't a price");
+    let (dollars, cents) = text.split_once('.').unwrap_or((text, ""));
+    let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    let no_cents = text.ends_with('.');
+    if dollars.is_empty()
+        || no_cents
+        || !all_digits(dollars)
+        || !all_digits(cents)
+        || cents.len() > 2
+    {
+        return Err(invalid());
+    }
+    let cents = match cents.len() {
+        0 => 0,
+        1 => cents.parse::<Cents>().map_err(|_| invalid())? * 10,
+        _ => cents.parse::<Cents>().map_err(|_| invalid())?,
+    };
+    dollars
+        .parse::<Cents>()
+        .ok()
+        .and_then(|d| d.checked_mul(100))
+        .and_then(|d| d.checked_add(cents))
+        .ok_or_else(invalid)
+}
+
+type Condition<'m> = Box<dyn Fn(&MenuItem) -> bool + 'm>;
+
+/// Items of a menu that meet every condition given so far.
+pub struct Query<'m> {
+    menu: &'m Menu,
+    conditions: Vec<Condition<'m>>,
+}
+
+impl<'m> Query<'m> {
+    fn only(mut self, condition: impl Fn(&MenuItem) -> bool + 'm) -> Query<'m> {
+        self.conditions.push(Box::new(condition));
+        self
+    }
+
+    pub fn category(self, category: Category) -> Query<'m> {
+        self.only(move |item| item.category == category)
+    }
+
+    pub fn available_in(self, season: Season) -> Query<'m> {
+        self.only(move |item| item.available_in(season))
+    }
+
+    pub fn vegetarian(self) -> Query<'m> {
+        self.only(MenuItem::is_vegetarian)
+    }
+
+    pub fn vegan(self) -> Query<'m> {
+        self.only(MenuItem::is_vegan)
+    }
+
+    /// Leaves out items containing `allergen`.
+    pub fn without(self, allergen: Allergen) -> Query<'m> {
+        self.only(move |item| !item.contains(allergen))
+    }
+
+    pub fn at_most(self, price: Cents) -> Query<'m> {
+        self.only(move |item| item.price <= price)
+    }
+
+    /// The matching items, in menu order.
+    pub fn items(&self) -> Vec<&'m MenuItem> {
+        self.menu
+            .items
+            .iter()
+            .filter(|item| self.conditions.iter().all(|matches| matches(item)))
+            .collect()
+    }
+
+    /// The names of the matching items, in menu order.
+    pub fn names(&self) -> Vec<&'m str> {
+        self.items()
+            .into_iter()
+            .map(|item| item.name.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::front_of_house::serving::take_order;
+
+    fn sample() -> Menu {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("menu.txt");
+        Menu::load(path).unwrap()
+    }
+
+    fn parse_error(text: &str) -> String {
+        text.parse::<Menu>().unwrap_err().to_string()
+    }
+
+    #[test]
+    fn loads_the_menu_file() {
+        let menu = sample();
+        let pancakes = menu.get("pancakes").unwrap();
+        assert_eq!(pancakes.name, "Pancakes");
+        assert_eq!(pancakes.category, Category::Breakfast);
+        assert_eq!(pancakes.price, 1150);
+        assert_eq!(pancakes.seasons, Season::ALL);
+        assert_eq!(
+            pancakes.allergens,
+            [Allergen::Gluten, Allergen::Dairy, Allergen::Egg]
+        );
+        assert!(pancakes.is_vegetarian() && !pancakes.is_vegan());
+        assert_eq!(menu.price_of("Tomato soup"), Some(650));
+        assert_eq!(menu.price_of("Lobster"), None);
+    }
+
+    #[test]
+    fn vegetarian_breakfasts_in_summer() {
+        let menu = sample();
+        let found = menu
+            .query()
+            .category(Category::Breakfast)
+            .vegetarian()
+            .available_in(Season::Summer)
+            .names();
+        assert_eq!(found, ["Pancakes", "Yogurt with berries", "Avocado toast"]);
+    }
+
+    #[test]
+    fn more_queries() {
+        let menu = sample();
+        assert_eq!(
+            menu.query()
+                .category(Category::Fruit)
+                .available_in(Season::Winter)
+                .names(),
+            ["Oranges"]
+        );
+        assert_eq!(
+            menu.query()
+                .category(Category::Main)
+                .without(Allergen::Gluten)
+                .without(Allergen::Fish)
+                .names(),
+            ["Roast chicken", "Mushroom risotto"]
+        );
+        assert_eq!(
+            menu.query().vegan().at_most(500).names(),
+            [
+                "Peaches",
+                "Strawberries",
+                "Oranges",
+                "Apples",
+                "Espresso",
+                "Lemonade"
+            ]
+        );
+        assert_eq!(menu.query().items().len(), menu.items().len());
+    }
+
+    #[test]
+    fn orders_are_priced_from_the_menu() {
+        let menu = sample();
+        let order = take_order(2, &[("Pancakes", 2), ("espresso", 2)], &menu, 0).unwrap();
+        assert_eq!(order.total(), 2 * 1150 + 2 * 300);
+    }
+
+    #[test]
+    fn prices() {
+        assert_eq!(parse_price("11.50"), Ok(1150));
+        assert_eq!(parse_price("11.5"), Ok(1150));
+        assert_eq!(parse_price("7"), Ok(700));
+        assert_eq!(parse_price("0.05"), Ok(5));
+        for bad in ["", ".50", "1.", "1.234", "1,50", "-1", "$3", "99999999"] {
+            assert!(parse_price(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn mistakes_are_reported_with_their_line() {
+        assert_eq!(
+            parse_error("Toast | 2"),
+            "line 1: an item needs a [category] heading above it"
+        );
+        assert_eq!(
+            parse_error("\n# the menu\n[brunch]\n"),
+            "line 3: unknown category 'brunch'"
+        );
+        assert_eq!(
+            parse_error("[drinks\n"),
+            "line 1: a heading needs a closing ']'"
+        );
+        assert_eq!(
+            parse_error("[drinks]\nTea\n"),
+            "line 2: 'Tea' needs a price"
+        );
+        assert_eq!(
+            parse_error("[drinks]\nTea | two\n"),
+            "line 2: 'two' isn't a price"
+        );
+        assert_eq!(
+            parse_error("[drinks]\n| 2\n"),
+            "line 2: an item needs a name"
+        );
+        assert_eq!(
+            parse_error("[drinks]\nTea | 2 | monsoon\n"),
+            "line 2: unknown season 'monsoon'"
+        );
+        assert_eq!(
+            parse_error("[drinks]\nTea | 2 | all | caffeine\n"),
+            "line 2: unknown allergen 'caffeine'"
+        );
+        assert_eq!(
+            parse_error("[drinks]\nTea | 2 | all | | keto\n"),
+            "line 2: unknown diet 'keto'"
+        );
+        assert_eq!(
+            parse_error("[drinks]\nTea | 2 | all | | | hot\n"),
+            "line 2: an item has at most 5 fields, not 6"
+        );
+        assert_eq!(
+            parse_error("[drinks]\nTea | 2\n\n[desserts]\ntea | 3\n"),
+            "line 5: 'tea' is already on the menu"
+        );
+    }
+
+    #[test]
+    fn short_lines_take_defaults() {
+        let menu: Menu = "[fruit]\nPears | 3 # all year\n".parse().unwrap();
+        let pears = &menu.items()[0];
+        assert_eq!(pears.seasons, Season::ALL);
+        assert!(pears.allergens.is_empty());
+        assert_eq!(pears.diet, Diet::Any);
+    }
+
+    #[test]
+    fn a_missing_file_is_an_io_error() {
+        assert!(matches!(
+            Menu::load("no such menu.txt"),
+            Err(MenuError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,152 @@
+#!/usr/bin/env python3
+"""Writes src/unicode_tables.rs from Python's copy of the Unicode Character
+Database. Run it from the top of the repository:
+
+    python3 scripts/unicode_tables.py > src/unicode_tables.rs
+
+The tables follow whatever Unicode version the Python running it has, which
+is written at the top of the file.
+"""
+
+import sys
+import unicodedata
+
+PER_LINE = 4
+
+
+def escape_char(c):
+    # printable ASCII as itself, everything else as \u{...}
+    if " " <= c <= "~" and c not in "\\'\"":
+        return c
+    return "\\u{%x}" % ord(c)
+
+
+def char_literal(c):
+    return "'\\u{%x}'" % ord(c)
+
+
+def str_literal(s):
+    return '"' + "".join(escape_char(c) for c in s) + '"'
+
+
+def table(comment, declaration, entries):
+    lines = ["// " + comment, "#[rustfmt::skip]", declaration + " = &["]
+    for i in range(0, len(entries), PER_LINE):
+        lines.append("    " + " ".join(e + "," for e in entries[i : i + PER_LINE]))
+    lines.append("];")
+    return "\n".join(lines)
+
+
+def decompositions():
+    # one level deep, as unicodedata gives them; Hangul syllables have
+    # none here because unicode.rs works them out by arithmetic
+    found = []
+    for code in range(sys.maxunicode + 1):
+        c = chr(code)
+        decomposition = unicodedata.decomposition(c)
+        if not decomposition or decomposition.startswith("<"):
+            continue
+        parts = "".join(chr(int(part, 16)) for part in decomposition.split())
+        found.append((c, parts))
+    return found
+
+
+def compositions(decomposed):
+    # The pairs that NFC puts back together. Characters that decompose to a
+    # single character, or to something starting with a mark, and the ones
+    # in the composition exclusion list never come back, and for each of
+    # them NFC gives something other than the character itself.
+    found = []
+    for c, parts in decomposed:
+        if len(parts) == 2 and unicodedata.normalize("NFC", c) == c:
+            found.append((parts[0], parts[1], c))
+    return sorted(found)
+
+
+def combining_classes():
+    runs = []
+    for code in range(sys.maxunicode + 1):
+        cls = unicodedata.combining(chr(code))
+        if not cls:
+            continue
+        if runs and runs[-1][1] == code - 1 and runs[-1][2] == cls:
+            runs[-1][1] = code
+        else:
+            runs.append([code, code, cls])
+    return runs
+
+
+def case_folding():
+    found = []
+    for code in range(sys.maxunicode + 1):
+        c = chr(code)
+        # Cherokee folds to its capitals, which unicode.rs does by rule
+        if 0x13A0 <= code <= 0x13FD or 0xAB70 <= code <= 0xABBF:
+            continue
+        if c.casefold() != c.lower():
+            found.append((c, c.casefold()))
+    return found
+
+
+def main():
+    decomposed = decompositions()
+    print(
+        """//Unicode data tables for unicode.rs
+// Generated from the Unicode Character Database, version %s, by
+// scripts/unicode_tables.py with Python's unicodedata module. They hold:
+//
+//     decompositions: every character whose unicodedata.decomposition() is
+//         canonical (doesn't start with '<'), and the characters it
+//         decomposes to, one level deep
+//     compositions: the pairs that NFC composes back into one character,
+//         leaving out the composition exclusions
+//     combining classes: runs of characters with the same nonzero
+//         unicodedata.combining(), as (first, last, class)
+//     case folding: every character where str.casefold() differs from
+//         str.lower(), except Cherokee, which unicode.rs handles by rule
+//
+// Hangul syllables are taken apart and put together by arithmetic instead.
+"""
+        % unicodedata.unidata_version
+    )
+    print(
+        table(
+            "(character, canonical decomposition), sorted by character",
+            "pub(crate) const DECOMPOSITIONS: &[(char, &str)]",
+            ["(%s, %s)" % (char_literal(c), str_literal(parts)) for c, parts in decomposed],
+        )
+    )
+    print()
+    print(
+        table(
+            "(first, second, composed), sorted by the pair",
+            "pub(crate) const COMPOSITIONS: &[(char, char, char)]",
+            [
+                "(%s, %s, %s)" % (char_literal(a), char_literal(b), char_literal(c))
+                for a, b, c in compositions(decomposed)
+            ],
+        )
+    )
+    print()
+    print(
+        table(
+            "(first, last, canonical combining class), sorted",
+            "pub(crate) const COMBINING_CLASSES: &[(char, char, u8)]",
+            [
+                "(%s, %s, %d)" % (char_literal(chr(first)), char_literal(chr(last)), cls)
+                for first, last, cls in combining_classes()
+            ],
+        )
+    )
+    print()
+    print(
+        table(
+            "(character, full case folding) where it isn't just the lowercase, sorted",
+            "pub(crate) const CASE_FOLDING: &[(char, &str)]",
+            ["(%s, %s)" % (char_literal(c), str_literal(folded)) for c, folded in case_folding()],
+        )
+    )
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,166 @@
+//Highlighting matches with ANSI colors
+/*
+When the output goes to a terminal, matches are shown in bold red, file names
+in magenta, line numbers in green and separators in cyan, the same scheme grep
+uses. '--color' picks when to do this:
+
+    --color=auto     only when stdout is a terminal (the default)
+    --color=always   even into a pipe or file, e.g. for 'less -R'
+    --color=never    never
+
+Following https://no-color.org, setting the NO_COLOR environment variable to
+anything but an empty string turns 'auto' off. An explicit '--color=always'
+still wins, since it was asked for on this particular command line.
+
+The matches are found as byte ranges, and the escape codes are inserted at
+exactly those byte offsets, so a match inside multibyte UTF-8 text is never
+split in the middle of a character.
+*/
+
+use std::fmt;
+use std::io::{self, Write};
+
+pub const MATCH: &str = "\x1b[1;31m";
+pub const PATH: &str = "\x1b[35m";
+pub const LINE_NUMBER: &str = "\x1b[32m";
+pub const SEPARATOR: &str = "\x1b[36m";
+pub const RESET: &str = "\x1b[0m";
+
+/// When to color the output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Parses the value given to `--color`.
+    pub fn parse(value: &str) -> Result<ColorChoice, &'static str> {
+        match value {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err("Color must be auto, always or never"),
+        }
+    }
+
+    /// Decides what `Auto` means, given whether the output is a terminal and
+    /// the value of NO_COLOR. `Always` and `Never` are left as they are.
+    pub fn resolve(self, is_terminal: bool, no_color: Option<&str>) -> ColorChoice {
+        match self {
+            ColorChoice::Auto => {
+                let no_color = no_color.is_some_and(|v| !v.is_empty());
+                if is_terminal && !no_color {
+                    ColorChoice::Always
+                } else {
+                    ColorChoice::Never
+                }
+            }
+            choice => choice,
+        }
+    }
+}
+
+impl fmt::Display for ColorChoice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ColorChoice::Auto => "auto",
+            ColorChoice::Always => "always",
+            ColorChoice::Never => "never",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Writes `text` wrapped in `color` and a reset.
+pub fn write_colored<W: Write>(out: &mut W, color: &str, text: &str) -> io::Result<()> {
+    write!(out, "{color}{text}{RESET}")
+}
+
+/// Writes `line` with every byte range in `matches` highlighted. The ranges
+/// must be in order, not overlap, and fall on character boundaries.
+pub fn write_highlighted<W: Write>(
+    out: &mut W,
+    line: &str,
+    matches: &[(usize, usize)],
+) -> io::Result<()> {
+    let mut last = 0;
+    for &(start, end) in matches {
+        // empty matches (like from 'x*') have nothing to show
+        if start == end {
+            continue;
+        }
+        write!(out, "{}", &line[last..start])?;
+        write_colored(out, MATCH, &line[start..end])?;
+        last = end;
+    }
+    write!(out, "{}", &line[last..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlight(line: &str, matches: &[(usize, usize)]) -> String {
+        let mut out = Vec::new();
+        write_highlighted(&mut out, line, matches).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn resolving_the_choice() {
+        let cases = [
+            (ColorChoice::Auto, true, None, ColorChoice::Always),
+            (ColorChoice::Auto, false, None, ColorChoice::Never),
+            (ColorChoice::Auto, true, Some("1"), ColorChoice::Never),
+            // an empty NO_COLOR counts as unset
+            (ColorChoice::Auto, true, Some(""), ColorChoice::Always),
+            (ColorChoice::Always, false, Some("1"), ColorChoice::Always),
+            (ColorChoice::Never, true, None, ColorChoice::Never),
+        ];
+        for (choice, is_terminal, no_color, expected) in cases {
+            assert_eq!(
+                choice.resolve(is_terminal, no_color),
+                expected,
+                "{choice:?} tty={is_terminal} NO_COLOR={no_color:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parsing_the_choice() {
+        assert_eq!(ColorChoice::parse("always"), Ok(ColorChoice::Always));
+        assert_eq!(ColorChoice::parse("never"), Ok(ColorChoice::Never));
+        assert_eq!(ColorChoice::parse("auto"), Ok(ColorChoice::Auto));
+        assert!(ColorChoice::parse("sometimes").is_err());
+    }
+
+    #[test]
+    fn highlights_byte_ranges() {
+        assert_eq!(
+            highlight("Are you nobody, too?", &[(16, 18)]),
+            "Are you nobody, \x1b[1;31mto\x1b[0mo?"
+        );
+        assert_eq!(
+            highlight("to be", &[(0, 2), (3, 5)]),
+            "\x1b[1;31mto\x1b[0m \x1b[1;31mbe\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn multibyte_text() {
+        // 'é' is two bytes and '🐸' is four
+        let line = "café 🐸 étang";
+        assert_eq!(
+            highlight(line, &[(3, 5), (6, 10), (11, 13)]),
+            "caf\x1b[1;31mé\x1b[0m \x1b[1;31m🐸\x1b[0m \x1b[1;31mé\x1b[0mtang"
+        );
+    }
+
+    #[test]
+    fn empty_matches_are_skipped() {
+        assert_eq!(highlight("bog", &[(0, 0), (3, 3)]), "bog");
+    }
+}
@@ -0,0 +1,377 @@
+//Approximate matching within an edit distance
+/*
+Notes and poems aren't always spelled the same way twice. '--fuzzy K' finds
+the places where a line contains the query with at most K edits, where an
+edit is inserting, deleting or changing one character. That count is the
+Levenshtein distance, so
+
+    minigrep --fuzzy 1 executed notes.txt
+
+also finds "exevuted", and "bouth_groups" is one edit away from "both_groups".
+
+The textbook way to find this is a table with one row per query character
+and one column per line character, where each cell is the distance so far.
+Filling it takes (query length) x (line length) steps. Gene Myers noticed
+that neighbouring cells only ever differ by -1, 0 or +1, so a whole column
+fits in a few bit vectors, one bit per query character, and the next column
+can be worked out with a handful of AND, OR, XOR, shifts and one addition.
+For queries of up to 64 characters a column is a single u64, and the search
+runs in one pass over the line ("A fast bit-vector algorithm for approximate
+string matching based on dynamic programming", 1999). Longer queries fall
+back to filling in the table the slow way.
+
+Each pass gives, for every position in the line, the fewest edits needed
+for the query to end there. A match ends where that number is lowest within
+a run of positions that are all close enough, and its start is found by
+running the table backwards from the end. Distances count characters, not
+bytes, and the reported ranges are byte offsets like every other match.
+*/
+
+use std::collections::HashMap;
+
+/// A match found by `Fuzzy`: its byte range in the line and how many edits
+/// away from the query it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub start: usize,
+    pub end: usize,
+    pub distance: usize,
+}
+
+/// A query to look for with up to `max_distance` edits.
+#[derive(Debug, Clone)]
+pub struct Fuzzy {
+    pattern: Vec<char>,
+    max_distance: usize,
+    ignore_case: bool,
+    // bit i is set where pattern[i] is the character; only for up to 64
+    ascii: [u64; 128],
+    other: HashMap<char, u64>,
+}
+
+impl Fuzzy {
+    pub fn new(pattern: &str, max_distance: usize, ignore_case: bool) -> Fuzzy {
+        let pattern: Vec<char> = pattern.chars().map(|c| fold(c, ignore_case)).collect();
+
+        let mut ascii = [0; 128];
+        let mut other = HashMap::new();
+        if pattern.len() <= 64 {
+            for (i, &c) in pattern.iter().enumerate() {
+                match ascii.get_mut(c as usize) {
+                    Some(mask) => *mask |= 1 << i,
+                    None => *other.entry(c).or_insert(0) |= 1 << i,
+                }
+            }
+        }
+
+        Fuzzy {
+            pattern,
+            max_distance,
+            ignore_case,
+            ascii,
+            other,
+        }
+    }
+
+    /// Every non-overlapping match in `line`, from left to right.
+    pub fn find_all(&self, line: &str) -> Vec<FuzzyMatch> {
+        let chars: Vec<(usize, char)> = line
+            .char_indices()
+            .map(|(i, c)| (i, fold(c, self.ignore_case)))
+            .collect();
+        let text: Vec<char> = chars.iter().map(|&(_, c)| c).collect();
+        let byte_at = |i: usize| chars.get(i).map_or(line.len(), |&(offset, _)| offset);
+
+        if self.pattern.is_empty() {
+            return vec![FuzzyMatch {
+                start: 0,
+                end: 0,
+                distance: 0,
+            }];
+        }
+
+        let scores = self.scores(&text);
+        let mut found = Vec::new();
+        // where the last match ended, as a character index
+        let mut taken = 0;
+        let mut i = 0;
+        while i < scores.len() {
+            if scores[i] > self.max_distance {
+                i += 1;
+                continue;
+            }
+            // the best end in this run of close-enough positions, and the
+            // last of equally good ones, so "froog" isn't cut short at "fro"
+            let mut best = i;
+            while i < scores.len() && scores[i] <= self.max_distance {
+                if scores[i] <= scores[best] {
+                    best = i;
+                }
+                i += 1;
+            }
+
+            let end = best + 1;
+            let (length, distance) = self.best_start(&text[taken..end]);
+            if distance <= self.max_distance {
+                found.push(FuzzyMatch {
+                    start: byte_at(end - length),
+                    end: byte_at(end),
+                    distance,
+                });
+                taken = end;
+            }
+        }
+        found
+    }
+
+    // The distance of the best match ending after each character of `text`.
+    fn scores(&self, text: &[char]) -> Vec<usize> {
+        if self.pattern.len() > 64 {
+            return self.scores_by_table(text);
+        }
+
+        let m = self.pattern.len();
+        let last = 1u64 << (m - 1);
+        // the column, as +1 (pv) and -1 (mv) steps down from the row above
+        let mut pv = u64::MAX;
+        let mut mv = 0u64;
+        let mut score = m;
+
+        let mut scores = Vec::with_capacity(text.len());
+        for &c in text {
+            let eq = self.mask(c);
+            let xv = eq | mv;
+            let xh = ((eq & pv).wrapping_add(pv) ^ pv) | eq;
+            // the same steps, but along the row below
+            let mut ph = mv | !(xh | pv);
+            let mut mh = pv & xh;
+            if ph & last != 0 {
+                score += 1;
+            } else if mh & last != 0 {
+                score -= 1;
+            }
+            // the top row is always 0, since a match may start anywhere
+            ph <<= 1;
+            mh <<= 1;
+            pv = mh | !(xv | ph);
+            mv = ph & xv;
+            scores.push(score);
+        }
+        scores
+    }
+
+    // The same as `scores`, one cell at a time.
+    fn scores_by_table(&self, text: &[char]) -> Vec<usize> {
+        let mut column: Vec<usize> = (0..=self.pattern.len()).collect();
+        let mut scores = Vec::with_capacity(text.len());
+        for &c in text {
+            let mut diagonal = column[0];
+            for (i, &p) in self.pattern.iter().enumerate() {
+                let changed = diagonal + usize::from(p != c);
+                diagonal = column[i + 1];
+                column[i + 1] = changed.min(column[i + 1] + 1).min(column[i] + 1);
+            }
+            scores.push(column[self.pattern.len()]);
+        }
+        scores
+    }
+
+    // Looks backwards from the end of `text` for the longest tail that is
+    // as close as possible to the pattern. Returns its length in characters
+    // and its distance.
+    fn best_start(&self, text: &[char]) -> (usize, usize) {
+        // one row per tail length, one column per pattern suffix length
+        let mut row: Vec<usize> = (0..=self.pattern.len()).collect();
+        let mut best = (0, row[self.pattern.len()]);
+        let longest = text.len().min(self.pattern.len() + self.max_distance);
+
+        for length in 1..=longest {
+            let c = text[text.len() - length];
+            let mut diagonal = row[0];
+            row[0] = length;
+            for (i, &p) in self.pattern.iter().rev().enumerate() {
+                let changed = diagonal + usize::from(p != c);
+                diagonal = row[i + 1];
+                row[i + 1] = changed.min(row[i + 1] + 1).min(row[i] + 1);
+            }
+            if row[self.pattern.len()] <= best.1 {
+                best = (length, row[self.pattern.len()]);
+            }
+        }
+        best
+    }
+
+    fn mask(&self, c: char) -> u64 {
+        match self.ascii.get(c as usize) {
+            Some(&mask) => mask,
+            None => self.other.get(&c).copied().unwrap_or(0),
+        }
+    }
+}
+
+/// The Levenshtein distance between `a` and `b`, counted in characters.
+pub fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let changed = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = changed.min(row[j + 1] + 1).min(row[j] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+// Lowercases a character for IGNORE_CASE, as long as it stays one character
+// so that positions still line up.
+fn fold(c: char, ignore_case: bool) -> char {
+    if !ignore_case {
+        return c;
+    }
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POEM: &str = include_str!("../poem.txt");
+
+    fn matches<'a>(pattern: &str, k: usize, line: &'a str) -> Vec<(&'a str, usize)> {
+        Fuzzy::new(pattern, k, false)
+            .find_all(line)
+            .into_iter()
+            .map(|m| (&line[m.start..m.end], m.distance))
+            .collect()
+    }
+
+    #[test]
+    fn levenshtein_distance() {
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("exevuted", "executed"), 1);
+        assert_eq!(distance("bouth_groups", "both_groups"), 1);
+        assert_eq!(distance("", "frog"), 4);
+        assert_eq!(distance("frog", "frog"), 0);
+        assert_eq!(distance("été", "ete"), 2);
+    }
+
+    #[test]
+    fn exact_and_near_matches() {
+        assert_eq!(
+            matches("frog", 0, "How public, like a frog"),
+            vec![("frog", 0)]
+        );
+        assert_eq!(
+            matches("frog", 1, "How public, like a frg"),
+            vec![("frg", 1)]
+        );
+        assert_eq!(
+            matches("frog", 1, "a froog and a fog"),
+            vec![("froog", 1), ("fog", 1)]
+        );
+        assert_eq!(matches("frog", 1, "How dreary to be somebody!"), vec![]);
+    }
+
+    #[test]
+    fn typos_in_the_notes() {
+        assert_eq!(
+            matches("executed", 1, "the code is exevuted line by line"),
+            vec![("exevuted", 1)]
+        );
+        assert_eq!(
+            matches("both_groups", 2, "let bouth_groups = vec![a, b];"),
+            vec![("bouth_groups", 1)]
+        );
+    }
+
+    #[test]
+    fn poem_lines_within_distance() {
+        // "nobody" with a letter missing, but not "somebody"
+        let fuzzy = Fuzzy::new("nobdy", 1, false);
+        let lines: Vec<(usize, usize)> = POEM
+            .lines()
+            .enumerate()
+            .filter_map(|(i, line)| {
+                let found = fuzzy.find_all(line);
+                found.iter().map(|m| m.distance).min().map(|d| (i + 1, d))
+            })
+            .collect();
+        assert_eq!(lines, vec![(1, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn byte_offsets_with_multibyte_text() {
+        let line = "un été à Paris";
+        let found = Fuzzy::new("eté", 1, false).find_all(line);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].start, found[0].end), (3, 8));
+        assert_eq!(&line[found[0].start..found[0].end], "été");
+        assert_eq!(found[0].distance, 1);
+
+        let found = Fuzzy::new("ÉTÉ", 0, true).find_all(line);
+        assert_eq!((found[0].start, found[0].end, found[0].distance), (3, 8, 0));
+    }
+
+    #[test]
+    fn distances_agree_with_levenshtein() {
+        // every reported match really is that many edits from the pattern
+        let lines = POEM.lines().chain(["kitten sitting", "abcabcab"]);
+        for line in lines {
+            for (pattern, k) in [
+                ("body", 2),
+                ("you", 1),
+                ("abc", 1),
+                ("tell", 2),
+                ("sitten", 2),
+            ] {
+                for m in Fuzzy::new(pattern, k, false).find_all(line) {
+                    assert!(m.distance <= k);
+                    assert_eq!(distance(pattern, &line[m.start..m.end]), m.distance);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bit_vectors_agree_with_the_table() {
+        // a small linear congruential generator, so the test is repeatable
+        let mut seed = 12345u64;
+        let mut next = |n: u64| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (seed >> 33) % n
+        };
+
+        for _ in 0..200 {
+            let pattern: String = (0..1 + next(12))
+                .map(|_| (b'a' + next(3) as u8) as char)
+                .collect();
+            let text: Vec<char> = (0..next(40))
+                .map(|_| (b'a' + next(3) as u8) as char)
+                .collect();
+            let fuzzy = Fuzzy::new(&pattern, 2, false);
+            assert_eq!(
+                fuzzy.scores(&text),
+                fuzzy.scores_by_table(&text),
+                "{pattern} in {text:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn long_patterns_use_the_table() {
+        let pattern = "Then there's a pair of us - don't tell! They'd banish us, you know.";
+        assert!(pattern.chars().count() > 64);
+        let line = "Then there's a pair of us - dont tell! They'd banish us, you know.";
+        let found = Fuzzy::new(pattern, 2, false).find_all(line);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].distance, 1);
+        assert_eq!((found[0].start, found[0].end), (0, line.len()));
+    }
+}
@@ -0,0 +1,539 @@
+//A trigram index for searching the same tree over and over
+/*
+Searching a big directory means reading every file in it, every time. Most
+files can't possibly match, though: a file can only contain "frog" if it
+contains each of the three-byte pieces "fro" and "rog". These pieces are
+called trigrams, and if we remember which trigrams each file has, we only
+need to read the files that have all of the query's trigrams.
+
+    minigrep index build DIR     writes DIR/.minigrep-index
+    minigrep --indexed to DIR    only reads the files the index allows
+
+The trigrams are taken from the text after case folding it (see unicode.rs),
+so the same index works for both case-sensitive and IGNORE_CASE searches.
+A regex has no fixed text to take trigrams from, so with --regex every file
+is still read, and so is every file when the query is shorter than three
+bytes.
+
+The index remembers each file's size and modification time. Running
+'index build' again only rereads files where they changed, and a search
+doesn't trust the index for those files either: a changed or new file is
+always searched in full, so the results are the same as without --indexed.
+With -v or -c the files that don't match are part of the results too, so
+those searches read every file.
+When there's no index at all, or it's from another version of minigrep, the
+search just reads every file.
+
+The file starts with the bytes "MGIX" and a format version, then a count of
+files. For each file there's its path relative to DIR, its modification time
+in seconds and nanoseconds, its size, whether it's binary, and its sorted
+trigrams. All numbers are little-endian.
+*/
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File, Metadata};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::lines::{self, LineReader};
+use crate::replace::TempFile;
+use crate::unicode::{self, Folding};
+use crate::walk;
+
+/// The files a search has to read, as far as the index can tell.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Candidates {
+    pub files: Vec<PathBuf>,
+    /// How many of `files` are new or changed since the index was built.
+    pub stale: usize,
+    /// How many text files were left out because they can't match.
+    pub ruled_out: usize,
+}
+
+/// The name of the index file, in the directory it indexes.
+pub const FILE_NAME: &str = ".minigrep-index";
+
+const MAGIC: &[u8; 4] = b"MGIX";
+
+/// The version of the file format; bumped whenever it changes.
+pub const VERSION: u32 = 1;
+
+// anything bigger than these means the file is corrupt
+const MAX_PATH_LEN: u32 = 64 * 1024;
+const MAX_TRIGRAMS: u32 = 1 << 24;
+
+/// What the index knows about one file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// When the file was last changed, in seconds and nanoseconds since the
+    /// Unix epoch.
+    pub modified: (u64, u32),
+    pub size: u64,
+    /// Binary files are never searched, so they have no trigrams.
+    pub binary: bool,
+    /// Every trigram in the case folded lines of the file, sorted.
+    pub trigrams: Vec<u32>,
+}
+
+impl Entry {
+    /// Reads the file at `path` and collects its trigrams.
+    pub fn read(path: &Path, metadata: &Metadata) -> io::Result<Entry> {
+        let mut input = BufReader::new(File::open(path)?);
+        let binary = lines::looks_binary(&mut input)?;
+
+        let mut found = HashSet::new();
+        if !binary {
+            for line in LineReader::new(input) {
+                found.extend(trigrams(&fold(&line?.text)));
+            }
+        }
+        let mut trigrams: Vec<u32> = found.into_iter().collect();
+        trigrams.sort_unstable();
+
+        Ok(Entry {
+            modified: modified(metadata),
+            size: metadata.len(),
+            binary,
+            trigrams,
+        })
+    }
+
+    /// Whether the file still looks the way it did when it was indexed.
+    pub fn is_fresh(&self, metadata: &Metadata) -> bool {
+        self.modified == modified(metadata) && self.size == metadata.len()
+    }
+
+    /// Whether the file has every one of `needed`, which must be sorted.
+    pub fn has_all(&self, needed: &[u32]) -> bool {
+        needed
+            .iter()
+            .all(|trigram| self.trigrams.binary_search(trigram).is_ok())
+    }
+}
+
+/// The trigrams of every file in a directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Index {
+    /// Keyed by the path relative to the directory, with '/' separators.
+    pub files: BTreeMap<String, Entry>,
+}
+
+/// What `Index::update` did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+impl Index {
+    /// Reads the index saved in `dir`.
+    pub fn load(dir: &Path) -> io::Result<Index> {
+        let mut input = BufReader::new(File::open(dir.join(FILE_NAME))?);
+        Index::read_from(&mut input)
+    }
+
+    /// Saves the index in `dir`, replacing the old one all at once so a
+    /// search never sees half of it.
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        let path = dir.join(FILE_NAME);
+        let mut temp = TempFile::create(&path)?;
+        self.write_to(&mut temp.writer)?;
+        temp.commit(&path)
+    }
+
+    /// Brings the index up to date with the files in `dir`, only reading the
+    /// ones that are new or have changed.
+    pub fn update(&mut self, dir: &Path) -> io::Result<Stats> {
+        let mut stats = Stats::default();
+        let mut files = BTreeMap::new();
+
+        for path in walk::walk(dir)? {
+            let Some(key) = key(dir, &path) else {
+                continue;
+            };
+            let metadata = fs::metadata(&path)?;
+            let entry = match self.files.remove(&key) {
+                Some(old) if old.is_fresh(&metadata) => {
+                    stats.unchanged += 1;
+                    old
+                }
+                Some(_) => {
+                    stats.updated += 1;
+                    Entry::read(&path, &metadata)?
+                }
+                None => {
+                    stats.added += 1;
+                    Entry::read(&path, &metadata)?
+                }
+            };
+            files.insert(key, entry);
+        }
+
+        // whatever is left wasn't found by the walk
+        stats.removed = self.files.len();
+        self.files = files;
+        Ok(stats)
+    }
+
+    /// Narrows `files`, found by walking `dir`, down to the ones that might
+    /// contain all of `needed`. Files the index doesn't know about or that
+    /// have changed since it was built are always kept.
+    pub fn candidates(
+        &self,
+        dir: &Path,
+        files: Vec<PathBuf>,
+        needed: &[u32],
+    ) -> io::Result<Candidates> {
+        let mut candidates = Candidates::default();
+        for path in files {
+            let Some(key) = key(dir, &path) else {
+                continue;
+            };
+            match self.files.get(&key) {
+                Some(entry) if entry.is_fresh(&fs::metadata(&path)?) => {
+                    if entry.binary {
+                        continue;
+                    }
+                    if entry.has_all(needed) {
+                        candidates.files.push(path);
+                    } else {
+                        candidates.ruled_out += 1;
+                    }
+                }
+                _ => {
+                    candidates.stale += 1;
+                    candidates.files.push(path);
+                }
+            }
+        }
+        Ok(candidates)
+    }
+
+    /// Reads an index in the binary format described at the top of this file.
+    pub fn read_from<R: Read>(input: &mut R) -> io::Result<Index> {
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a minigrep index"));
+        }
+        let version = read_u32(input)?;
+        if version != VERSION {
+            return Err(invalid(&format!(
+                "index has format version {version}, but this minigrep uses version {VERSION}"
+            )));
+        }
+
+        let mut files = BTreeMap::new();
+        for _ in 0..read_u32(input)? {
+            let len = read_u32(input)?;
+            if len > MAX_PATH_LEN {
+                return Err(invalid("index is corrupt"));
+            }
+            let mut path = vec![0; len as usize];
+            input.read_exact(&mut path)?;
+            let path = String::from_utf8(path).map_err(|_| invalid("index is corrupt"))?;
+
+            let modified = (read_u64(input)?, read_u32(input)?);
+            let size = read_u64(input)?;
+            let mut binary = [0];
+            input.read_exact(&mut binary)?;
+
+            let count = read_u32(input)?;
+            if count > MAX_TRIGRAMS {
+                return Err(invalid("index is corrupt"));
+            }
+            let trigrams = (0..count)
+                .map(|_| read_u32(input))
+                .collect::<io::Result<Vec<_>>>()?;
+
+            let entry = Entry {
+                modified,
+                size,
+                binary: binary[0] != 0,
+                trigrams,
+            };
+            files.insert(path, entry);
+        }
+
+        Ok(Index { files })
+    }
+
+    /// Writes the index in its binary format.
+    pub fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&(self.files.len() as u32).to_le_bytes())?;
+
+        for (path, entry) in &self.files {
+            out.write_all(&(path.len() as u32).to_le_bytes())?;
+            out.write_all(path.as_bytes())?;
+            out.write_all(&entry.modified.0.to_le_bytes())?;
+            out.write_all(&entry.modified.1.to_le_bytes())?;
+            out.write_all(&entry.size.to_le_bytes())?;
+            out.write_all(&[entry.binary as u8])?;
+            out.write_all(&(entry.trigrams.len() as u32).to_le_bytes())?;
+            for trigram in &entry.trigrams {
+                out.write_all(&trigram.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builds or updates the index for `dir` and saves it.
+pub fn build(dir: &Path) -> io::Result<Stats> {
+    if !dir.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a directory", dir.display()),
+        ));
+    }
+    // an unreadable or outdated index is simply built again from scratch
+    let mut index = Index::load(dir).unwrap_or_default();
+    let stats = index.update(dir)?;
+    index.save(dir)?;
+    Ok(stats)
+}
+
+/// Case folds `text` the same way an IGNORE_CASE search compares it.
+pub fn fold(text: &str) -> String {
+    unicode::fold(text, Folding::Full)
+}
+
+/// The distinct trigrams in `text`, sorted. Each one is its three bytes
+/// packed into the low 24 bits of a `u32`.
+pub fn trigrams(text: &str) -> Vec<u32> {
+    let mut found: Vec<u32> = text
+        .as_bytes()
+        .windows(3)
+        .map(|w| u32::from(w[0]) << 16 | u32::from(w[1]) << 8 | u32::from(w[2]))
+        .collect();
+    found.sort_unstable();
+    found.dedup();
+    found
+}
+
+// The key for a file found under `dir`, or None for the index itself.
+fn key(dir: &Path, path: &Path) -> Option<String> {
+    if path.file_name().is_some_and(|name| name == FILE_NAME) {
+        return None;
+    }
+    let relative = path.strip_prefix(dir).unwrap_or(path);
+    Some(walk::slash_path(relative))
+}
+
+fn modified(metadata: &Metadata) -> (u64, u32) {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or((0, 0), |since| (since.as_secs(), since.subsec_nanos()))
+}
+
+fn read_u32<R: Read>(input: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    // the files under `dir` that a search for `query` would read, and how
+    // many of them are stale
+    fn candidates(dir: &TempDir, index: &Index, query: &str) -> (Vec<String>, usize) {
+        let files = walk::walk(dir.path()).unwrap();
+        let candidates = index
+            .candidates(dir.path(), files, &trigrams(&fold(query)))
+            .unwrap();
+        let kept = candidates
+            .files
+            .iter()
+            .filter_map(|f| key(dir.path(), f))
+            .collect();
+        (kept, candidates.stale)
+    }
+
+    fn pack(trigram: &[u8; 3]) -> u32 {
+        u32::from(trigram[0]) << 16 | u32::from(trigram[1]) << 8 | u32::from(trigram[2])
+    }
+
+    #[test]
+    fn trigrams_of_text() {
+        assert_eq!(trigrams("frog"), vec![pack(b"fro"), pack(b"rog")]);
+        assert_eq!(trigrams("bobob"), vec![pack(b"bob"), pack(b"obo")]);
+        assert!(trigrams("to").is_empty());
+        // multibyte characters give trigrams of their bytes
+        assert_eq!(trigrams("é!"), vec![pack(&[0xc3, 0xa9, b'!'])]);
+    }
+
+    #[test]
+    fn folding_keeps_the_index_valid_for_ignore_case() {
+        assert_eq!(fold("How Public"), "how public");
+        // 'İ' folds to two characters, just as IGNORE_CASE compares it
+        assert_eq!(fold("İ"), "i\u{307}");
+        assert_eq!(fold("Straße"), fold("STRASSE"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut index = Index::default();
+        index.files.insert(
+            String::from("poem.txt"),
+            Entry {
+                modified: (1_700_000_000, 123),
+                size: 232,
+                binary: false,
+                trigrams: trigrams("frog bog"),
+            },
+        );
+        index.files.insert(
+            String::from("bin/tool"),
+            Entry {
+                modified: (5, 0),
+                size: 9,
+                binary: true,
+                trigrams: Vec::new(),
+            },
+        );
+
+        let mut bytes = Vec::new();
+        index.write_to(&mut bytes).unwrap();
+        assert_eq!(&bytes[..8], b"MGIX\x01\x00\x00\x00");
+        assert_eq!(Index::read_from(&mut &bytes[..]).unwrap(), index);
+    }
+
+    #[test]
+    fn rejects_other_versions_and_garbage() {
+        let mut bytes = Vec::new();
+        Index::default().write_to(&mut bytes).unwrap();
+        bytes[4] = 7;
+        let err = Index::read_from(&mut &bytes[..]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "index has format version 7, but this minigrep uses version 1"
+        );
+
+        let err = Index::read_from(&mut &b"I'm nobody! Who are you?"[..]).unwrap_err();
+        assert_eq!(err.to_string(), "not a minigrep index");
+
+        // cut off in the middle of an entry
+        let mut index = Index::default();
+        index.files.insert(
+            String::from("a"),
+            Entry {
+                modified: (0, 0),
+                size: 0,
+                binary: false,
+                trigrams: vec![1, 2, 3],
+            },
+        );
+        let mut bytes = Vec::new();
+        index.write_to(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() - 2);
+        assert!(Index::read_from(&mut &bytes[..]).is_err());
+    }
+
+    #[test]
+    fn narrows_the_candidates() {
+        let dir = TempDir::new("index-narrow");
+        dir.file("poem.txt", include_bytes!("../poem.txt"));
+        dir.file("notes/pond.txt", b"A FROG in the pond\n");
+        dir.file("notes/list.txt", b"eggs\nmilk\n");
+        dir.file("tool.bin", b"frog\0\0");
+
+        let stats = build(dir.path()).unwrap();
+        assert_eq!(stats.added, 4);
+        assert!(dir.join(FILE_NAME).exists());
+
+        let index = Index::load(dir.path()).unwrap();
+        assert_eq!(
+            candidates(&dir, &index, "frog"),
+            (
+                vec![String::from("notes/pond.txt"), String::from("poem.txt")],
+                0
+            )
+        );
+        assert_eq!(
+            candidates(&dir, &index, "milk"),
+            (vec![String::from("notes/list.txt")], 0)
+        );
+        assert_eq!(candidates(&dir, &index, "toad"), (Vec::new(), 0));
+        // the binary file is never read, so it isn't ruled out either
+        let files = walk::walk(dir.path()).unwrap();
+        let toad = index
+            .candidates(dir.path(), files, &trigrams("toad"))
+            .unwrap();
+        assert_eq!(toad.ruled_out, 3);
+        // too short to have trigrams, so every text file could match
+        assert_eq!(candidates(&dir, &index, "eg").0.len(), 3);
+    }
+
+    #[test]
+    fn incremental_updates() {
+        let dir = TempDir::new("index-update");
+        dir.file("a.txt", b"frog\n");
+        dir.file("b.txt", b"bog\n");
+        dir.file("c.txt", b"toad\n");
+        build(dir.path()).unwrap();
+
+        dir.file("b.txt", b"a much longer bog\n");
+        dir.file("d.txt", b"newt\n");
+        fs::remove_file(dir.join("c.txt")).unwrap();
+
+        let stats = build(dir.path()).unwrap();
+        assert_eq!(
+            stats,
+            Stats {
+                added: 1,
+                updated: 1,
+                unchanged: 1,
+                removed: 1,
+            }
+        );
+
+        let index = Index::load(dir.path()).unwrap();
+        let keys: Vec<&String> = index.files.keys().collect();
+        assert_eq!(keys, ["a.txt", "b.txt", "d.txt"]);
+        assert!(index.files["b.txt"].has_all(&trigrams("longer")));
+    }
+
+    #[test]
+    fn stale_files_are_always_candidates() {
+        let dir = TempDir::new("index-stale");
+        dir.file("a.txt", b"frog\n");
+        dir.file("b.txt", b"bog\n");
+        build(dir.path()).unwrap();
+        let index = Index::load(dir.path()).unwrap();
+
+        // changed and new files since the build may match now
+        dir.file("b.txt", b"frogs and bogs\n");
+        dir.file("c.txt", b"tree frog\n");
+        assert_eq!(
+            candidates(&dir, &index, "frog"),
+            (
+                vec![
+                    String::from("a.txt"),
+                    String::from("b.txt"),
+                    String::from("c.txt")
+                ],
+                2
+            )
+        );
+    }
+}
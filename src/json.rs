@@ -0,0 +1,510 @@
+//JSON Lines output for --json
+/*
+Scripts that read minigrep's output shouldn't have to scrape text, so --json
+writes one JSON object per line instead:
+
+    {"type":"begin","query":"to","path":"poem.txt"}
+    {"type":"match","path":"poem.txt","line_number":2,"start":41,"end":43,"text":"to","line":"Are you nobody, too?"}
+    {"type":"end","files":1,"files_with_matches":1,"matches":2}
+
+'start' and 'end' are byte offsets from the beginning of the file, so
+'text' is the bytes start..end of the file. (If a line isn't valid UTF-8,
+the bad bytes are replaced before matching and the offsets within that line
+count the replacement characters instead.) There is one 'match'
+record for every match, not every line, so a line can show up more than
+once. With -v each selected line is one record that covers the whole line.
+With --fuzzy every match record also has a 'distance', the number of edits
+between 'text' and the query. The records are matches, so --json can't be
+used with -c or -l.
+
+The crate has no dependencies, so the JSON is written by hand with a small
+Value type.
+*/
+
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use crate::lines::LineReader;
+use crate::output::Found;
+
+/// A JSON value. Objects keep their keys in the order they were added.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Looks up `key` if this is an object.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<usize> for Value {
+    fn from(n: usize) -> Value {
+        Value::Number(n as f64)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(n: u64) -> Value {
+        Value::Number(n as f64)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+            Value::Number(n) => write!(f, "{n}"),
+            Value::String(s) => write_string(f, s),
+            Value::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                write!(f, "]")
+            }
+            Value::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+fn object(fields: Vec<(&str, Value)>) -> Value {
+    Value::Object(
+        fields
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect(),
+    )
+}
+
+/// The totals reported in the `end` record.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub files: usize,
+    pub files_with_matches: usize,
+    pub matches: usize,
+}
+
+impl Summary {
+    /// Adds one searched file that had `matches` matches.
+    pub fn add_file(&mut self, matches: usize) {
+        self.files += 1;
+        self.matches += matches;
+        if matches > 0 {
+            self.files_with_matches += 1;
+        }
+    }
+}
+
+pub fn write_begin<W: Write>(out: &mut W, query: &str, path: &str) -> io::Result<()> {
+    let record = object(vec![
+        ("type", "begin".into()),
+        ("query", query.into()),
+        ("path", path.into()),
+    ]);
+    writeln!(out, "{record}")
+}
+
+pub fn write_end<W: Write>(out: &mut W, summary: &Summary) -> io::Result<()> {
+    let record = object(vec![
+        ("type", "end".into()),
+        ("files", summary.files.into()),
+        ("files_with_matches", summary.files_with_matches.into()),
+        ("matches", summary.matches.into()),
+    ]);
+    writeln!(out, "{record}")
+}
+
+/// Reads `input` line by line, writes a `match` record for every match and
+/// returns how many there were. `find` gives the matches in one line; fuzzy
+/// matches also get a `distance`.
+pub fn write_matches<R: BufRead, W: Write>(
+    out: &mut W,
+    path: &str,
+    input: R,
+    find: impl Fn(&str) -> Vec<Found>,
+    invert: bool,
+) -> io::Result<usize> {
+    let mut count = 0;
+
+    for line in LineReader::new(input) {
+        let line = line?;
+        let found = find(&line.text);
+
+        let found = if invert {
+            if found.is_empty() {
+                vec![Found::from((0, line.text.len()))]
+            } else {
+                Vec::new()
+            }
+        } else {
+            found
+        };
+
+        for Found {
+            start,
+            end,
+            distance,
+        } in found
+        {
+            let mut fields = vec![
+                ("type", "match".into()),
+                ("path", path.into()),
+                ("line_number", line.number.into()),
+                ("start", (line.offset + start as u64).into()),
+                ("end", (line.offset + end as u64).into()),
+                ("text", line.text[start..end].into()),
+                ("line", line.text.as_str().into()),
+            ];
+            if let Some(distance) = distance {
+                fields.push(("distance", distance.into()));
+            }
+            writeln!(out, "{}", object(fields))?;
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POEM: &str = include_str!("../poem.txt");
+
+    // A small JSON parser, only used to check that what we write reads back.
+    struct Parser<'a> {
+        text: &'a str,
+        pos: usize,
+    }
+
+    impl Parser<'_> {
+        fn parse(text: &str) -> Result<Value, String> {
+            let mut parser = Parser { text, pos: 0 };
+            let value = parser.value()?;
+            parser.skip_whitespace();
+            if parser.pos != text.len() {
+                return Err(format!("trailing characters at {}", parser.pos));
+            }
+            Ok(value)
+        }
+
+        fn peek(&self) -> Option<char> {
+            self.text[self.pos..].chars().next()
+        }
+
+        fn skip_whitespace(&mut self) {
+            while self.peek().is_some_and(|c| c.is_whitespace()) {
+                self.pos += 1;
+            }
+        }
+
+        fn expect(&mut self, s: &str) -> Result<(), String> {
+            if self.text[self.pos..].starts_with(s) {
+                self.pos += s.len();
+                Ok(())
+            } else {
+                Err(format!("expected {s:?} at {}", self.pos))
+            }
+        }
+
+        fn value(&mut self) -> Result<Value, String> {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('n') => self.expect("null").map(|_| Value::Null),
+                Some('t') => self.expect("true").map(|_| Value::Bool(true)),
+                Some('f') => self.expect("false").map(|_| Value::Bool(false)),
+                Some('"') => self.string().map(Value::String),
+                Some('[') => self.array(),
+                Some('{') => self.object(),
+                Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+                _ => Err(format!("unexpected input at {}", self.pos)),
+            }
+        }
+
+        fn number(&mut self) -> Result<Value, String> {
+            let start = self.pos;
+            while self
+                .peek()
+                .is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(c))
+            {
+                self.pos += 1;
+            }
+            self.text[start..self.pos]
+                .parse()
+                .map(Value::Number)
+                .map_err(|e| e.to_string())
+        }
+
+        fn string(&mut self) -> Result<String, String> {
+            self.expect("\"")?;
+            let mut s = String::new();
+            loop {
+                let c = self.peek().ok_or("unterminated string")?;
+                self.pos += c.len_utf8();
+                match c {
+                    '"' => return Ok(s),
+                    '\\' => {
+                        let e = self.peek().ok_or("unterminated escape")?;
+                        self.pos += 1;
+                        match e {
+                            'n' => s.push('\n'),
+                            'r' => s.push('\r'),
+                            't' => s.push('\t'),
+                            'u' => {
+                                let hex = &self.text[self.pos..self.pos + 4];
+                                let code =
+                                    u32::from_str_radix(hex, 16).map_err(|e| e.to_string())?;
+                                s.push(char::from_u32(code).ok_or("bad escape")?);
+                                self.pos += 4;
+                            }
+                            e => s.push(e),
+                        }
+                    }
+                    c => s.push(c),
+                }
+            }
+        }
+
+        fn array(&mut self) -> Result<Value, String> {
+            self.expect("[")?;
+            let mut items = Vec::new();
+            self.skip_whitespace();
+            if self.expect("]").is_ok() {
+                return Ok(Value::Array(items));
+            }
+            loop {
+                items.push(self.value()?);
+                self.skip_whitespace();
+                if self.expect("]").is_ok() {
+                    return Ok(Value::Array(items));
+                }
+                self.expect(",")?;
+            }
+        }
+
+        fn object(&mut self) -> Result<Value, String> {
+            self.expect("{")?;
+            let mut fields = Vec::new();
+            self.skip_whitespace();
+            if self.expect("}").is_ok() {
+                return Ok(Value::Object(fields));
+            }
+            loop {
+                self.skip_whitespace();
+                let key = self.string()?;
+                self.skip_whitespace();
+                self.expect(":")?;
+                fields.push((key, self.value()?));
+                self.skip_whitespace();
+                if self.expect("}").is_ok() {
+                    return Ok(Value::Object(fields));
+                }
+                self.expect(",")?;
+            }
+        }
+    }
+
+    fn find_literal(query: &'static str) -> impl Fn(&str) -> Vec<Found> {
+        move |line| {
+            line.match_indices(query)
+                .map(|(i, m)| Found::from((i, i + m.len())))
+                .collect()
+        }
+    }
+
+    fn records(query: &'static str, invert: bool) -> Vec<Value> {
+        let mut out = Vec::new();
+        write_matches(
+            &mut out,
+            "poem.txt",
+            POEM.as_bytes(),
+            find_literal(query),
+            invert,
+        )
+        .unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| Parser::parse(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn values_round_trip() {
+        let value = Value::Object(vec![
+            (String::from("null"), Value::Null),
+            (String::from("yes"), Value::Bool(true)),
+            (String::from("n"), Value::Number(42.0)),
+            (String::from("half"), Value::Number(0.5)),
+            (
+                String::from("tricky \"key\""),
+                Value::String(String::from("tab\there\nquote\" slash\\ bell\u{7} é")),
+            ),
+            (
+                String::from("list"),
+                Value::Array(vec![Value::Number(-1.0), Value::Array(Vec::new())]),
+            ),
+        ]);
+
+        let text = value.to_string();
+        assert!(!text.contains('\n'));
+        assert_eq!(Parser::parse(&text).unwrap(), value);
+    }
+
+    #[test]
+    fn escapes_control_characters() {
+        let value = Value::from("a\u{1}b");
+        assert_eq!(value.to_string(), "\"a\\u0001b\"");
+    }
+
+    #[test]
+    fn match_records_round_trip() {
+        let records = records("to", false);
+        assert_eq!(records.len(), 2);
+
+        let first = &records[0];
+        assert_eq!(first.get("type"), Some(&Value::from("match")));
+        assert_eq!(first.get("path"), Some(&Value::from("poem.txt")));
+        assert_eq!(first.get("line_number"), Some(&Value::Number(2.0)));
+        assert_eq!(first.get("text"), Some(&Value::from("to")));
+        assert_eq!(
+            first.get("line"),
+            Some(&Value::from("Are you nobody, too?"))
+        );
+    }
+
+    #[test]
+    fn offsets_point_into_the_file() {
+        for record in records("o", false) {
+            let start = match record.get("start") {
+                Some(Value::Number(n)) => *n as usize,
+                other => panic!("bad start {other:?}"),
+            };
+            let end = match record.get("end") {
+                Some(Value::Number(n)) => *n as usize,
+                other => panic!("bad end {other:?}"),
+            };
+            assert_eq!(Some(&Value::from(&POEM[start..end])), record.get("text"));
+        }
+    }
+
+    #[test]
+    fn one_record_per_match() {
+        // "I'm nobody! Who are you?" has four o's
+        let records = records("o", false);
+        let first_line: Vec<_> = records
+            .iter()
+            .filter(|r| r.get("line_number") == Some(&Value::Number(1.0)))
+            .map(|r| r.get("start").unwrap())
+            .collect();
+        assert_eq!(
+            first_line,
+            vec![
+                &Value::Number(5.0),
+                &Value::Number(7.0),
+                &Value::Number(14.0),
+                &Value::Number(21.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn invert_covers_whole_lines() {
+        let records = records("o", true);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].get("line_number"), Some(&Value::Number(5.0)));
+        assert_eq!(records[0].get("text"), Some(&Value::from("")));
+    }
+
+    #[test]
+    fn fuzzy_matches_have_a_distance() {
+        let mut out = Vec::new();
+        let find = |line: &str| -> Vec<Found> {
+            line.find("fro")
+                .map(|start| Found {
+                    start,
+                    end: start + 4,
+                    distance: Some(1),
+                })
+                .into_iter()
+                .collect()
+        };
+        write_matches(&mut out, "poem.txt", POEM.as_bytes(), find, false).unwrap();
+        let record = Parser::parse(String::from_utf8(out).unwrap().trim_end()).unwrap();
+        assert_eq!(record.get("text"), Some(&Value::from("frog")));
+        assert_eq!(record.get("distance"), Some(&Value::Number(1.0)));
+
+        // exact matches don't have one
+        assert_eq!(records("frog", false)[0].get("distance"), None);
+    }
+
+    #[test]
+    fn begin_and_end_records() {
+        let mut summary = Summary::default();
+        summary.add_file(3);
+        summary.add_file(0);
+
+        let mut out = Vec::new();
+        write_begin(&mut out, "to", "poem.txt").unwrap();
+        write_end(&mut out, &summary).unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        assert_eq!(
+            text,
+            "{\"type\":\"begin\",\"query\":\"to\",\"path\":\"poem.txt\"}\n\
+             {\"type\":\"end\",\"files\":2,\"files_with_matches\":1,\"matches\":3}\n"
+        );
+        for line in text.lines() {
+            Parser::parse(line).unwrap();
+        }
+    }
+}
@@ -0,0 +1,1627 @@
+//minigrep: the I/O project from chapter 12
+/*
+A small version of the classic command line search tool 'grep'. It takes a
+query string and a file path, reads the file, and prints the lines that
+contain the query. Given a directory instead of a file, it searches every
+file below it that git wouldn't ignore and prints matches as 'path:line:text'
+(or 'path:text' after --no-line-numbers).
+With no file path, or a path of '-', it searches standard input, so it can sit
+at the end of a pipe. Input is read one line at a time (see lines.rs), so huge
+files don't have to fit in memory. Flags like -n, -c and -C choose how the
+results are shown (see output.rs), --fuzzy K allows up to K typos in each
+match (see fuzzy.rs), and --json writes JSON Lines for other
+programs to read (see json.rs). --replace rewrites the matches in place
+instead of printing them (see replace.rs). In a terminal the matches are
+highlighted in color (see color.rs). -w only matches whole words, and
+--normalize and --turkish change how accents and case are compared (see
+unicode.rs). Settings can also come from
+~/.minigreprc and environment variables like IGNORE_CASE (see settings.rs).
+For trees that get searched again and again, 'minigrep index build DIR' and
+--indexed skip the files that can't match (see index.rs). --watch keeps
+going after the search and prints the matches that appear or disappear as
+files change (see watch.rs).
+The logic lives here in lib.rs so it can be tested and reused by other
+crates, which can also use just the matching through the Searcher trait (see
+searcher.rs); main.rs only collects the arguments and handles errors.
+*/
+
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+pub mod color;
+pub mod fuzzy;
+pub mod index;
+pub mod json;
+pub mod lines;
+pub mod output;
+pub mod parallel;
+pub mod regex;
+pub mod replace;
+pub mod searcher;
+pub mod settings;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod unicode;
+mod unicode_tables;
+pub mod walk;
+pub mod watch;
+
+use crate::color::ColorChoice;
+use crate::fuzzy::Fuzzy;
+use crate::index::{Candidates, Index};
+use crate::output::{Found, OutputOptions};
+use crate::regex::Regex;
+use crate::replace::Template;
+use crate::searcher::{is_whole_word, IgnoreCase, Literal, Searcher};
+use crate::settings::{Source, Sources};
+use crate::unicode::{Folding, Normalization, Prepare};
+
+/// The settings for one search, built from the command line arguments.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub query: String,
+    pub file_path: String,
+    pub ignore_case: bool,
+    pub regex: bool,
+    /// Only match where the match starts and ends at a word boundary.
+    pub whole_word: bool,
+    /// Put the query and each line in this form before comparing them.
+    pub normalize: Option<Normalization>,
+    /// Fold case the Turkish way, where 'I' goes with 'ı' and 'İ' with 'i'.
+    pub turkish: bool,
+    pub output: OutputOptions,
+    /// How many threads search a directory; 0 picks one per CPU.
+    pub threads: usize,
+    /// Rewrite matches with this replacement instead of printing them.
+    pub replace: Option<String>,
+    /// With `replace`, print a diff instead of changing any files.
+    pub dry_run: bool,
+    /// Print the settings and where they came from instead of searching.
+    pub print_config: bool,
+    /// Match within this many edits of the query instead of exactly.
+    pub fuzzy: Option<usize>,
+    /// Use the index built by `minigrep index build` to skip files.
+    pub indexed: bool,
+    /// Build or update the index for `file_path` instead of searching.
+    pub build_index: bool,
+    /// Keep searching `file_path` as it changes, printing what's different.
+    pub watch: bool,
+    pub sources: Sources,
+}
+
+impl Config {
+    /// Builds a `Config` from the defaults, ~/.minigreprc, environment
+    /// variables and then an iterator of arguments, such as `env::args()`,
+    /// each overriding the one before. See settings.rs.
+    pub fn build(args: impl Iterator<Item = String>) -> Result<Config, Box<dyn Error>> {
+        let mut config = Config::default();
+        settings::load(&mut config)?;
+        Ok(config.with_args(args)?)
+    }
+
+    /// Applies the command line arguments on top of this `Config`. The first
+    /// item is the program name and is skipped.
+    pub fn with_args(
+        mut self,
+        mut args: impl Iterator<Item = String>,
+    ) -> Result<Config, &'static str> {
+        args.next();
+
+        let output = &mut self.output;
+        let sources = &mut self.sources;
+        let mut positional = Vec::new();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--regex" => {
+                    self.regex = true;
+                    sources.regex = Source::Flag;
+                }
+                "--no-regex" => {
+                    self.regex = false;
+                    sources.regex = Source::Flag;
+                }
+                "--ignore-case" => {
+                    self.ignore_case = true;
+                    sources.ignore_case = Source::Flag;
+                }
+                // turn off what ~/.minigreprc or the environment turned on
+                "--no-ignore-case" | "--case-sensitive" => {
+                    self.ignore_case = false;
+                    sources.ignore_case = Source::Flag;
+                }
+                "--line-numbers" => {
+                    output.line_numbers = true;
+                    sources.line_numbers = Source::Flag;
+                }
+                "--no-line-numbers" => {
+                    output.line_numbers = false;
+                    sources.line_numbers = Source::Flag;
+                }
+                "--turkish" => self.turkish = true,
+                "--normalize" => match args.next() {
+                    Some(form) => self.normalize = Some(Normalization::parse(&form)?),
+                    None => return Err("Didn't get a normalization form"),
+                },
+                _ if arg.starts_with("--normalize=") => {
+                    self.normalize = Some(Normalization::parse(&arg["--normalize=".len()..])?)
+                }
+                "--json" => output.json = true,
+                "--dry-run" => self.dry_run = true,
+                "--print-config" => self.print_config = true,
+                "--indexed" => self.indexed = true,
+                "--watch" => self.watch = true,
+                "--fuzzy" => self.fuzzy = Some(parse_distance(args.next())?),
+                _ if arg.starts_with("--fuzzy=") => {
+                    self.fuzzy = Some(parse_distance(Some(arg["--fuzzy=".len()..].to_string()))?)
+                }
+                // a bare --color means auto, as in grep
+                "--color" => {
+                    output.color = ColorChoice::Auto;
+                    sources.color = Source::Flag;
+                }
+                _ if arg.starts_with("--color=") => {
+                    output.color = ColorChoice::parse(&arg["--color=".len()..])?;
+                    sources.color = Source::Flag;
+                }
+                "--replace" => match args.next() {
+                    Some(replacement) => self.replace = Some(replacement),
+                    None => return Err("Didn't get a replacement"),
+                },
+                _ if arg.starts_with("--replace=") => {
+                    self.replace = Some(arg["--replace=".len()..].to_string())
+                }
+                "--threads" => {
+                    self.threads = parse_threads(args.next())?;
+                    sources.threads = Source::Flag;
+                }
+                _ if arg.starts_with("--threads=") => {
+                    self.threads = parse_threads(Some(arg["--threads=".len()..].to_string()))?;
+                    sources.threads = Source::Flag;
+                }
+                "-A" | "-B" | "-C" => {
+                    let n = parse_context(args.next())?;
+                    set_context(output, sources, &arg, n);
+                }
+                "-" => positional.push(arg),
+                _ if arg.starts_with("--") => return Err("Unrecognized option"),
+                _ if arg.starts_with("-A") || arg.starts_with("-B") || arg.starts_with("-C") => {
+                    let n = parse_context(Some(arg[2..].to_string()))?;
+                    set_context(output, sources, &arg[..2], n);
+                }
+                _ if arg.starts_with('-') => {
+                    // short flags can be grouped, as in -nv
+                    for flag in arg.chars().skip(1) {
+                        match flag {
+                            'c' => output.count = true,
+                            'v' => output.invert = true,
+                            'n' => {
+                                output.line_numbers = true;
+                                sources.line_numbers = Source::Flag;
+                            }
+                            'i' => {
+                                self.ignore_case = true;
+                                sources.ignore_case = Source::Flag;
+                            }
+                            'l' => output.files_with_matches = true,
+                            'w' => self.whole_word = true,
+                            _ => return Err("Unrecognized option"),
+                        }
+                    }
+                }
+                _ => positional.push(arg),
+            }
+        }
+        // 'index build DIR' would be too many arguments for a search, so
+        // it can't be mistaken for one
+        if positional.len() == 3 && positional[0] == "index" && positional[1] == "build" {
+            self.build_index = true;
+            self.file_path = positional.pop().unwrap_or_default();
+            return Ok(self);
+        }
+        let mut positional = positional.into_iter();
+
+        self.query = match positional.next() {
+            Some(arg) => arg,
+            // there's nothing to search for when only printing the settings
+            None if self.print_config => String::new(),
+            None => return Err("Didn't get a query string"),
+        };
+
+        // no path means standard input, as in `cat poem.txt | minigrep to`,
+        // except that an indexed search is of the current directory
+        let default_path = if self.indexed { "." } else { "-" };
+        self.file_path = positional
+            .next()
+            .unwrap_or_else(|| String::from(default_path));
+        if positional.next().is_some() {
+            return Err("Too many arguments");
+        }
+
+        if let Some(distance) = self.fuzzy {
+            if self.regex {
+                return Err("--fuzzy can't be used with --regex");
+            }
+            // otherwise every line would match
+            if distance >= self.query.chars().count() {
+                return Err("Fuzzy distance must be less than the query length");
+            }
+        }
+        if self.turkish {
+            self.ignore_case = true;
+            self.sources.ignore_case = Source::Flag;
+        }
+        // these change how the text is prepared, which a regex and a fuzzy
+        // match don't do
+        if (self.regex || self.fuzzy.is_some()) && (self.normalize.is_some() || self.turkish) {
+            return Err("--normalize and --turkish can't be used with --regex or --fuzzy");
+        }
+        if self.watch {
+            if self.file_path == "-" {
+                return Err("--watch needs a file or directory to watch");
+            }
+            let output = &self.output;
+            if output.count || output.files_with_matches || output.json {
+                return Err("--watch only prints lines; it can't be used with -c, -l or --json");
+            }
+            if self.replace.is_some() || self.indexed {
+                return Err("--watch can't be used with --replace or --indexed");
+            }
+        }
+        // a JSON record is a match, so there's nothing to count or list
+        if self.output.json && (self.output.count || self.output.files_with_matches) {
+            return Err("--json can't be used with -c or -l");
+        }
+        if self.dry_run && self.replace.is_none() {
+            return Err("--dry-run only makes sense with --replace");
+        }
+        if self.replace.is_some() && self.output.invert {
+            return Err("-v can't be used with --replace");
+        }
+
+        Ok(self)
+    }
+
+    /// How to show the results of searching `path`. With many files, every
+    /// line says where it came from, so a directory gets line numbers
+    /// unless they were turned off on purpose.
+    pub fn output_for(&self, path: &Path) -> OutputOptions {
+        let mut options = self.output.clone();
+        if path.is_dir() && self.sources.line_numbers == Source::Default {
+            options.line_numbers = true;
+        }
+        options
+    }
+}
+
+fn parse_threads(value: Option<String>) -> Result<usize, &'static str> {
+    match value.map(|v| v.parse()) {
+        Some(Ok(n)) if n > 0 => Ok(n),
+        Some(_) => Err("Thread count must be a positive number"),
+        None => Err("Didn't get a thread count"),
+    }
+}
+
+fn parse_distance(value: Option<String>) -> Result<usize, &'static str> {
+    match value {
+        Some(value) => value.parse().map_err(|_| "Fuzzy distance must be a number"),
+        None => Err("Didn't get a fuzzy distance"),
+    }
+}
+
+fn parse_context(value: Option<String>) -> Result<usize, &'static str> {
+    match value {
+        Some(value) => value.parse().map_err(|_| "Context length must be a number"),
+        None => Err("Didn't get a context length"),
+    }
+}
+
+fn set_context(output: &mut OutputOptions, sources: &mut Sources, flag: &str, n: usize) {
+    if flag != "-B" {
+        output.after_context = n;
+        sources.after_context = Source::Flag;
+    }
+    if flag != "-A" {
+        output.before_context = n;
+        sources.before_context = Source::Flag;
+    }
+}
+
+// what standard input is called in the output, the same as grep
+const STDIN_NAME: &str = "(standard input)";
+
+/// Reads the file named in `config` and prints every matching line. If the
+/// path is a directory, every file below it is searched instead.
+pub fn run(mut config: Config) -> Result<(), Box<dyn Error>> {
+    let stdout = io::stdout();
+    if config.print_config {
+        settings::write_config(&mut stdout.lock(), &config)?;
+        return Ok(());
+    }
+    if config.build_index {
+        let stats = index::build(Path::new(&config.file_path))?;
+        println!(
+            "Indexed {}: {} new, {} changed, {} unchanged, {} removed",
+            config.file_path, stats.added, stats.updated, stats.unchanged, stats.removed
+        );
+        return Ok(());
+    }
+
+    let no_color = env::var("NO_COLOR").ok();
+    config.output.color = config
+        .output
+        .color
+        .resolve(stdout.is_terminal(), no_color.as_deref());
+    if config.watch {
+        let matcher = Matcher::new(&config)?;
+        let path = Path::new(&config.file_path);
+        watch::watch(
+            path,
+            |line| matcher.find(line),
+            &config.output_for(path),
+            watch::INTERVAL,
+            &mut stdout.lock(),
+        )?;
+        return Ok(());
+    }
+    search_path(&config, &mut stdout.lock())
+}
+
+/// Does the search described by `config` and writes the results to `out`.
+pub fn search_path<W: Write>(config: &Config, out: &mut W) -> Result<(), Box<dyn Error>> {
+    let matcher = Matcher::new(config)?;
+    if let Some(replacement) = &config.replace {
+        return replace_path(config, &matcher, replacement, out);
+    }
+
+    let path = Path::new(&config.file_path);
+    let mut summary = json::Summary::default();
+
+    if config.indexed && !path.is_dir() {
+        return Err("--indexed only works on a directory".into());
+    }
+
+    if config.output.json {
+        let shown = match config.file_path.as_str() {
+            "-" => STDIN_NAME,
+            path => path,
+        };
+        json::write_begin(out, &config.query, shown)?;
+    }
+
+    if path.is_dir() {
+        let options = config.output_for(path);
+        let threads = match config.threads {
+            0 => parallel::default_threads(),
+            n => n,
+        };
+
+        let mut files = walk::walk(path)?;
+        // -v and -c show files without matches too, so they need them all
+        if config.indexed && !options.invert && !options.count {
+            let candidates = indexed_candidates(path, files, &matcher)?;
+            // the files left out count as searched, with no matches
+            for _ in 0..candidates.ruled_out {
+                summary.add_file(0);
+            }
+            files = candidates.files;
+        }
+        search_files(&files, &matcher, &options, threads, &mut summary, out)?;
+    } else if config.file_path == "-" {
+        let stdin = io::stdin();
+        let count = write_file_results(
+            out,
+            &matcher,
+            STDIN_NAME,
+            false,
+            stdin.lock(),
+            &config.output,
+        )?;
+        summary.add_file(count);
+    } else {
+        let input = BufReader::new(File::open(path)?);
+        let count = write_file_results(
+            out,
+            &matcher,
+            &config.file_path,
+            false,
+            input,
+            &config.output,
+        )?;
+        summary.add_file(count);
+    }
+
+    if config.output.json {
+        json::write_end(out, &summary)?;
+    }
+
+    Ok(())
+}
+
+// Searches each of `files` on `threads` threads, writing the results in
+// order. A file that can't be read is reported on stderr and skipped, as
+// grep does, so one unreadable file doesn't end the whole search.
+fn search_files<W: Write>(
+    files: &[PathBuf],
+    matcher: &Matcher,
+    options: &OutputOptions,
+    threads: usize,
+    summary: &mut json::Summary,
+    out: &mut W,
+) -> io::Result<()> {
+    parallel::map_ordered(
+        files,
+        threads,
+        |file| {
+            search_file(file, matcher, options)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", file.display())))
+        },
+        |searched| {
+            match searched {
+                Ok(Some((results, count))) => {
+                    summary.add_file(count);
+                    out.write_all(&results)?;
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("{e}"),
+            }
+            Ok(())
+        },
+    )
+}
+
+// The results for one file of a directory, and how many lines matched, or
+// `None` for a binary file.
+fn search_file(
+    file: &Path,
+    matcher: &Matcher,
+    options: &OutputOptions,
+) -> io::Result<Option<(Vec<u8>, usize)>> {
+    let mut input = BufReader::new(File::open(file)?);
+    if lines::looks_binary(&mut input)? {
+        return Ok(None);
+    }
+
+    let mut results = Vec::new();
+    let shown = file.display().to_string();
+    let count = write_file_results(&mut results, matcher, &shown, true, input, options)?;
+    Ok(Some((results, count)))
+}
+
+// Narrows the `files` under `dir` with its index, or keeps them all if the
+// index can't be used. Either way no file with a match is left out, though
+// files without one are, which -v and -c would have shown.
+fn indexed_candidates(
+    dir: &Path,
+    files: Vec<PathBuf>,
+    matcher: &Matcher,
+) -> io::Result<Candidates> {
+    let index = match Index::load(dir) {
+        Ok(index) => index,
+        Err(e) => {
+            eprintln!(
+                "Can't use the index in {}: {e}; searching every file",
+                dir.display()
+            );
+            return Ok(Candidates {
+                files,
+                ..Candidates::default()
+            });
+        }
+    };
+    let needed = matcher.trigrams();
+    let candidates = index.candidates(dir, files, &needed)?;
+    if candidates.stale > 0 {
+        eprintln!(
+            "Files changed since the index was built: {}; run 'minigrep index build {}' to update it",
+            candidates.stale,
+            dir.display()
+        );
+    }
+    Ok(candidates)
+}
+
+// Rewrites the matches in the file or directory named in `config`, or with
+// --dry-run, writes a diff of what would change.
+fn replace_path<W: Write>(
+    config: &Config,
+    matcher: &Matcher,
+    replacement: &str,
+    out: &mut W,
+) -> Result<(), Box<dyn Error>> {
+    let template = Template::parse(replacement, matcher.group_count())?;
+    if config.file_path == "-" {
+        return Err("--replace needs a file or directory to rewrite".into());
+    }
+
+    let path = Path::new(&config.file_path);
+    let mut failed = 0;
+    for file in walk::walk(path)? {
+        // like a search, one file that can't be rewritten doesn't stop the rest
+        let (lines, changes) = match replace_file(
+            &file,
+            path.is_dir(),
+            |line| matcher.replace(line, &template),
+            config.dry_run,
+        ) {
+            Ok(Some(replaced)) => replaced,
+            Ok(None) => continue,
+            Err(e) => {
+                eprintln!("{}: {e}", file.display());
+                failed += 1;
+                continue;
+            }
+        };
+
+        let shown = file.display().to_string();
+        if config.dry_run {
+            replace::write_diff(out, &shown, &lines, &changes)?;
+        } else if !changes.is_empty() {
+            writeln!(out, "{shown}: replaced {} lines", changes.len())?;
+        }
+    }
+
+    if failed > 0 {
+        let files = if failed == 1 { "file" } else { "files" };
+        return Err(format!("{failed} {files} couldn't be rewritten").into());
+    }
+    Ok(())
+}
+
+// Rewrites one file, or returns `None` for a binary file found in a
+// directory, which is left alone.
+fn replace_file(
+    file: &Path,
+    in_dir: bool,
+    replace: impl Fn(&str) -> Option<String>,
+    dry_run: bool,
+) -> io::Result<Option<(Vec<String>, Vec<replace::Change>)>> {
+    if in_dir && lines::looks_binary(&mut BufReader::new(File::open(file)?))? {
+        return Ok(None);
+    }
+    replace::replace_in_file(file, replace, dry_run).map(Some)
+}
+
+// Searches one input and writes the results in the format `options` asks
+// for, returning the number of matches. `show_path` puts the path on every line.
+fn write_file_results<R: BufRead, W: Write>(
+    out: &mut W,
+    matcher: &Matcher,
+    path: &str,
+    show_path: bool,
+    input: R,
+    options: &OutputOptions,
+) -> io::Result<usize> {
+    if options.json {
+        return json::write_matches(out, path, input, |line| matcher.find(line), options.invert);
+    }
+
+    // -l still names the file when there's only one
+    let shown = (show_path || options.files_with_matches).then_some(path);
+    output::write_results(out, shown, input, |line| matcher.find(line), options)
+}
+
+// The kind of matching a Config asks for, set up once before the search.
+enum Matcher {
+    Literal(Literal),
+    // also for only normalizing, without folding case
+    IgnoreCase(IgnoreCase),
+    Regex(Regex),
+    Fuzzy(Box<Fuzzy>),
+    // only the inner matcher's matches that are whole words
+    WholeWord(Box<Matcher>),
+}
+
+impl Matcher {
+    fn new(config: &Config) -> Result<Matcher, regex::Error> {
+        let matcher = Matcher::for_query(config)?;
+        Ok(if config.whole_word {
+            Matcher::WholeWord(Box::new(matcher))
+        } else {
+            matcher
+        })
+    }
+
+    fn for_query(config: &Config) -> Result<Matcher, regex::Error> {
+        let prepare = Prepare {
+            fold: config.ignore_case.then_some(if config.turkish {
+                Folding::Turkish
+            } else {
+                Folding::Full
+            }),
+            normalize: config.normalize,
+        };
+
+        Ok(if let Some(distance) = config.fuzzy {
+            Matcher::Fuzzy(Box::new(Fuzzy::new(
+                &config.query,
+                distance,
+                config.ignore_case,
+            )))
+        } else if config.regex {
+            Matcher::Regex(Regex::build(&config.query, config.ignore_case)?)
+        } else if prepare != Prepare::default() {
+            Matcher::IgnoreCase(IgnoreCase::with(&config.query, prepare))
+        } else {
+            Matcher::Literal(Literal::new(&config.query))
+        })
+    }
+
+    // the byte ranges of every non-overlapping match in the line
+    fn find_all(&self, line: &str) -> Vec<(usize, usize)> {
+        match self {
+            Matcher::Literal(literal) => ranges(literal.find_in_line(line)),
+            Matcher::IgnoreCase(ignore_case) => ranges(ignore_case.find_in_line(line)),
+            Matcher::Regex(re) => re.find_iter(line).collect(),
+            Matcher::Fuzzy(fuzzy) => ranges(fuzzy.find_in_line(line)),
+            Matcher::WholeWord(inner) => inner
+                .find_all(line)
+                .into_iter()
+                .filter(|&(start, end)| is_whole_word(line, start, end))
+                .collect(),
+        }
+    }
+
+    // the matches in the line, with their distances when they're fuzzy
+    fn find(&self, line: &str) -> Vec<Found> {
+        match self {
+            Matcher::Fuzzy(fuzzy) => fuzzy
+                .find_all(line)
+                .into_iter()
+                .map(|m| Found {
+                    start: m.start,
+                    end: m.end,
+                    distance: Some(m.distance),
+                })
+                .collect(),
+            Matcher::WholeWord(inner) => inner
+                .find(line)
+                .into_iter()
+                .filter(|m| is_whole_word(line, m.start, m.end))
+                .collect(),
+            _ => self.find_all(line).into_iter().map(Found::from).collect(),
+        }
+    }
+
+    // the trigrams a line needs to have for this to match it; see index.rs
+    fn trigrams(&self) -> Vec<u32> {
+        match self {
+            Matcher::Literal(literal) => index::trigrams(&index::fold(literal.query())),
+            // already folded the way the index folds text; the index
+            // doesn't normalize or fold the Turkish way, though
+            Matcher::IgnoreCase(ignore_case) => match ignore_case.prepare() {
+                Prepare {
+                    fold: Some(Folding::Full),
+                    normalize: None,
+                } => index::trigrams(ignore_case.query()),
+                _ => Vec::new(),
+            },
+            Matcher::WholeWord(inner) => inner.trigrams(),
+            // a regex has no fixed text and a fuzzy match may have none of
+            // the query's trigrams, so any file might match
+            Matcher::Regex(_) | Matcher::Fuzzy(_) => Vec::new(),
+        }
+    }
+
+    // the number of groups a replacement can refer to, counting the whole match
+    fn group_count(&self) -> usize {
+        match self {
+            Matcher::Regex(re) => re.group_count(),
+            Matcher::WholeWord(inner) => inner.group_count(),
+            _ => 1,
+        }
+    }
+
+    // the line with every match replaced, or None if nothing matched
+    fn replace(&self, line: &str, template: &Template) -> Option<String> {
+        let mut replaced = String::new();
+        let mut last = 0;
+        let mut matched = false;
+
+        let (regex, whole_word) = match self {
+            Matcher::Regex(re) => (Some(re), false),
+            Matcher::WholeWord(inner) => match inner.as_ref() {
+                Matcher::Regex(re) => (Some(re), true),
+                // find_all already leaves out what isn't a whole word
+                _ => (None, false),
+            },
+            _ => (None, false),
+        };
+
+        match regex {
+            Some(re) => {
+                for caps in re.captures_iter(line) {
+                    let (start, end) = caps.get(0)?;
+                    if whole_word && !is_whole_word(line, start, end) {
+                        continue;
+                    }
+                    replaced.push_str(&line[last..start]);
+                    template.expand(|i| caps.get(i).map(|(s, e)| &line[s..e]), &mut replaced);
+                    last = end;
+                    matched = true;
+                }
+            }
+            None => {
+                for (start, end) in self.find_all(line) {
+                    replaced.push_str(&line[last..start]);
+                    template.expand(|i| (i == 0).then(|| &line[start..end]), &mut replaced);
+                    last = end;
+                    matched = true;
+                }
+            }
+        }
+
+        if !matched {
+            return None;
+        }
+        replaced.push_str(&line[last..]);
+        Some(replaced)
+    }
+}
+
+// a Searcher's ranges as the (start, end) pairs the output uses
+fn ranges(found: Vec<Range<usize>>) -> Vec<(usize, usize)> {
+    found.into_iter().map(|r| (r.start, r.end)).collect()
+}
+
+/// Returns the lines of `contents` that contain `query`.
+pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+    contents
+        .lines()
+        .filter(|line| line.contains(query))
+        .collect()
+}
+
+/// Like `search`, but ignores the case of both the query and the lines.
+pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+    let query = query.to_lowercase();
+
+    contents
+        .lines()
+        .filter(|line| line.to_lowercase().contains(&query))
+        .collect()
+}
+
+/// Returns the lines of `contents` that `re` matches somewhere.
+pub fn search_regex<'a>(re: &Regex, contents: &'a str) -> Vec<&'a str> {
+    contents.lines().filter(|line| re.is_match(line)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::fs;
+
+    const POEM: &str = include_str!("../poem.txt");
+
+    fn args(list: &[&str]) -> impl Iterator<Item = String> {
+        list.iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn build_reads_query_and_path() {
+        let config = Config::default()
+            .with_args(args(&["minigrep", "to", "poem.txt"]))
+            .unwrap();
+        assert_eq!(config.query, "to");
+        assert_eq!(config.file_path, "poem.txt");
+    }
+
+    #[test]
+    fn build_needs_a_query() {
+        let result = Config::default().with_args(args(&["minigrep"]));
+        assert_eq!(result.err(), Some("Didn't get a query string"));
+    }
+
+    #[test]
+    fn build_defaults_to_stdin() {
+        let config = Config::default()
+            .with_args(args(&["minigrep", "to"]))
+            .unwrap();
+        assert_eq!(config.file_path, "-");
+
+        let config = Config::default()
+            .with_args(args(&["minigrep", "to", "-"]))
+            .unwrap();
+        assert_eq!(config.file_path, "-");
+    }
+
+    #[test]
+    fn build_rejects_extra_arguments() {
+        let result = Config::default().with_args(args(&["minigrep", "to", "poem.txt", "extra"]));
+        assert_eq!(result.err(), Some("Too many arguments"));
+    }
+
+    #[test]
+    fn build_reads_regex_flag() {
+        let config = Config::default()
+            .with_args(args(&["minigrep", "--regex", "^To", "poem.txt"]))
+            .unwrap();
+        assert!(config.regex);
+        assert_eq!(config.query, "^To");
+        assert_eq!(config.file_path, "poem.txt");
+    }
+
+    #[test]
+    fn build_rejects_unknown_options() {
+        let result =
+            Config::default().with_args(args(&["minigrep", "--frobnicate", "to", "poem.txt"]));
+        assert_eq!(result.err(), Some("Unrecognized option"));
+    }
+
+    #[test]
+    fn one_result() {
+        let query = "duct";
+        let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Duct tape.";
+
+        assert_eq!(vec!["safe, fast, productive."], search(query, contents));
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let query = "rUsT";
+        let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
+
+        assert_eq!(
+            vec!["Rust:", "Trust me."],
+            search_case_insensitive(query, contents)
+        );
+    }
+
+    #[test]
+    fn poem_to() {
+        assert_eq!(
+            vec!["Are you nobody, too?", "How dreary to be somebody!"],
+            search("to", POEM)
+        );
+    }
+
+    #[test]
+    fn poem_to_case_insensitive() {
+        assert_eq!(
+            vec![
+                "Are you nobody, too?",
+                "How dreary to be somebody!",
+                "To tell your name the livelong day",
+                "To an admiring bog!",
+            ],
+            search_case_insensitive("to", POEM)
+        );
+    }
+
+    #[test]
+    fn poem_no_match() {
+        assert!(search("monomorphization", POEM).is_empty());
+    }
+
+    #[test]
+    fn poem_regex() {
+        let re = Regex::new("^(How|To) ").unwrap();
+        assert_eq!(
+            vec![
+                "How dreary to be somebody!",
+                "How public, like a frog",
+                "To tell your name the livelong day",
+                "To an admiring bog!",
+            ],
+            search_regex(&re, POEM)
+        );
+    }
+
+    #[test]
+    fn build_reads_output_flags() {
+        let config = Config::default()
+            .with_args(args(&["minigrep", "-nv", "-c", "-l", "to", "poem.txt"]))
+            .unwrap();
+        assert_eq!(
+            config.output,
+            OutputOptions {
+                line_numbers: true,
+                invert: true,
+                count: true,
+                files_with_matches: true,
+                ..OutputOptions::default()
+            }
+        );
+    }
+
+    #[test]
+    fn build_reads_context_flags() {
+        let config = Config::default()
+            .with_args(args(&["minigrep", "-A", "2", "-B1", "to", "poem.txt"]))
+            .unwrap();
+        assert_eq!(
+            (config.output.before_context, config.output.after_context),
+            (1, 2)
+        );
+
+        let config = Config::default()
+            .with_args(args(&["minigrep", "-C3", "to", "poem.txt"]))
+            .unwrap();
+        assert_eq!(
+            (config.output.before_context, config.output.after_context),
+            (3, 3)
+        );
+
+        let result =
+            Config::default().with_args(args(&["minigrep", "-C", "lots", "to", "poem.txt"]));
+        assert_eq!(result.err(), Some("Context length must be a number"));
+
+        let result = Config::default().with_args(args(&["minigrep", "to", "poem.txt", "-A"]));
+        assert_eq!(result.err(), Some("Didn't get a context length"));
+    }
+
+    #[test]
+    fn build_reads_color_flag() {
+        let config = Config::default()
+            .with_args(args(&["minigrep", "to", "poem.txt"]))
+            .unwrap();
+        assert_eq!(config.output.color, ColorChoice::Auto);
+
+        let config =
+            Config::default().with_args(args(&["minigrep", "--color=always", "to", "poem.txt"]));
+        assert_eq!(config.unwrap().output.color, ColorChoice::Always);
+
+        let config =
+            Config::default().with_args(args(&["minigrep", "--color=never", "--color", "to"]));
+        assert_eq!(config.unwrap().output.color, ColorChoice::Auto);
+
+        let result =
+            Config::default().with_args(args(&["minigrep", "--color=red", "to", "poem.txt"]));
+        assert_eq!(result.err(), Some("Color must be auto, always or never"));
+    }
+
+    #[test]
+    fn flags_record_their_source() {
+        let config = Config::default()
+            .with_args(args(&["minigrep", "-in", "-A1", "to", "poem.txt"]))
+            .unwrap();
+        assert!(config.ignore_case);
+        assert_eq!(config.sources.ignore_case, Source::Flag);
+        assert_eq!(config.sources.line_numbers, Source::Flag);
+        assert_eq!(config.sources.after_context, Source::Flag);
+        assert_eq!(config.sources.before_context, Source::Default);
+        assert_eq!(config.sources.regex, Source::Default);
+    }
+
+    #[test]
+    fn print_config_needs_no_query() {
+        let config = Config::default()
+            .with_args(args(&["minigrep", "--print-config"]))
+            .unwrap();
+        assert!(config.print_config);
+        assert_eq!(config.query, "");
+    }
+
+    #[test]
+    fn build_reads_index_commands() {
+        let config = Config::default()
+            .with_args(args(&["minigrep", "index", "build", "src"]))
+            .unwrap();
+        assert!(config.build_index);
+        assert_eq!(config.file_path, "src");
+
+        // two words are still a search for "index" in the file "build"
+        let config = Config::default()
+            .with_args(args(&["minigrep", "index", "build"]))
+            .unwrap();
+        assert!(!config.build_index);
+        assert_eq!(
+            (config.query.as_str(), config.file_path.as_str()),
+            ("index", "build")
+        );
+
+        let config = Config::default()
+            .with_args(args(&["minigrep", "--indexed", "frog"]))
+            .unwrap();
+        assert!(config.indexed);
+        assert_eq!(config.file_path, ".");
+    }
+
+    #[test]
+    fn indexed_search_finds_the_same_lines() {
+        let dir = TempDir::new("lib-indexed");
+        dir.file("poem.txt", POEM);
+        dir.file("notes/pond.txt", "A Frog in the pond\n");
+        dir.file("notes/list.txt", "eggs\nmilk\n");
+
+        let search = |query: &str, ignore_case: bool, indexed: bool| {
+            let config = Config {
+                query: query.to_string(),
+                file_path: dir.path().display().to_string(),
+                ignore_case,
+                indexed,
+                ..Config::default()
+            };
+            let mut out = Vec::new();
+            search_path(&config, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        // with no index yet, every file is searched
+        assert_eq!(search("frog", false, true), search("frog", false, false));
+
+        index::build(dir.path()).unwrap();
+        for (query, ignore_case) in [
+            ("frog", false),
+            ("frog", true),
+            ("milk", false),
+            ("to", true),
+        ] {
+            assert_eq!(
+                search(query, ignore_case, true),
+                search(query, ignore_case, false),
+                "{query} ignore_case={ignore_case}"
+            );
+        }
+        assert!(search("frog", true, true).contains("pond.txt:1:A Frog"));
+
+        // -v and -c show the files that don't match too
+        let search_with = |output: OutputOptions, indexed: bool| {
+            let config = Config {
+                query: String::from("frog"),
+                file_path: dir.path().display().to_string(),
+                indexed,
+                output,
+                ..Config::default()
+            };
+            let mut out = Vec::new();
+            search_path(&config, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let outputs = [
+            OutputOptions {
+                invert: true,
+                ..OutputOptions::default()
+            },
+            OutputOptions {
+                count: true,
+                ..OutputOptions::default()
+            },
+            OutputOptions {
+                json: true,
+                ..OutputOptions::default()
+            },
+        ];
+        for output in outputs {
+            assert_eq!(
+                search_with(output.clone(), true),
+                search_with(output, false)
+            );
+        }
+        assert!(search_with(
+            OutputOptions {
+                count: true,
+                ..OutputOptions::default()
+            },
+            true
+        )
+        .contains("list.txt:0"));
+
+        // a file changed after the build is still searched
+        dir.file("notes/list.txt", "eggs\nfrogspawn\n");
+        assert!(search("frog", false, true).contains("list.txt:2:frogspawn"));
+    }
+
+    #[test]
+    fn directories_get_line_numbers_unless_turned_off() {
+        let dir = Path::new("src");
+        let config = Config::default()
+            .with_args(args(&["minigrep", "x"]))
+            .unwrap();
+        assert!(config.output_for(dir).line_numbers);
+        assert!(!config.output_for(Path::new("poem.txt")).line_numbers);
+
+        let config = Config::default()
+            .with_args(args(&["minigrep", "--no-line-numbers", "x"]))
+            .unwrap();
+        assert!(!config.output_for(dir).line_numbers);
+
+        let mut config = Config::default();
+        settings::apply_file(&mut config, Path::new("rc"), "line_numbers = false").unwrap();
+        assert!(!config.output_for(dir).line_numbers);
+    }
+
+    #[test]
+    fn unreadable_files_are_skipped() {
+        let dir = TempDir::new("lib-unreadable");
+        dir.file("a.txt", "a frog\n");
+        dir.file("c.txt", "c frog\n");
+        // as if b.txt was deleted between the walk and the search
+        let files = ["a.txt", "b.txt", "c.txt"].map(|name| dir.join(name));
+
+        let config = Config {
+            query: String::from("frog"),
+            ..Config::default()
+        };
+        let matcher = Matcher::new(&config).unwrap();
+        let options = OutputOptions {
+            line_numbers: true,
+            ..OutputOptions::default()
+        };
+        for threads in [1, 3] {
+            let mut out = Vec::new();
+            let mut summary = json::Summary::default();
+            search_files(&files, &matcher, &options, threads, &mut summary, &mut out).unwrap();
+            let out = String::from_utf8(out).unwrap();
+            assert!(out.contains("a.txt:1:a frog"), "{out}");
+            assert!(out.contains("c.txt:1:c frog"), "{out}");
+        }
+    }
+
+    #[test]
+    fn build_reads_fuzzy_flag() {
+        let config = Config::default()
+            .with_args(args(&["minigrep", "--fuzzy", "1", "frog", "poem.txt"]))
+            .unwrap();
+        assert_eq!(config.fuzzy, Some(1));
+
+        let config = Config::default()
+            .with_args(args(&["minigrep", "--fuzzy=2", "frog", "poem.txt"]))
+            .unwrap();
+        assert_eq!(config.fuzzy, Some(2));
+
+        let errors = [
+            (
+                vec!["--fuzzy", "one", "frog"],
+                "Fuzzy distance must be a number",
+            ),
+            (vec!["frog", "--fuzzy"], "Didn't get a fuzzy distance"),
+            (
+                vec!["--fuzzy=4", "frog"],
+                "Fuzzy distance must be less than the query length",
+            ),
+            (
+                vec!["--fuzzy=1", "--regex", "fr.g"],
+                "--fuzzy can't be used with --regex",
+            ),
+        ];
+        for (flags, expected) in errors {
+            let result = Config::default().with_args(args(&[&["minigrep"], &flags[..]].concat()));
+            assert_eq!(result.err(), Some(expected));
+        }
+    }
+
+    #[test]
+    fn fuzzy_search_reports_distances() {
+        let config = Config::default()
+            .with_args(args(&[
+                "minigrep", "-n", "--fuzzy", "1", "admring", "poem.txt",
+            ]))
+            .unwrap();
+        let mut out = Vec::new();
+        search_path(&config, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "9:1:To an admiring bog!\n");
+
+        // and a replacement can fix the typo
+        let matcher = Matcher::new(&config).unwrap();
+        let template = Template::parse("admiring", 1).unwrap();
+        assert_eq!(
+            matcher.replace("an admring bog", &template).as_deref(),
+            Some("an admiring bog")
+        );
+    }
+
+    #[test]
+    fn build_reads_word_and_unicode_flags() {
+        let config = Config::default()
+            .with_args(args(&["minigrep", "-wn", "--normalize=nfc", "to"]))
+            .unwrap();
+        assert!(config.whole_word);
+        assert_eq!(config.normalize, Some(Normalization::Nfc));
+        assert!(!config.ignore_case);
+
+        // Turkish folding is a kind of ignoring case
+        let config = Config::default()
+            .with_args(args(&["minigrep", "--turkish", "--normalize", "NFD", "to"]))
+            .unwrap();
+        assert!(config.ignore_case);
+        assert_eq!(config.normalize, Some(Normalization::Nfd));
+
+        let cases = [
+            (
+                &["minigrep", "--normalize=nfkc", "to"][..],
+                "Normalization must be nfc or nfd",
+            ),
+            (
+                &["minigrep", "to", "--normalize"],
+                "Didn't get a normalization form",
+            ),
+            (
+                &["minigrep", "--regex", "--turkish", "to"],
+                "--normalize and --turkish can't be used with --regex or --fuzzy",
+            ),
+            (
+                &["minigrep", "--fuzzy", "1", "--normalize=nfc", "to"],
+                "--normalize and --turkish can't be used with --regex or --fuzzy",
+            ),
+        ];
+        for (list, message) in cases {
+            assert_eq!(
+                Config::default().with_args(args(list)).unwrap_err(),
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn whole_word_search() {
+        let search = |list: &[&str]| {
+            let config = Config::default().with_args(args(list)).unwrap();
+            let mut out = Vec::new();
+            search_path(&config, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        // "to" is inside "too" and "Nobody" too, but isn't a word there
+        assert_eq!(
+            search(&["minigrep", "-wn", "to", "poem.txt"]),
+            "6:How dreary to be somebody!\n"
+        );
+        assert_eq!(
+            search(&["minigrep", "-wc", "--ignore-case", "to", "poem.txt"]),
+            "3\n"
+        );
+        // an apostrophe inside a word doesn't end it
+        assert_eq!(search(&["minigrep", "-wc", "don", "poem.txt"]), "0\n");
+        assert_eq!(search(&["minigrep", "-wc", "don't", "poem.txt"]), "1\n");
+    }
+
+    #[test]
+    fn build_reads_watch_flag() {
+        let config = Config::default()
+            .with_args(args(&["minigrep", "--watch", "ERROR", "app.log"]))
+            .unwrap();
+        assert!(config.watch);
+        assert_eq!(config.file_path, "app.log");
+
+        let cases = [
+            (
+                &["minigrep", "--watch", "ERROR"][..],
+                "--watch needs a file or directory to watch",
+            ),
+            (
+                &["minigrep", "--watch", "-c", "ERROR", "app.log"],
+                "--watch only prints lines; it can't be used with -c, -l or --json",
+            ),
+            (
+                &["minigrep", "--watch", "--replace", "x", "ERROR", "app.log"],
+                "--watch can't be used with --replace or --indexed",
+            ),
+        ];
+        for (list, message) in cases {
+            assert_eq!(
+                Config::default().with_args(args(list)).unwrap_err(),
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn build_reads_thread_count() {
+        let config = Config::default()
+            .with_args(args(&["minigrep", "--threads", "4", "to", "."]))
+            .unwrap();
+        assert_eq!(config.threads, 4);
+
+        let config = Config::default()
+            .with_args(args(&["minigrep", "--threads=2", "to", "."]))
+            .unwrap();
+        assert_eq!(config.threads, 2);
+
+        let config = Config::default()
+            .with_args(args(&["minigrep", "to", "."]))
+            .unwrap();
+        assert_eq!(config.threads, 0);
+
+        let result = Config::default().with_args(args(&["minigrep", "--threads", "0", "to", "."]));
+        assert_eq!(result.err(), Some("Thread count must be a positive number"));
+    }
+
+    #[test]
+    fn threaded_search_matches_single_threaded() {
+        let search = |threads| {
+            let config = Config {
+                query: String::from("fn "),
+                file_path: String::from("src"),
+                threads,
+                ..Config::default()
+            };
+            let mut out = Vec::new();
+            search_path(&config, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let single = search(1);
+        assert!(single.contains("src/lib.rs:"));
+        assert_eq!(single, search(4));
+    }
+
+    #[test]
+    fn find_all_gives_byte_ranges() {
+        let literal = Matcher::Literal(Literal::new("o"));
+        assert_eq!(literal.find_all("nobody"), vec![(1, 2), (3, 4)]);
+
+        let ignore_case = Matcher::IgnoreCase(IgnoreCase::new("to"));
+        assert_eq!(
+            ignore_case.find_all("To tell, too, TO"),
+            vec![(0, 2), (9, 11), (14, 16)]
+        );
+
+        // 'É' is two bytes, so the offsets must count bytes, not characters
+        let ignore_case = Matcher::IgnoreCase(IgnoreCase::new("été"));
+        assert_eq!(ignore_case.find_all("un ÉTÉ chaud"), vec![(3, 8)]);
+
+        // 'ß' folds to two letters, so one byte range can match several
+        let ignore_case = Matcher::IgnoreCase(IgnoreCase::new("ss"));
+        assert_eq!(ignore_case.find_all("Maße, MASSE"), vec![(2, 4), (9, 11)]);
+    }
+
+    #[test]
+    fn color_highlights_multibyte_matches() {
+        let input = "un été à Paris\nÉTÉ 🐸 été\n".as_bytes();
+        let options = OutputOptions {
+            color: ColorChoice::Always,
+            ..OutputOptions::default()
+        };
+        let mut out = Vec::new();
+        let matcher = Matcher::IgnoreCase(IgnoreCase::new("été"));
+        write_file_results(&mut out, &matcher, "-", false, input, &options).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "un \x1b[1;31mété\x1b[0m à Paris\n\
+             \x1b[1;31mÉTÉ\x1b[0m 🐸 \x1b[1;31mété\x1b[0m\n"
+        );
+
+        let mut out = Vec::new();
+        let matcher = Matcher::Regex(Regex::new("🐸|à").unwrap());
+        write_file_results(&mut out, &matcher, "-", false, input, &options).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "un été \x1b[1;31mà\x1b[0m Paris\nÉTÉ \x1b[1;31m🐸\x1b[0m été\n"
+        );
+    }
+
+    #[test]
+    fn json_output_has_begin_and_end() {
+        let config = Config::default()
+            .with_args(args(&["minigrep", "--json", "frog", "poem.txt"]))
+            .unwrap();
+        let mut out = Vec::new();
+        search_path(&config, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            r#"{"type":"begin","query":"frog","path":"poem.txt"}"#
+        );
+        assert_eq!(
+            lines[1],
+            r#"{"type":"match","path":"poem.txt","line_number":7,"start":161,"end":165,"text":"frog","line":"How public, like a frog"}"#
+        );
+        assert_eq!(
+            lines[2],
+            r#"{"type":"end","files":1,"files_with_matches":1,"matches":1}"#
+        );
+    }
+
+    #[test]
+    fn json_is_only_for_matches() {
+        for flag in ["-c", "-l"] {
+            assert_eq!(
+                Config::default()
+                    .with_args(args(&["minigrep", "--json", flag, "frog", "poem.txt"]))
+                    .unwrap_err(),
+                "--json can't be used with -c or -l"
+            );
+        }
+
+        // a search that can't start writes no JSON at all
+        let config = Config::default()
+            .with_args(args(&[
+                "minigrep",
+                "--json",
+                "--indexed",
+                "frog",
+                "poem.txt",
+            ]))
+            .unwrap();
+        let mut out = Vec::new();
+        let err = search_path(&config, &mut out).unwrap_err();
+        assert_eq!(err.to_string(), "--indexed only works on a directory");
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn file_results_from_a_reader() {
+        let matcher = Matcher::Literal(Literal::new("to"));
+        let input: &[u8] = b"to be\n\xffnot \xfeto\nsomebody\n";
+        let mut out = Vec::new();
+        let options = OutputOptions {
+            line_numbers: true,
+            ..OutputOptions::default()
+        };
+
+        let count = write_file_results(&mut out, &matcher, "-", false, input, &options).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "1:to be\n2:\u{fffd}not \u{fffd}to\n"
+        );
+    }
+
+    #[test]
+    fn build_reads_replace_flags() {
+        let config = Config::default()
+            .with_args(args(&[
+                "minigrep",
+                "--replace",
+                "toad",
+                "--dry-run",
+                "frog",
+                "poem.txt",
+            ]))
+            .unwrap();
+        assert_eq!(config.replace.as_deref(), Some("toad"));
+        assert!(config.dry_run);
+
+        let config = Config::default()
+            .with_args(args(&["minigrep", "--replace=", "frog", "poem.txt"]))
+            .unwrap();
+        assert_eq!(config.replace.as_deref(), Some(""));
+
+        let result =
+            Config::default().with_args(args(&["minigrep", "--dry-run", "frog", "poem.txt"]));
+        assert_eq!(
+            result.err(),
+            Some("--dry-run only makes sense with --replace")
+        );
+    }
+
+    #[test]
+    fn matcher_replace() {
+        let template = Template::parse("[$0]", 1).unwrap();
+        let literal = Matcher::Literal(Literal::new("o"));
+        assert_eq!(
+            literal.replace("to bog", &template).as_deref(),
+            Some("t[o] b[o]g")
+        );
+        assert_eq!(literal.replace("frag", &template), None);
+
+        let re = Regex::new("(\\w+)body").unwrap();
+        let template = Template::parse("$1one", re.group_count()).unwrap();
+        let regex = Matcher::Regex(re);
+        assert_eq!(
+            regex
+                .replace("I'm nobody! Who are you?", &template)
+                .as_deref(),
+            Some("I'm noone! Who are you?")
+        );
+
+        // replacing a match with nothing still counts as a change
+        let empty = Template::parse("", 1).unwrap();
+        assert_eq!(
+            Matcher::Literal(Literal::new("I'm "))
+                .replace("I'm nobody", &empty)
+                .as_deref(),
+            Some("nobody")
+        );
+    }
+
+    #[test]
+    fn dry_run_prints_a_diff() {
+        let config = Config::default()
+            .with_args(args(&[
+                "minigrep",
+                "--regex",
+                "--replace",
+                "$1 pond",
+                "--dry-run",
+                "(admiring) bog",
+                "poem.txt",
+            ]))
+            .unwrap();
+        let mut out = Vec::new();
+        search_path(&config, &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+--- a/poem.txt
++++ b/poem.txt
+@@ -6,4 +6,4 @@
+ How dreary to be somebody!
+ How public, like a frog
+ To tell your name the livelong day
+-To an admiring bog!
++To an admiring pond!
+"
+        );
+    }
+
+    #[test]
+    fn replacing_goes_on_past_files_it_cant_rewrite() {
+        let dir = TempDir::new("lib-replace");
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            dir.file(name, format!("{name} toad\n"));
+        }
+        // something in the way of b.txt's temporary file
+        let temp = format!(".b.txt.minigrep-{}.tmp", std::process::id());
+        fs::create_dir(dir.join(&temp)).unwrap();
+
+        let config = Config {
+            query: String::from("toad"),
+            file_path: dir.path().display().to_string(),
+            replace: Some(String::from("frog")),
+            ..Config::default()
+        };
+        let mut out = Vec::new();
+        let err = search_path(&config, &mut out).unwrap_err();
+        assert_eq!(err.to_string(), "1 file couldn't be rewritten");
+        assert_eq!(
+            fs::read_to_string(dir.join("a.txt")).unwrap(),
+            "a.txt frog\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("b.txt")).unwrap(),
+            "b.txt toad\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("c.txt")).unwrap(),
+            "c.txt frog\n"
+        );
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("c.txt: replaced 1 lines"), "{out}");
+    }
+
+    #[test]
+    fn matcher_follows_config() {
+        let config = Config::default()
+            .with_args(args(&["minigrep", "--regex", "bog|frog", "poem.txt"]))
+            .unwrap();
+        let matcher = Matcher::new(&config).unwrap();
+        assert!(!matcher.find_all("How public, like a frog").is_empty());
+        assert!(matcher.find_all("How dreary to be somebody!").is_empty());
+    }
+
+    #[test]
+    fn run_reports_missing_file() {
+        let config = Config {
+            query: String::from("to"),
+            file_path: String::from("no-such-file.txt"),
+            ..Config::default()
+        };
+        assert!(run(config).is_err());
+    }
+
+    #[test]
+    fn run_reports_bad_regex() {
+        let config = Config {
+            query: String::from("(to"),
+            file_path: String::from("poem.txt"),
+            regex: true,
+            ..Config::default()
+        };
+        let err = run(config).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid regex at position 0: unclosed group"
+        );
+    }
+}
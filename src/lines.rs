@@ -0,0 +1,170 @@
+//Reading lines from any BufRead, one at a time
+/*
+The book version of minigrep reads the whole file with 'fs::read_to_string',
+which is fine for a poem but not for a multi-gigabyte log or for text piped
+in on stdin. A LineReader pulls one line at a time out of anything that
+implements 'BufRead', so memory use only depends on the longest line.
+
+'read_to_string' also refuses files that aren't valid UTF-8. Logs often have
+a stray byte or two in them, so invalid sequences are replaced with U+FFFD
+(the same thing 'String::from_utf8_lossy' does) and the search carries on.
+*/
+
+use std::io::{self, BufRead};
+
+/// One line of input, without its line ending.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    /// The line number, counting from 1.
+    pub number: usize,
+    /// The byte offset of the start of the line in the input.
+    pub offset: u64,
+    pub text: String,
+}
+
+/// An iterator over the lines of a `BufRead`.
+pub struct LineReader<R> {
+    reader: R,
+    buf: Vec<u8>,
+    number: usize,
+    offset: u64,
+}
+
+impl<R: BufRead> LineReader<R> {
+    pub fn new(reader: R) -> LineReader<R> {
+        LineReader::resume(reader, 1, 0)
+    }
+
+    /// A reader for input that carries on from the middle of a file, where
+    /// the next line is number `line` and starts at byte `offset`.
+    pub fn resume(reader: R, line: usize, offset: u64) -> LineReader<R> {
+        LineReader {
+            reader,
+            buf: Vec::new(),
+            number: line - 1,
+            offset,
+        }
+    }
+
+    /// The byte offset just past everything read so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: BufRead> Iterator for LineReader<R> {
+    type Item = io::Result<Line>;
+
+    fn next(&mut self) -> Option<io::Result<Line>> {
+        self.buf.clear();
+        let read = match self.reader.read_until(b'\n', &mut self.buf) {
+            Ok(0) => return None,
+            Ok(read) => read,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let mut end = self.buf.len();
+        if self.buf[..end].ends_with(b"\n") {
+            end -= 1;
+            if self.buf[..end].ends_with(b"\r") {
+                end -= 1;
+            }
+        }
+
+        let line = Line {
+            number: self.number + 1,
+            offset: self.offset,
+            text: String::from_utf8_lossy(&self.buf[..end]).into_owned(),
+        };
+        self.number += 1;
+        self.offset += read as u64;
+        Some(Ok(line))
+    }
+}
+
+/// Whether the start of the input looks like a binary file, going by the
+/// same rule as grep: text files don't contain NUL bytes.
+pub fn looks_binary<R: BufRead>(reader: &mut R) -> io::Result<bool> {
+    Ok(reader.fill_buf()?.contains(&0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(input: &[u8]) -> Vec<Line> {
+        LineReader::new(input).map(|line| line.unwrap()).collect()
+    }
+
+    #[test]
+    fn numbers_and_offsets() {
+        let lines = read_all(b"to be\n\nsomebody");
+        assert_eq!(
+            lines,
+            vec![
+                Line {
+                    number: 1,
+                    offset: 0,
+                    text: String::from("to be"),
+                },
+                Line {
+                    number: 2,
+                    offset: 6,
+                    text: String::new(),
+                },
+                Line {
+                    number: 3,
+                    offset: 7,
+                    text: String::from("somebody"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn resuming_keeps_counting() {
+        let mut reader = LineReader::resume(&b"bog\nfrog"[..], 5, 20);
+        let line = reader.next().unwrap().unwrap();
+        assert_eq!((line.number, line.offset), (5, 20));
+        assert_eq!(reader.offset(), 24);
+        let line = reader.next().unwrap().unwrap();
+        assert_eq!((line.number, line.offset), (6, 24));
+        assert_eq!(reader.offset(), 28);
+    }
+
+    #[test]
+    fn strips_crlf() {
+        let lines = read_all(b"frog\r\nbog\r\n");
+        let texts: Vec<_> = lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts, vec!["frog", "bog"]);
+        assert_eq!(lines[1].offset, 6);
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced() {
+        let lines = read_all(b"caf\xe9 frog\nok\n");
+        assert_eq!(lines[0].text, "caf\u{fffd} frog");
+        assert_eq!(lines[1].text, "ok");
+        assert_eq!(lines[1].offset, 10);
+    }
+
+    #[test]
+    fn same_lines_as_str_lines() {
+        let poem = include_str!("../poem.txt");
+        let texts: Vec<String> = read_all(poem.as_bytes())
+            .into_iter()
+            .map(|l| l.text)
+            .collect();
+        assert_eq!(texts, poem.lines().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn binary_detection() {
+        assert!(looks_binary(&mut &b"ELF\0\0\x01"[..]).unwrap());
+        assert!(!looks_binary(&mut &b"plain text"[..]).unwrap());
+    }
+}
@@ -0,0 +1,53 @@
+use std::env;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+
+use minigrep::Config;
+
+fn main() {
+    let config = Config::build(env::args()).unwrap_or_else(|err| {
+        eprintln!("Problem parsing arguments: {err}");
+        process::exit(1);
+    });
+
+    if let Err(e) = write_header(&config)
+        .map_err(Into::into)
+        .and_then(|()| minigrep::run(config))
+    {
+        // the reader went away, as in `minigrep to big.log | head`
+        if let Some(e) = e.downcast_ref::<io::Error>() {
+            if e.kind() == io::ErrorKind::BrokenPipe {
+                process::exit(0);
+            }
+        }
+        eprintln!("Application error: {e}");
+        process::exit(1);
+    }
+}
+
+// Says what's being searched for, and where, before the matching lines.
+fn write_header(config: &Config) -> io::Result<()> {
+    // JSON output has to be nothing but JSON, and a diff nothing but the diff,
+    // and a count or a list of files is for other programs to read too
+    let output = &config.output;
+    let quiet = config.print_config
+        || config.build_index
+        || output.json
+        || output.count
+        || output.files_with_matches
+        || config.replace.is_some();
+    if quiet {
+        return Ok(());
+    }
+
+    let mut out = io::stdout().lock();
+    writeln!(out, "Searching for {}", config.query)?;
+    if config.file_path == "-" {
+        writeln!(out, "In standard input")
+    } else if Path::new(&config.file_path).is_dir() {
+        writeln!(out, "In directory {}", config.file_path)
+    } else {
+        writeln!(out, "In file {}", config.file_path)
+    }
+}
@@ -0,0 +1,506 @@
+//grep-style output modes
+/*
+Once we know which lines match, there are several ways to show them:
+
+    -A N / -B N / -C N   also print N lines after / before / around each match
+    -c                   print how many lines matched instead of the lines
+    -v                   invert the search and select the lines that don't match
+    -n                   put the line number in front of each line
+    -l                   print only the names of files that have a match
+
+Like grep, a selected line is written as 'path:number:text' and a context line
+as 'path-number-text' (the path only when searching more than one file), and
+groups of lines that aren't next to each other are separated by '--'.
+
+With '--color' the matches inside each selected line are highlighted as well;
+see color.rs. With '--fuzzy' each selected line also says how many edits its
+closest match needed, as in 'number:distance:text'; see fuzzy.rs.
+*/
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+
+use crate::color::{self, ColorChoice};
+use crate::lines::{Line, LineReader};
+
+/// One match in a line: its byte range, and with --fuzzy, how many edits
+/// away from the query it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Found {
+    pub start: usize,
+    pub end: usize,
+    pub distance: Option<usize>,
+}
+
+impl From<(usize, usize)> for Found {
+    fn from((start, end): (usize, usize)) -> Found {
+        Found {
+            start,
+            end,
+            distance: None,
+        }
+    }
+}
+
+/// How the results for each file are printed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutputOptions {
+    pub before_context: usize,
+    pub after_context: usize,
+    pub count: bool,
+    pub invert: bool,
+    pub line_numbers: bool,
+    pub files_with_matches: bool,
+    /// Write JSON Lines instead of text; see json.rs.
+    pub json: bool,
+    /// Only `Always` colors anything here; `run` decides what `Auto` means.
+    pub color: ColorChoice,
+}
+
+/// Reads `input` line by line, writes the results to `out` and returns how
+/// many lines were selected. `find` returns the matches in a line, and a line
+/// matches when there is at least one. `path` is printed
+/// before each line when it's given. With -l the search stops at the first
+/// selected line.
+pub fn write_results<R: BufRead, W: Write>(
+    out: &mut W,
+    path: Option<&str>,
+    input: R,
+    find: impl Fn(&str) -> Vec<Found>,
+    options: &OutputOptions,
+) -> io::Result<usize> {
+    let show_context = options.before_context > 0 || options.after_context > 0;
+    let print_lines = !options.count && !options.files_with_matches;
+
+    let mut count = 0;
+    // the last few unprinted lines, in case the next line is selected
+    let mut before: VecDeque<Line> = VecDeque::with_capacity(options.before_context);
+    let mut last_printed: Option<usize> = None;
+    let mut after_left = 0;
+
+    for line in LineReader::new(input) {
+        let line = line?;
+
+        let matches = find(&line.text);
+        if matches.is_empty() == options.invert {
+            count += 1;
+            if options.files_with_matches {
+                break;
+            }
+            if !print_lines {
+                continue;
+            }
+
+            let first = before.front().map_or(line.number, |l| l.number);
+            if show_context && last_printed.is_some_and(|n| first > n + 1) {
+                write_separator(out, "--", options)?;
+                writeln!(out)?;
+            }
+            for context in before.drain(..) {
+                write_line(out, path, &context, &[], '-', options)?;
+            }
+            // with -v the selected lines have no matches to highlight
+            write_line(out, path, &line, &matches, ':', options)?;
+
+            last_printed = Some(line.number);
+            after_left = options.after_context;
+        } else if print_lines && after_left > 0 {
+            write_line(out, path, &line, &[], '-', options)?;
+            last_printed = Some(line.number);
+            after_left -= 1;
+        } else if print_lines && options.before_context > 0 {
+            if before.len() == options.before_context {
+                before.pop_front();
+            }
+            before.push_back(line);
+        }
+    }
+
+    if options.files_with_matches {
+        if count > 0 {
+            write_path(out, path.unwrap_or("(standard input)"), options)?;
+            writeln!(out)?;
+        }
+    } else if options.count {
+        if let Some(path) = path {
+            write_path(out, path, options)?;
+            write_separator(out, ":", options)?;
+        }
+        writeln!(out, "{count}")?;
+    }
+
+    Ok(count)
+}
+
+// One line, with its path and line number as `options` asks; used by
+// watch.rs too.
+pub(crate) fn write_line<W: Write>(
+    out: &mut W,
+    path: Option<&str>,
+    line: &Line,
+    matches: &[Found],
+    separator: char,
+    options: &OutputOptions,
+) -> io::Result<()> {
+    let separator = separator.to_string();
+    if let Some(path) = path {
+        write_path(out, path, options)?;
+        write_separator(out, &separator, options)?;
+    }
+    if options.line_numbers {
+        let number = line.number.to_string();
+        if options.color == ColorChoice::Always {
+            color::write_colored(out, color::LINE_NUMBER, &number)?;
+        } else {
+            out.write_all(number.as_bytes())?;
+        }
+        write_separator(out, &separator, options)?;
+    }
+    if let Some(distance) = matches.iter().filter_map(|m| m.distance).min() {
+        write!(out, "{distance}")?;
+        write_separator(out, &separator, options)?;
+    }
+    if options.color == ColorChoice::Always {
+        let ranges: Vec<(usize, usize)> = matches.iter().map(|m| (m.start, m.end)).collect();
+        color::write_highlighted(out, &line.text, &ranges)?;
+    } else {
+        out.write_all(line.text.as_bytes())?;
+    }
+    writeln!(out)
+}
+
+fn write_path<W: Write>(out: &mut W, path: &str, options: &OutputOptions) -> io::Result<()> {
+    if options.color == ColorChoice::Always {
+        color::write_colored(out, color::PATH, path)
+    } else {
+        out.write_all(path.as_bytes())
+    }
+}
+
+fn write_separator<W: Write>(
+    out: &mut W,
+    separator: &str,
+    options: &OutputOptions,
+) -> io::Result<()> {
+    if options.color == ColorChoice::Always {
+        color::write_colored(out, color::SEPARATOR, separator)
+    } else {
+        out.write_all(separator.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POEM: &str = include_str!("../poem.txt");
+
+    fn find<'a>(query: &'a str) -> impl Fn(&str) -> Vec<Found> + 'a {
+        move |line| {
+            line.match_indices(query)
+                .map(|(start, m)| Found::from((start, start + m.len())))
+                .collect()
+        }
+    }
+
+    fn render(path: Option<&str>, query: &str, options: OutputOptions) -> String {
+        let mut out = Vec::new();
+        write_results(&mut out, path, POEM.as_bytes(), find(query), &options).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn plain() {
+        assert_eq!(
+            render(None, "to", OutputOptions::default()),
+            include_str!("../tests/expected/plain.txt")
+        );
+    }
+
+    #[test]
+    fn after_context() {
+        let options = OutputOptions {
+            after_context: 1,
+            ..OutputOptions::default()
+        };
+        assert_eq!(
+            render(None, "to", options),
+            include_str!("../tests/expected/after_context.txt")
+        );
+    }
+
+    #[test]
+    fn before_context() {
+        let options = OutputOptions {
+            before_context: 2,
+            ..OutputOptions::default()
+        };
+        assert_eq!(
+            render(None, "og", options),
+            include_str!("../tests/expected/before_context.txt")
+        );
+    }
+
+    #[test]
+    fn context_with_separators() {
+        let options = OutputOptions {
+            before_context: 1,
+            after_context: 1,
+            line_numbers: true,
+            ..OutputOptions::default()
+        };
+        assert_eq!(
+            render(None, "body", options),
+            include_str!("../tests/expected/context.txt")
+        );
+    }
+
+    #[test]
+    fn context_with_path() {
+        let options = OutputOptions {
+            before_context: 1,
+            after_context: 1,
+            line_numbers: true,
+            ..OutputOptions::default()
+        };
+        assert_eq!(
+            render(Some("poem.txt"), "frog", options),
+            include_str!("../tests/expected/context_with_path.txt")
+        );
+    }
+
+    #[test]
+    fn count() {
+        let options = OutputOptions {
+            count: true,
+            ..OutputOptions::default()
+        };
+        assert_eq!(
+            render(None, "you", options.clone()),
+            include_str!("../tests/expected/count.txt")
+        );
+        assert_eq!(
+            render(Some("poem.txt"), "you", options),
+            include_str!("../tests/expected/count_with_path.txt")
+        );
+    }
+
+    #[test]
+    fn invert() {
+        let options = OutputOptions {
+            invert: true,
+            ..OutputOptions::default()
+        };
+        assert_eq!(
+            render(None, "you", options),
+            include_str!("../tests/expected/invert.txt")
+        );
+    }
+
+    #[test]
+    fn invert_count() {
+        let options = OutputOptions {
+            invert: true,
+            count: true,
+            ..OutputOptions::default()
+        };
+        assert_eq!(render(None, "to", options), "7\n");
+    }
+
+    #[test]
+    fn line_numbers() {
+        let options = OutputOptions {
+            line_numbers: true,
+            ..OutputOptions::default()
+        };
+        assert_eq!(
+            render(None, "us", options),
+            include_str!("../tests/expected/line_numbers.txt")
+        );
+    }
+
+    #[test]
+    fn files_with_matches() {
+        let options = OutputOptions {
+            files_with_matches: true,
+            ..OutputOptions::default()
+        };
+        assert_eq!(
+            render(Some("poem.txt"), "frog", options.clone()),
+            include_str!("../tests/expected/files_with_matches.txt")
+        );
+        assert_eq!(render(Some("poem.txt"), "toad", options), "");
+    }
+
+    #[test]
+    fn color() {
+        let options = OutputOptions {
+            line_numbers: true,
+            color: ColorChoice::Always,
+            ..OutputOptions::default()
+        };
+        assert_eq!(
+            render(None, "to", options),
+            include_str!("../tests/expected/color.txt")
+        );
+    }
+
+    #[test]
+    fn color_with_context_and_path() {
+        let options = OutputOptions {
+            after_context: 1,
+            line_numbers: true,
+            color: ColorChoice::Always,
+            ..OutputOptions::default()
+        };
+        assert_eq!(
+            render(Some("poem.txt"), "body", options),
+            include_str!("../tests/expected/color_context.txt")
+        );
+    }
+
+    #[test]
+    fn color_count() {
+        let options = OutputOptions {
+            count: true,
+            color: ColorChoice::Always,
+            ..OutputOptions::default()
+        };
+        assert_eq!(
+            render(Some("poem.txt"), "body", options),
+            include_str!("../tests/expected/color_count.txt")
+        );
+    }
+
+    #[test]
+    fn color_never_and_auto_write_plain_text() {
+        for color in [ColorChoice::Never, ColorChoice::Auto] {
+            let options = OutputOptions {
+                color,
+                ..OutputOptions::default()
+            };
+            assert_eq!(
+                render(None, "to", options),
+                include_str!("../tests/expected/plain.txt")
+            );
+        }
+    }
+
+    #[test]
+    fn inverted_lines_are_not_highlighted() {
+        let options = OutputOptions {
+            invert: true,
+            color: ColorChoice::Always,
+            ..OutputOptions::default()
+        };
+        assert_eq!(
+            render(None, "you", options),
+            include_str!("../tests/expected/invert.txt")
+        );
+    }
+
+    #[test]
+    fn fuzzy_distances() {
+        let options = OutputOptions {
+            line_numbers: true,
+            after_context: 1,
+            ..OutputOptions::default()
+        };
+        let mut out = Vec::new();
+        let find = |line: &str| -> Vec<Found> {
+            // pretend "bog" is one edit from "frog" and "frog" none
+            ["frog", "bog"]
+                .iter()
+                .zip([0, 1])
+                .filter_map(|(word, distance)| {
+                    line.find(word).map(|start| Found {
+                        start,
+                        end: start + word.len(),
+                        distance: Some(distance),
+                    })
+                })
+                .collect()
+        };
+        write_results(&mut out, None, POEM.as_bytes(), find, &options).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+7:0:How public, like a frog
+8-To tell your name the livelong day
+9:1:To an admiring bog!
+"
+        );
+    }
+
+    #[test]
+    fn invalid_utf8_does_not_stop_the_search() {
+        let input: &[u8] = b"caf\xe9 to go\n\xff\xfe\nto be\n";
+        let mut out = Vec::new();
+        let options = OutputOptions {
+            line_numbers: true,
+            ..OutputOptions::default()
+        };
+        write_results(&mut out, None, input, find("to"), &options).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "1:caf\u{fffd} to go\n3:to be\n"
+        );
+    }
+
+    #[test]
+    fn streams_long_input() {
+        // far more text than we'd want to hold at once, produced as it's read
+        struct Generated {
+            lines_left: usize,
+            pending: Vec<u8>,
+        }
+
+        impl io::Read for Generated {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.pending.is_empty() {
+                    if self.lines_left == 0 {
+                        return Ok(0);
+                    }
+                    let line = if self.lines_left.is_multiple_of(1000) {
+                        "How dreary to be somebody!\n"
+                    } else {
+                        "How public, like a frog\n"
+                    };
+                    self.pending.extend_from_slice(line.as_bytes());
+                    self.lines_left -= 1;
+                }
+                let n = buf.len().min(self.pending.len());
+                buf[..n].copy_from_slice(&self.pending[..n]);
+                self.pending.drain(..n);
+                Ok(n)
+            }
+        }
+
+        let input = io::BufReader::new(Generated {
+            lines_left: 200_000,
+            pending: Vec::new(),
+        });
+        let options = OutputOptions {
+            count: true,
+            ..OutputOptions::default()
+        };
+        let mut out = Vec::new();
+        let count = write_results(&mut out, None, input, find("dreary"), &options).unwrap();
+        assert_eq!(count, 200);
+        assert_eq!(out, b"200\n");
+    }
+
+    #[test]
+    fn returns_selected_count() {
+        let mut out = Vec::new();
+        let count = write_results(
+            &mut out,
+            None,
+            POEM.as_bytes(),
+            find("How"),
+            &OutputOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(count, 2);
+    }
+}
@@ -0,0 +1,158 @@
+//Searching many files at once with threads and channels
+/*
+Reading and scanning files is independent work, so with a directory full of
+files we hand them out to a few worker threads. The workers pull file indexes
+from a shared job channel (a Mutex around the Receiver, like the thread pool
+in chapter 20) and send each result back on a second channel tagged with its
+index.
+
+Results come back in whatever order the workers finish, but the output has to
+be stable so diffs and snapshots don't churn. The main thread holds on to
+results that arrive early and only emits a result once everything before it
+has been emitted, so the output is always in the same order as the input.
+*/
+
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+
+/// The number of threads to use when none was asked for.
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Runs `work` on every item using up to `threads` threads and passes the
+/// results to `emit` in the same order as `items`. Stops early and returns
+/// the error if `emit` fails.
+pub fn map_ordered<T, R, F, E>(items: &[T], threads: usize, work: F, mut emit: E) -> io::Result<()>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+    E: FnMut(R) -> io::Result<()>,
+{
+    if threads <= 1 || items.len() <= 1 {
+        for item in items {
+            emit(work(item))?;
+        }
+        return Ok(());
+    }
+
+    let (job_sender, job_receiver) = mpsc::channel();
+    for i in 0..items.len() {
+        job_sender.send(i).unwrap();
+    }
+    drop(job_sender);
+    let job_receiver = Mutex::new(job_receiver);
+
+    let (result_sender, result_receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..threads.min(items.len()) {
+            let job_receiver = &job_receiver;
+            let result_sender = result_sender.clone();
+            let work = &work;
+
+            scope.spawn(move || loop {
+                // the lock is dropped at the end of this statement, so other
+                // workers can take jobs while this one is busy
+                let job = job_receiver.lock().unwrap().recv();
+                let i = match job {
+                    Ok(i) => i,
+                    Err(_) => break,
+                };
+                // the receiver is gone if emit failed, so there's no point going on
+                if result_sender.send((i, work(&items[i]))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(result_sender);
+
+        let mut waiting = BTreeMap::new();
+        let mut next = 0;
+        for (i, result) in result_receiver {
+            waiting.insert(i, result);
+            while let Some(result) = waiting.remove(&next) {
+                emit(result)?;
+                next += 1;
+            }
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn results_keep_input_order() {
+        let items: Vec<u64> = (0..40).collect();
+        let mut seen = Vec::new();
+
+        // later items finish first, so they all have to wait
+        map_ordered(
+            &items,
+            8,
+            |n| {
+                thread::sleep(Duration::from_millis(40 - n));
+                n * 2
+            },
+            |result| {
+                seen.push(result);
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert_eq!(seen, items.iter().map(|n| n * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn one_thread_runs_inline() {
+        let items = ["to", "be", "somebody"];
+        let mut seen = Vec::new();
+        map_ordered(
+            &items,
+            1,
+            |s| s.len(),
+            |len| {
+                seen.push(len);
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(seen, vec![2, 2, 8]);
+    }
+
+    #[test]
+    fn emit_error_stops_the_search() {
+        let items: Vec<u32> = (0..100).collect();
+        let mut emitted = 0;
+        let result = map_ordered(
+            &items,
+            4,
+            |n| *n,
+            |n| {
+                if n == 10 {
+                    return Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"));
+                }
+                emitted += 1;
+                Ok(())
+            },
+        );
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(emitted, 10);
+    }
+
+    #[test]
+    fn empty_input() {
+        let items: Vec<u32> = Vec::new();
+        map_ordered(&items, 4, |n| *n, |_| panic!("nothing to emit")).unwrap();
+    }
+}
@@ -0,0 +1,1146 @@
+//A small regular expression engine for --regex
+/*
+The workspace has no dependencies, so minigrep carries its own regex engine.
+A pattern is parsed into a syntax tree, compiled into a little program of
+instructions, and run by a backtracking matcher. The matcher remembers every
+(instruction, position) pair it has already tried, so it never does the same
+work twice and can't blow up exponentially on patterns like '(a*)*b'.
+
+Supported syntax:
+    abc         literal characters
+    .           any character
+    [abc] [a-z] [^0-9]   character classes
+    \d \w \s    digits, word characters, whitespace (\D \W \S negate them)
+    \b \B       word boundary, not a word boundary
+    ^ $         start and end of the line
+    a|b         alternation
+    (...)       capturing group, (?:...) for a group that doesn't capture
+    * + ? {n} {n,} {n,m}   repetition, add '?' to make it lazy
+    \. \* ...   escape a special character
+*/
+
+use std::error;
+use std::fmt;
+
+/// A pattern that failed to parse, with the character position of the problem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid regex at position {}: {}",
+            self.position, self.message
+        )
+    }
+}
+
+impl error::Error for Error {}
+
+/// A compiled regular expression.
+#[derive(Debug, Clone)]
+pub struct Regex {
+    program: Vec<Inst>,
+    groups: usize,
+    ignore_case: bool,
+}
+
+/// The byte ranges of a match (group 0) and of each capturing group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Captures {
+    slots: Vec<Option<usize>>,
+}
+
+impl Captures {
+    /// The byte range of group `i`, or `None` if it didn't take part in the match.
+    pub fn get(&self, i: usize) -> Option<(usize, usize)> {
+        match (self.slots.get(i * 2), self.slots.get(i * 2 + 1)) {
+            (Some(Some(start)), Some(Some(end))) => Some((*start, *end)),
+            _ => None,
+        }
+    }
+
+    /// The number of groups, counting the whole match as group 0.
+    pub fn len(&self) -> usize {
+        self.slots.len() / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Regex, Error> {
+        Regex::build(pattern, false)
+    }
+
+    /// Compiles `pattern`, optionally matching letters without regard to case.
+    pub fn build(pattern: &str, ignore_case: bool) -> Result<Regex, Error> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+            groups: 0,
+        };
+        let node = parser.parse()?;
+        if node.size().saturating_add(3) > MAX_PROGRAM {
+            return Err(Error {
+                position: 0,
+                message: format!("pattern is too large: it needs more than {MAX_PROGRAM} steps"),
+            });
+        }
+
+        let mut compiler = Compiler {
+            program: Vec::new(),
+        };
+        compiler.push(Inst::Save(0));
+        compiler.compile(&node);
+        compiler.push(Inst::Save(1));
+        compiler.push(Inst::Match);
+
+        Ok(Regex {
+            program: compiler.program,
+            groups: parser.groups + 1,
+            ignore_case,
+        })
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.captures_at(text, 0).is_some()
+    }
+
+    /// The byte range of the leftmost match in `text`.
+    pub fn find(&self, text: &str) -> Option<(usize, usize)> {
+        self.find_at(text, 0)
+    }
+
+    /// The byte range of the leftmost match that starts at or after `start`.
+    pub fn find_at(&self, text: &str, start: usize) -> Option<(usize, usize)> {
+        self.captures_at(text, start).and_then(|caps| caps.get(0))
+    }
+
+    /// Every non-overlapping match in `text`, from left to right.
+    pub fn find_iter<'r, 't>(&'r self, text: &'t str) -> FindIter<'r, 't> {
+        FindIter {
+            captures: self.captures_iter(text),
+        }
+    }
+
+    /// The captures of every non-overlapping match in `text`, from left to right.
+    pub fn captures_iter<'r, 't>(&'r self, text: &'t str) -> CapturesIter<'r, 't> {
+        CapturesIter {
+            regex: self,
+            text,
+            pos: 0,
+            visited: Visited::new(self.program.len(), text.len()),
+        }
+    }
+
+    /// The number of capturing groups, counting the whole match as group 0.
+    pub fn group_count(&self) -> usize {
+        self.groups
+    }
+
+    pub fn captures(&self, text: &str) -> Option<Captures> {
+        self.captures_at(text, 0)
+    }
+
+    /// Runs the matcher from every character boundary at or after `start`
+    /// until one of them matches.
+    pub fn captures_at(&self, text: &str, start: usize) -> Option<Captures> {
+        self.search(
+            text,
+            start,
+            &mut Visited::new(self.program.len(), text.len()),
+        )
+    }
+
+    // captures_at with a visited table the caller keeps, so iterating over
+    // the matches in a line doesn't allocate a new table for each one
+    fn search(&self, text: &str, start: usize, visited: &mut Visited) -> Option<Captures> {
+        if start > text.len() {
+            return None;
+        }
+
+        visited.clear();
+        let mut matcher = Matcher {
+            regex: self,
+            text,
+            visited,
+            slots: vec![None; self.groups * 2],
+        };
+
+        let mut at = start;
+        loop {
+            if matcher.run(at) {
+                return Some(Captures {
+                    slots: matcher.slots,
+                });
+            }
+            match text[at..].chars().next() {
+                Some(c) => at += c.len_utf8(),
+                None => return None,
+            }
+        }
+    }
+}
+
+/// The iterator returned by `Regex::find_iter`.
+pub struct FindIter<'r, 't> {
+    captures: CapturesIter<'r, 't>,
+}
+
+impl Iterator for FindIter<'_, '_> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> {
+        self.captures.next().and_then(|caps| caps.get(0))
+    }
+}
+
+/// The iterator returned by `Regex::captures_iter`.
+pub struct CapturesIter<'r, 't> {
+    regex: &'r Regex,
+    text: &'t str,
+    pos: usize,
+    visited: Visited,
+}
+
+impl Iterator for CapturesIter<'_, '_> {
+    type Item = Captures;
+
+    fn next(&mut self) -> Option<Captures> {
+        let caps = self.regex.search(self.text, self.pos, &mut self.visited)?;
+        let (start, end) = caps.get(0)?;
+        self.pos = if end > start {
+            end
+        } else {
+            // step past an empty match so we don't find it again forever
+            match self.text[end..].chars().next() {
+                Some(c) => end + c.len_utf8(),
+                None => self.text.len() + 1,
+            }
+        };
+        Some(caps)
+    }
+}
+
+//Parsing
+
+#[derive(Debug, Clone)]
+enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class(Class),
+    Assert(Assertion),
+    Group(Box<Node>, Option<usize>),
+    Concat(Vec<Node>),
+    Alternate(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+}
+
+impl Node {
+    // the number of instructions Compiler::compile turns the node into
+    fn size(&self) -> usize {
+        match self {
+            Node::Empty => 0,
+            Node::Char(_) | Node::Any | Node::Class(_) | Node::Assert(_) => 1,
+            Node::Group(inner, index) => {
+                inner
+                    .size()
+                    .saturating_add(if index.is_some() { 2 } else { 0 })
+            }
+            Node::Concat(nodes) => nodes
+                .iter()
+                .fold(0, |sum, node| sum.saturating_add(node.size())),
+            Node::Alternate(branches) => {
+                branches.iter().fold(2 * (branches.len() - 1), |sum, node| {
+                    sum.saturating_add(node.size())
+                })
+            }
+            Node::Repeat { node, min, max, .. } => {
+                let size = node.size();
+                let optional = match max {
+                    None => size.saturating_add(2),
+                    Some(max) => (*max as usize - *min as usize).saturating_mul(size + 1),
+                };
+                (*min as usize)
+                    .saturating_mul(size)
+                    .saturating_add(optional)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Assertion {
+    LineStart,
+    LineEnd,
+    WordBoundary,
+    NotWordBoundary,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match *self {
+            ClassItem::Range(lo, hi) => lo <= c && c <= hi,
+            ClassItem::Digit(negated) => c.is_ascii_digit() != negated,
+            ClassItem::Word(negated) => is_word_char(c) != negated,
+            ClassItem::Space(negated) => c.is_whitespace() != negated,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Class {
+    items: Vec<ClassItem>,
+    negated: bool,
+}
+
+impl Class {
+    fn single(item: ClassItem) -> Class {
+        Class {
+            items: vec![item],
+            negated: false,
+        }
+    }
+
+    fn matches(&self, c: char) -> bool {
+        self.items.iter().any(|item| item.matches(c)) != self.negated
+    }
+}
+
+// bounded repetition gets expanded into copies, so keep the counts sane
+const MAX_REPEAT: u32 = 1000;
+
+// and since copies of copies multiply, keep the whole program sane too
+const MAX_PROGRAM: usize = 10_000;
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    groups: usize,
+}
+
+impl Parser {
+    fn parse(&mut self) -> Result<Node, Error> {
+        let node = self.parse_alternation()?;
+        match self.peek() {
+            None => Ok(node),
+            Some(')') => Err(self.error("unmatched ')'")),
+            Some(c) => Err(self.error(&format!("unexpected '{c}'"))),
+        }
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error {
+            position: self.pos,
+            message: message.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_alternation(&mut self) -> Result<Node, Error> {
+        let mut branches = vec![self.parse_concat()?];
+        while self.eat('|') {
+            branches.push(self.parse_concat()?);
+        }
+
+        if branches.len() == 1 {
+            Ok(branches.pop().unwrap())
+        } else {
+            Ok(Node::Alternate(branches))
+        }
+    }
+
+    fn parse_concat(&mut self) -> Result<Node, Error> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_repeat(atom)?);
+        }
+
+        match nodes.len() {
+            0 => Ok(Node::Empty),
+            1 => Ok(nodes.pop().unwrap()),
+            _ => Ok(Node::Concat(nodes)),
+        }
+    }
+
+    fn parse_repeat(&mut self, mut atom: Node) -> Result<Node, Error> {
+        loop {
+            let start = self.pos;
+            let (min, max) = match self.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                Some('{') => match self.parse_braces()? {
+                    Some(bounds) => bounds,
+                    None => return Ok(atom),
+                },
+                _ => return Ok(atom),
+            };
+            if self.pos == start {
+                self.pos += 1;
+            }
+
+            if matches!(atom, Node::Empty | Node::Assert(_)) {
+                return Err(Error {
+                    position: start,
+                    message: String::from("nothing to repeat"),
+                });
+            }
+
+            let greedy = !self.eat('?');
+            atom = Node::Repeat {
+                node: Box::new(atom),
+                min,
+                max,
+                greedy,
+            };
+        }
+    }
+
+    // Parses {n}, {n,} or {n,m}. A '{' that doesn't start a valid counter
+    // is left alone and treated as a literal brace.
+    fn parse_braces(&mut self) -> Result<Option<(u32, Option<u32>)>, Error> {
+        let start = self.pos;
+        self.pos += 1;
+
+        let min = self.parse_number();
+        let max = if self.eat(',') {
+            self.parse_number()
+        } else {
+            min
+        };
+
+        let min = match min {
+            Some(min) if self.eat('}') => min,
+            _ => {
+                self.pos = start;
+                return Ok(None);
+            }
+        };
+
+        if let Some(max) = max {
+            if max < min {
+                return Err(Error {
+                    position: start,
+                    message: String::from("repetition range is backwards"),
+                });
+            }
+        }
+        if min > MAX_REPEAT || max.unwrap_or(0) > MAX_REPEAT {
+            return Err(Error {
+                position: start,
+                message: format!("repetition count is larger than {MAX_REPEAT}"),
+            });
+        }
+        Ok(Some((min, max)))
+    }
+
+    fn parse_number(&mut self) -> Option<u32> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return None;
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        digits.parse().ok().or(Some(u32::MAX))
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, Error> {
+        let c = self.peek().unwrap();
+        let start = self.pos;
+        self.pos += 1;
+
+        match c {
+            '(' => {
+                let index = if self.chars[self.pos..].starts_with(&['?', ':']) {
+                    self.pos += 2;
+                    None
+                } else {
+                    self.groups += 1;
+                    Some(self.groups)
+                };
+                let inner = self.parse_alternation()?;
+                if !self.eat(')') {
+                    return Err(Error {
+                        position: start,
+                        message: String::from("unclosed group"),
+                    });
+                }
+                Ok(Node::Group(Box::new(inner), index))
+            }
+            '[' => self.parse_class(start),
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Assert(Assertion::LineStart)),
+            '$' => Ok(Node::Assert(Assertion::LineEnd)),
+            '\\' => self.parse_escape(),
+            '*' | '+' | '?' => Err(Error {
+                position: start,
+                message: String::from("nothing to repeat"),
+            }),
+            c => Ok(Node::Char(c)),
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<Node, Error> {
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return Err(self.error("pattern ends with a backslash")),
+        };
+        self.pos += 1;
+
+        Ok(match c {
+            'b' => Node::Assert(Assertion::WordBoundary),
+            'B' => Node::Assert(Assertion::NotWordBoundary),
+            _ => match escape_class(c) {
+                Some(item) => Node::Class(Class::single(item)),
+                None => Node::Char(escape_char(c)),
+            },
+        })
+    }
+
+    fn parse_class(&mut self, start: usize) -> Result<Node, Error> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+
+        // a ']' right after the opening bracket is a literal
+        if self.eat(']') {
+            items.push(ClassItem::Range(']', ']'));
+        }
+
+        loop {
+            let c = match self.peek() {
+                Some(c) => c,
+                None => {
+                    return Err(Error {
+                        position: start,
+                        message: String::from("unclosed character class"),
+                    })
+                }
+            };
+            self.pos += 1;
+
+            if c == ']' {
+                break;
+            }
+
+            let lo = if c == '\\' {
+                let escaped = match self.peek() {
+                    Some(e) => e,
+                    None => return Err(self.error("pattern ends with a backslash")),
+                };
+                self.pos += 1;
+                if let Some(item) = escape_class(escaped) {
+                    items.push(item);
+                    continue;
+                }
+                escape_char(escaped)
+            } else {
+                c
+            };
+
+            // a '-' at the end of the class is a literal
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                self.pos += 1;
+                let hi = match self.peek() {
+                    Some('\\') => {
+                        self.pos += 1;
+                        match self.peek() {
+                            Some(e) => escape_char(e),
+                            None => return Err(self.error("pattern ends with a backslash")),
+                        }
+                    }
+                    Some(hi) => hi,
+                    None => continue,
+                };
+                self.pos += 1;
+                if hi < lo {
+                    return Err(self.error("character range is backwards"));
+                }
+                items.push(ClassItem::Range(lo, hi));
+            } else {
+                items.push(ClassItem::Range(lo, lo));
+            }
+        }
+
+        Ok(Node::Class(Class { items, negated }))
+    }
+}
+
+fn escape_class(c: char) -> Option<ClassItem> {
+    match c {
+        'd' => Some(ClassItem::Digit(false)),
+        'D' => Some(ClassItem::Digit(true)),
+        'w' => Some(ClassItem::Word(false)),
+        'W' => Some(ClassItem::Word(true)),
+        's' => Some(ClassItem::Space(false)),
+        'S' => Some(ClassItem::Space(true)),
+        _ => None,
+    }
+}
+
+fn escape_char(c: char) -> char {
+    match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        c => c,
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+//Compiling
+
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Assert(Assertion),
+    // try the first branch, and if that fails, the second
+    Split(usize, usize),
+    Jump(usize),
+    Save(usize),
+    Match,
+}
+
+struct Compiler {
+    program: Vec<Inst>,
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> usize {
+        self.program.push(inst);
+        self.program.len() - 1
+    }
+
+    fn next(&self) -> usize {
+        self.program.len()
+    }
+
+    fn compile(&mut self, node: &Node) {
+        match node {
+            Node::Empty => {}
+            Node::Char(c) => {
+                self.push(Inst::Char(*c));
+            }
+            Node::Any => {
+                self.push(Inst::Any);
+            }
+            Node::Class(class) => {
+                self.push(Inst::Class(class.clone()));
+            }
+            Node::Assert(assertion) => {
+                self.push(Inst::Assert(*assertion));
+            }
+            Node::Group(inner, index) => match index {
+                Some(i) => {
+                    self.push(Inst::Save(i * 2));
+                    self.compile(inner);
+                    self.push(Inst::Save(i * 2 + 1));
+                }
+                None => self.compile(inner),
+            },
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    self.compile(node);
+                }
+            }
+            Node::Alternate(branches) => {
+                let mut jumps = Vec::new();
+                for (i, branch) in branches.iter().enumerate() {
+                    if i + 1 < branches.len() {
+                        let split = self.push(Inst::Split(0, 0));
+                        self.compile(branch);
+                        jumps.push(self.push(Inst::Jump(0)));
+                        let next = self.next();
+                        self.program[split] = Inst::Split(split + 1, next);
+                    } else {
+                        self.compile(branch);
+                    }
+                }
+                let end = self.next();
+                for jump in jumps {
+                    self.program[jump] = Inst::Jump(end);
+                }
+            }
+            Node::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+            } => {
+                for _ in 0..*min {
+                    self.compile(node);
+                }
+                match max {
+                    None => {
+                        let split = self.push(Inst::Split(0, 0));
+                        self.compile(node);
+                        self.push(Inst::Jump(split));
+                        let end = self.next();
+                        self.program[split] = self.split(split + 1, end, *greedy);
+                    }
+                    Some(max) => {
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            splits.push(self.push(Inst::Split(0, 0)));
+                            self.compile(node);
+                        }
+                        let end = self.next();
+                        for split in splits {
+                            self.program[split] = self.split(split + 1, end, *greedy);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn split(&self, take: usize, skip: usize, greedy: bool) -> Inst {
+        if greedy {
+            Inst::Split(take, skip)
+        } else {
+            Inst::Split(skip, take)
+        }
+    }
+}
+
+//Matching
+
+enum Job {
+    Try(usize, usize),
+    Restore(usize, Option<usize>),
+}
+
+// One bit per (instruction, position); a pair that failed once always fails.
+// Clearing only resets the words that were written to, so a search that
+// gives up early doesn't pay for the whole table.
+struct Visited {
+    bits: Vec<u64>,
+    dirty: Vec<usize>,
+    width: usize,
+}
+
+impl Visited {
+    fn new(instructions: usize, text_len: usize) -> Visited {
+        let width = text_len + 1;
+        Visited {
+            bits: vec![0; (instructions * width).div_ceil(64)],
+            dirty: Vec::new(),
+            width,
+        }
+    }
+
+    // marks the pair, returning false if it was already marked
+    fn insert(&mut self, pc: usize, pos: usize) -> bool {
+        let key = pc * self.width + pos;
+        let (word, bit) = (key / 64, 1 << (key % 64));
+        if self.bits[word] & bit != 0 {
+            return false;
+        }
+        if self.bits[word] == 0 {
+            self.dirty.push(word);
+        }
+        self.bits[word] |= bit;
+        true
+    }
+
+    fn clear(&mut self) {
+        for word in self.dirty.drain(..) {
+            self.bits[word] = 0;
+        }
+    }
+}
+
+struct Matcher<'r, 't, 'v> {
+    regex: &'r Regex,
+    text: &'t str,
+    visited: &'v mut Visited,
+    slots: Vec<Option<usize>>,
+}
+
+impl Matcher<'_, '_, '_> {
+    fn run(&mut self, start: usize) -> bool {
+        let mut stack = vec![Job::Try(0, start)];
+
+        while let Some(job) = stack.pop() {
+            let (mut pc, mut pos) = match job {
+                Job::Try(pc, pos) => (pc, pos),
+                Job::Restore(slot, value) => {
+                    self.slots[slot] = value;
+                    continue;
+                }
+            };
+
+            loop {
+                if !self.visited.insert(pc, pos) {
+                    break;
+                }
+
+                match &self.regex.program[pc] {
+                    Inst::Match => return true,
+                    Inst::Char(expected) => match self.char_at(pos) {
+                        Some(c) if self.same_char(c, *expected) => {
+                            pc += 1;
+                            pos += c.len_utf8();
+                        }
+                        _ => break,
+                    },
+                    Inst::Any => match self.char_at(pos) {
+                        Some(c) => {
+                            pc += 1;
+                            pos += c.len_utf8();
+                        }
+                        None => break,
+                    },
+                    Inst::Class(class) => match self.char_at(pos) {
+                        Some(c) if self.class_matches(class, c) => {
+                            pc += 1;
+                            pos += c.len_utf8();
+                        }
+                        _ => break,
+                    },
+                    Inst::Assert(assertion) => {
+                        if self.assert(*assertion, pos) {
+                            pc += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                    Inst::Split(first, second) => {
+                        stack.push(Job::Try(*second, pos));
+                        pc = *first;
+                    }
+                    Inst::Jump(target) => pc = *target,
+                    Inst::Save(slot) => {
+                        stack.push(Job::Restore(*slot, self.slots[*slot]));
+                        self.slots[*slot] = Some(pos);
+                        pc += 1;
+                    }
+                }
+            }
+        }
+
+        false
+    }
+
+    fn char_at(&self, pos: usize) -> Option<char> {
+        self.text[pos..].chars().next()
+    }
+
+    fn char_before(&self, pos: usize) -> Option<char> {
+        self.text[..pos].chars().next_back()
+    }
+
+    fn same_char(&self, c: char, expected: char) -> bool {
+        c == expected || (self.regex.ignore_case && c.to_lowercase().eq(expected.to_lowercase()))
+    }
+
+    fn class_matches(&self, class: &Class, c: char) -> bool {
+        if !self.regex.ignore_case {
+            return class.matches(c);
+        }
+        // a negated class must reject every case variant, not just one of them
+        let mut variants = std::iter::once(c)
+            .chain(c.to_lowercase())
+            .chain(c.to_uppercase());
+        let hit = variants.any(|v| class.items.iter().any(|item| item.matches(v)));
+        hit != class.negated
+    }
+
+    fn assert(&self, assertion: Assertion, pos: usize) -> bool {
+        match assertion {
+            Assertion::LineStart => pos == 0,
+            Assertion::LineEnd => pos == self.text.len(),
+            Assertion::WordBoundary | Assertion::NotWordBoundary => {
+                let before = self.char_before(pos).is_some_and(is_word_char);
+                let after = self.char_at(pos).is_some_and(is_word_char);
+                (before != after) == (assertion == Assertion::WordBoundary)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POEM: &str = include_str!("../poem.txt");
+
+    fn matching_lines(pattern: &str) -> Vec<&'static str> {
+        let re = Regex::new(pattern).unwrap();
+        POEM.lines().filter(|line| re.is_match(line)).collect()
+    }
+
+    fn find(pattern: &str, text: &str) -> Option<(usize, usize)> {
+        Regex::new(pattern).unwrap().find(text)
+    }
+
+    #[test]
+    fn literal() {
+        assert_eq!(
+            vec!["Are you nobody, too?", "How dreary to be somebody!"],
+            matching_lines("to")
+        );
+    }
+
+    #[test]
+    fn anchors() {
+        assert_eq!(
+            vec!["To tell your name the livelong day", "To an admiring bog!"],
+            matching_lines("^To")
+        );
+        assert_eq!(vec!["Are you nobody, too?"], matching_lines("too\\?$"));
+        assert_eq!(vec![""], matching_lines("^$"));
+    }
+
+    #[test]
+    fn alternation() {
+        assert_eq!(
+            vec!["How public, like a frog", "To an admiring bog!"],
+            matching_lines("frog|bog")
+        );
+        assert_eq!(
+            vec!["How public, like a frog", "To an admiring bog!"],
+            matching_lines("(fr|b)og")
+        );
+    }
+
+    #[test]
+    fn dot_and_star() {
+        assert_eq!(vec!["I'm nobody! Who are you?"], matching_lines("^I.*you"));
+        assert_eq!(find("a.*y", "a pair of us - don't tell!"), None);
+        assert_eq!(find("b.*y", "How dreary to be somebody!"), Some((14, 25)));
+    }
+
+    #[test]
+    fn plus_and_question() {
+        assert_eq!(
+            vec!["Then there's a pair of us - don't tell!"],
+            matching_lines("tel+!")
+        );
+        assert_eq!(
+            vec!["They'd banish us, you know."],
+            matching_lines("They'?d")
+        );
+        assert_eq!(find("colou?r", "color"), Some((0, 5)));
+        assert_eq!(find("colou?r", "colour"), Some((0, 6)));
+        assert_eq!(find("lo+ng", "the livelong day"), Some((8, 12)));
+    }
+
+    #[test]
+    fn classes() {
+        assert_eq!(
+            vec!["Then there's a pair of us - don't tell!"],
+            matching_lines("[-]")
+        );
+        assert_eq!(
+            vec![
+                "Then there's a pair of us - don't tell!",
+                "How dreary to be somebody!",
+                "To an admiring bog!",
+            ],
+            matching_lines("[!]$")
+        );
+        assert_eq!(find("[A-Z][a-z]+", "an admiring Bog"), Some((12, 15)));
+        assert_eq!(find("[^a-z ]", "how public, like"), Some((10, 11)));
+        assert_eq!(find("[]x]", "a]b"), Some((1, 2)));
+        assert_eq!(find("[a-]", "x-y"), Some((1, 2)));
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(find("\\d+", "room 101"), Some((5, 8)));
+        assert_eq!(find("\\w+", "  you, too"), Some((2, 5)));
+        assert_eq!(find("\\s", "a b"), Some((1, 2)));
+        assert_eq!(find("[\\d.]+", "v1.25"), Some((1, 5)));
+        assert_eq!(find("\\.", "a.b"), Some((1, 2)));
+        assert_eq!(find("\\btoo\\b", "tool too"), Some((5, 8)));
+        assert_eq!(find("\\Bod", "nobody"), Some((3, 5)));
+    }
+
+    #[test]
+    fn word_boundaries_on_poem() {
+        // "to" as a whole word only appears once; "too" and "To" don't count
+        assert_eq!(
+            vec!["How dreary to be somebody!"],
+            matching_lines("\\bto\\b")
+        );
+    }
+
+    #[test]
+    fn counted_repetition() {
+        assert_eq!(find("o{2}", "nobody, too"), Some((9, 11)));
+        assert_eq!(find("a{2,}", "baaad"), Some((1, 4)));
+        assert_eq!(find("a{1,2}", "baaad"), Some((1, 3)));
+        assert_eq!(find("x{a}", "x{a}"), Some((0, 4)));
+    }
+
+    #[test]
+    fn lazy_repetition() {
+        assert_eq!(find("b.*?y", "nobody, somebody"), Some((2, 6)));
+        assert_eq!(find("b.*y", "nobody, somebody"), Some((2, 16)));
+    }
+
+    #[test]
+    fn captures() {
+        let re = Regex::new("(\\w+)body").unwrap();
+        let caps = re.captures("How dreary to be somebody!").unwrap();
+        assert_eq!(caps.len(), 2);
+        assert_eq!(caps.get(0), Some((17, 25)));
+        assert_eq!(caps.get(1), Some((17, 21)));
+
+        let re = Regex::new("(a)|(b)").unwrap();
+        let caps = re.captures("b").unwrap();
+        assert_eq!(caps.get(1), None);
+        assert_eq!(caps.get(2), Some((0, 1)));
+    }
+
+    #[test]
+    fn ignore_case() {
+        let re = Regex::build("^to", true).unwrap();
+        let lines: Vec<&str> = POEM.lines().filter(|line| re.is_match(line)).collect();
+        assert_eq!(
+            vec!["To tell your name the livelong day", "To an admiring bog!"],
+            lines
+        );
+
+        let re = Regex::build("[^a-z]", true).unwrap();
+        assert_eq!(re.find("Frog!"), Some((4, 5)));
+    }
+
+    #[test]
+    fn multibyte_text() {
+        assert_eq!(find("é+", "café olé"), Some((3, 5)));
+        assert_eq!(find(".b", "ébc"), Some((0, 3)));
+        assert_eq!(find("[à-ÿ]", "naïve"), Some((2, 4)));
+    }
+
+    #[test]
+    fn find_iter_skips_past_matches() {
+        let re = Regex::new("o\\w").unwrap();
+        let found: Vec<_> = re.find_iter("nobody, too").collect();
+        assert_eq!(found, vec![(1, 3), (3, 5), (9, 11)]);
+
+        let re = Regex::new("x*").unwrap();
+        assert_eq!(re.find_iter("ab").count(), 3);
+    }
+
+    #[test]
+    fn captures_iter_finds_every_match() {
+        let re = Regex::new("(\\w)o").unwrap();
+        let firsts: Vec<_> = re
+            .captures_iter("nobody, too")
+            .map(|caps| caps.get(1).unwrap())
+            .collect();
+        assert_eq!(firsts, vec![(0, 1), (2, 3), (8, 9)]);
+        assert_eq!(re.group_count(), 2);
+    }
+
+    #[test]
+    fn pathological_pattern_finishes() {
+        let text = "a".repeat(40);
+        assert!(!Regex::new("(a*)*b").unwrap().is_match(&text));
+        assert!(!Regex::new("(a|aa)+$b").unwrap().is_match(&text));
+    }
+
+    #[test]
+    fn matches_on_a_long_line_share_one_table() {
+        let text = "ab".repeat(50_000);
+        let re = Regex::new("a").unwrap();
+        assert_eq!(re.find_iter(&text).count(), 50_000);
+        let caps: Vec<_> = Regex::new("(b)").unwrap().captures_iter(&text).collect();
+        assert_eq!(caps.len(), 50_000);
+        assert_eq!(caps[49_999].get(1), Some((99_999, 100_000)));
+    }
+
+    #[test]
+    fn program_size_matches_the_compiler() {
+        for pattern in [
+            "",
+            "a",
+            "(a|bc|)d",
+            "(?:x[0-9]){2,4}?",
+            "(a*)+b",
+            "^\\b(a{3}){2,}$",
+        ] {
+            let mut parser = Parser {
+                chars: pattern.chars().collect(),
+                pos: 0,
+                groups: 0,
+            };
+            let node = parser.parse().unwrap();
+            let re = Regex::new(pattern).unwrap();
+            assert_eq!(node.size() + 3, re.program.len(), "pattern {pattern:?}");
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        let cases = [
+            ("(to", 0, "unclosed group"),
+            ("to)", 2, "unmatched ')'"),
+            ("[a-", 0, "unclosed character class"),
+            ("*to", 0, "nothing to repeat"),
+            ("a\\", 2, "pattern ends with a backslash"),
+            ("[z-a]", 4, "character range is backwards"),
+            ("a{3,1}", 1, "repetition range is backwards"),
+            ("a{1001}", 1, "repetition count is larger than 1000"),
+            (
+                "((a{1000}){1000}){1000}",
+                0,
+                "pattern is too large: it needs more than 10000 steps",
+            ),
+        ];
+        for (pattern, position, message) in cases {
+            let err = Regex::new(pattern).unwrap_err();
+            assert_eq!(
+                (err.position, err.message.as_str()),
+                (position, message),
+                "pattern {pattern:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,526 @@
+//Search and replace for --replace
+/*
+With '--replace REPL' minigrep rewrites every match in place instead of
+printing it. In regex mode the replacement can refer to capture groups:
+
+    $0 or ${0}   the whole match
+    $1 ... $9    a capturing group, ${12} for groups past 9
+    $$           a literal dollar sign
+
+Without --regex only $0 makes sense, since a literal search has no groups.
+
+Files are never written directly. The new contents go to a temporary file in
+the same directory, which is flushed to disk and then renamed over the
+original. A rename within one directory is atomic, so if minigrep crashes
+part way through, the original file is still there untouched. A symlink is
+followed first, so the file it points to is rewritten and the link stays a
+link. In a directory, a file that can't be rewritten is reported and the
+others are rewritten anyway, and minigrep exits with an error at the end.
+
+'--dry-run' writes nothing and prints a unified diff of what would change,
+in the same format as 'diff -u', so it can be reviewed or piped to 'patch'.
+*/
+
+use std::error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+// lines of unchanged text shown around each change in a diff
+const DIFF_CONTEXT: usize = 3;
+
+/// A replacement template that didn't make sense.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid replacement: {}", self.message)
+    }
+}
+
+impl error::Error for Error {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Group(usize),
+}
+
+/// A parsed replacement string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Parses `text`, which may refer to groups 0 up to `groups - 1`.
+    pub fn parse(text: &str, groups: usize) -> Result<Template, Error> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c != '$' {
+                literal.push(c);
+                continue;
+            }
+
+            let group = match chars.peek() {
+                Some('$') => {
+                    chars.next();
+                    literal.push('$');
+                    continue;
+                }
+                Some('{') => {
+                    chars.next();
+                    let mut digits = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(d) if d.is_ascii_digit() => digits.push(d),
+                            _ => {
+                                return Err(Error {
+                                    message: String::from(
+                                        "'${' must be followed by a group number and '}'",
+                                    ),
+                                })
+                            }
+                        }
+                    }
+                    digits.parse::<usize>().map_err(|_| Error {
+                        message: String::from("'${}' needs a group number"),
+                    })?
+                }
+                Some(d) if d.is_ascii_digit() => {
+                    let d = d.to_digit(10).unwrap() as usize;
+                    chars.next();
+                    d
+                }
+                // a '$' that isn't a reference is just a dollar sign
+                _ => {
+                    literal.push('$');
+                    continue;
+                }
+            };
+
+            if group >= groups {
+                let message = if groups == 1 {
+                    format!("${group} refers to a capture group, which needs --regex")
+                } else {
+                    format!("${group} refers to a group the pattern doesn't have")
+                };
+                return Err(Error { message });
+            }
+
+            if !literal.is_empty() {
+                parts.push(Part::Literal(std::mem::take(&mut literal)));
+            }
+            parts.push(Part::Group(group));
+        }
+
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Template { parts })
+    }
+
+    /// Appends the replacement for one match to `out`. `group` gives the
+    /// text of each group; groups that didn't match expand to nothing.
+    pub fn expand<'a>(&self, group: impl Fn(usize) -> Option<&'a str>, out: &mut String) {
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => out.push_str(text),
+                Part::Group(i) => out.push_str(group(*i).unwrap_or("")),
+            }
+        }
+    }
+}
+
+/// One line that the replacement changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// The line number, counting from 1.
+    pub number: usize,
+    pub old: String,
+    pub new: String,
+}
+
+/// Runs `replace` over every line of the file at `path` and returns the
+/// lines it changed. `replace` returns `None` for lines it leaves alone.
+///
+/// Unless `dry_run` is set, the new contents are streamed to a temporary file
+/// that replaces the original once everything has been written, and only if
+/// anything changed. Line endings are kept as they were, and lines that
+/// aren't valid UTF-8 are copied through byte for byte. With `dry_run` the
+/// lines of the file are returned too, to give a diff its context.
+pub fn replace_in_file(
+    path: &Path,
+    replace: impl Fn(&str) -> Option<String>,
+    dry_run: bool,
+) -> io::Result<(Vec<String>, Vec<Change>)> {
+    // through any symlinks, so the file they point to is the one rewritten
+    let path = &fs::canonicalize(path)?;
+    let mut input = BufReader::new(File::open(path)?);
+    let mut output = if dry_run {
+        None
+    } else {
+        Some(TempFile::create(path)?)
+    };
+    let mut lines = Vec::new();
+    let mut changes = Vec::new();
+    let mut raw = Vec::new();
+    let mut number = 0;
+
+    loop {
+        raw.clear();
+        if input.read_until(b'\n', &mut raw)? == 0 {
+            break;
+        }
+        number += 1;
+
+        let ending_len = if raw.ends_with(b"\r\n") {
+            2
+        } else if raw.ends_with(b"\n") {
+            1
+        } else {
+            0
+        };
+        let (body, ending) = raw.split_at(raw.len() - ending_len);
+
+        let text = std::str::from_utf8(body).ok();
+        let new = text
+            .and_then(&replace)
+            .filter(|new| Some(new.as_str()) != text);
+
+        if let Some(output) = output.as_mut() {
+            let body = new.as_ref().map_or(body, |new| new.as_bytes());
+            output.writer.write_all(body)?;
+            output.writer.write_all(ending)?;
+        }
+        if dry_run {
+            lines.push(String::from_utf8_lossy(body).into_owned());
+        }
+        if let (Some(old), Some(new)) = (text, new) {
+            changes.push(Change {
+                number,
+                old: old.to_string(),
+                new,
+            });
+        }
+    }
+
+    if let Some(output) = output {
+        if changes.is_empty() {
+            output.discard();
+        } else {
+            output.commit(path)?;
+        }
+    }
+    Ok((lines, changes))
+}
+
+// A temporary file next to the one being rewritten. It is removed again if
+// it's dropped without being committed, so an error doesn't leave it behind.
+pub(crate) struct TempFile {
+    path: PathBuf,
+    pub(crate) writer: BufWriter<File>,
+    committed: bool,
+}
+
+impl TempFile {
+    pub(crate) fn create(original: &Path) -> io::Result<TempFile> {
+        let path = temp_path(original);
+        let file = File::create(&path)?;
+        Ok(TempFile {
+            path,
+            writer: BufWriter::new(file),
+            committed: false,
+        })
+    }
+
+    // Flushes everything to disk and renames the temporary file over
+    // `original`, keeping the original's permissions (like the executable bit)
+    // if there is one.
+    pub(crate) fn commit(mut self, original: &Path) -> io::Result<()> {
+        self.writer.flush()?;
+        self.writer.get_ref().sync_all()?;
+        match fs::metadata(original) {
+            Ok(metadata) => fs::set_permissions(&self.path, metadata.permissions())?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        fs::rename(&self.path, original)?;
+        self.committed = true;
+        Ok(())
+    }
+
+    fn discard(self) {}
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+// a hidden file in the same directory, so the rename doesn't cross filesystems
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{name}.minigrep-{}.tmp", process::id()))
+}
+
+/// Writes a unified diff for the `changes` made to a file whose lines are
+/// `lines`, labelled with `path`.
+pub fn write_diff<W: Write>(
+    out: &mut W,
+    path: &str,
+    lines: &[String],
+    changes: &[Change],
+) -> io::Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "--- a/{path}")?;
+    writeln!(out, "+++ b/{path}")?;
+
+    // group changes whose context would touch into one hunk
+    let mut hunks: Vec<&[Change]> = Vec::new();
+    let mut start = 0;
+    for i in 1..=changes.len() {
+        if i == changes.len() || changes[i].number - changes[i - 1].number > 2 * DIFF_CONTEXT + 1 {
+            hunks.push(&changes[start..i]);
+            start = i;
+        }
+    }
+
+    for hunk in hunks {
+        let first = hunk[0].number.saturating_sub(DIFF_CONTEXT).max(1);
+        let last = (hunk[hunk.len() - 1].number + DIFF_CONTEXT).min(lines.len());
+        let len = last + 1 - first;
+        // only whole lines are replaced, so both sides have the same length
+        writeln!(out, "@@ -{first},{len} +{first},{len} @@")?;
+
+        let mut changed = hunk.iter().peekable();
+        for number in first..=last {
+            match changed.peek() {
+                Some(change) if change.number == number => {
+                    writeln!(out, "-{}", change.old)?;
+                    writeln!(out, "+{}", change.new)?;
+                    changed.next();
+                }
+                _ => writeln!(out, " {}", lines[number - 1])?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regex::Regex;
+    use crate::test_support::TempDir;
+
+    fn expand_regex(pattern: &str, replacement: &str, line: &str) -> String {
+        let re = Regex::new(pattern).unwrap();
+        let template = Template::parse(replacement, re.group_count()).unwrap();
+        let mut out = String::new();
+        let mut last = 0;
+        for caps in re.captures_iter(line) {
+            let (start, end) = caps.get(0).unwrap();
+            out.push_str(&line[last..start]);
+            template.expand(|i| caps.get(i).map(|(s, e)| &line[s..e]), &mut out);
+            last = end;
+        }
+        out.push_str(&line[last..]);
+        out
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rewrites_the_file_a_symlink_points_to() {
+        let dir = TempDir::new("replace-real");
+        let file = dir.file("real.txt", b"a toad\n");
+        let link = dir.join("link.txt");
+        std::os::unix::fs::symlink(&file, &link).unwrap();
+
+        let (_, changes) =
+            replace_in_file(&link, |line| Some(line.replace("toad", "frog")), false).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "a frog\n");
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        fs::remove_file(&link).unwrap();
+    }
+
+    #[test]
+    fn template_parsing() {
+        let template = Template::parse("<$1|${2}|$$|$0|$x>", 3).unwrap();
+        assert_eq!(
+            template.parts,
+            vec![
+                Part::Literal(String::from("<")),
+                Part::Group(1),
+                Part::Literal(String::from("|")),
+                Part::Group(2),
+                Part::Literal(String::from("|$|")),
+                Part::Group(0),
+                Part::Literal(String::from("|$x>")),
+            ]
+        );
+    }
+
+    #[test]
+    fn template_errors() {
+        let err = Template::parse("$1", 1).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid replacement: $1 refers to a capture group, which needs --regex"
+        );
+
+        let err = Template::parse("${3}", 2).unwrap_err();
+        assert_eq!(err.message, "$3 refers to a group the pattern doesn't have");
+
+        assert!(Template::parse("${1", 2).is_err());
+        assert!(Template::parse("${}", 2).is_err());
+    }
+
+    #[test]
+    fn capture_references() {
+        assert_eq!(
+            expand_regex("(\\w+)body", "$1one", "I'm nobody! Who are you?"),
+            "I'm noone! Who are you?"
+        );
+        assert_eq!(
+            expand_regex("(\\w+), (\\w+)", "$2, $1", "How public, like a frog"),
+            "How like, public a frog"
+        );
+        assert_eq!(expand_regex("o", "[$0]", "to bog"), "t[o] b[o]g");
+        assert_eq!(expand_regex("(x)?y", "<$1>", "y"), "<>");
+    }
+
+    #[test]
+    fn replaces_in_place_keeping_line_endings() {
+        let dir = TempDir::new("replace-endings");
+        let file = dir.file("endings.txt", b"to be\r\nor not\nto be\xff\nto");
+        let (_, changes) =
+            replace_in_file(&file, |line| Some(line.replace("to", "TO")), false).unwrap();
+
+        assert_eq!(
+            changes,
+            vec![
+                Change {
+                    number: 1,
+                    old: String::from("to be"),
+                    new: String::from("TO be"),
+                },
+                Change {
+                    number: 4,
+                    old: String::from("to"),
+                    new: String::from("TO"),
+                },
+            ]
+        );
+        // the line with a bad byte is left exactly as it was
+        assert_eq!(fs::read(&file).unwrap(), b"TO be\r\nor not\nto be\xff\nTO");
+    }
+
+    #[test]
+    fn dry_run_leaves_the_file_alone() {
+        let poem = include_str!("../poem.txt");
+        let dir = TempDir::new("replace-dry-run");
+        let file = dir.file("dry-run.txt", poem.as_bytes());
+        let (lines, changes) =
+            replace_in_file(&file, |line| Some(line.replace("frog", "toad")), true).unwrap();
+
+        assert_eq!(lines.len(), 9);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(fs::read_to_string(&file).unwrap(), poem);
+    }
+
+    #[test]
+    fn no_temp_file_is_left_behind() {
+        let dir = TempDir::new("replace-tidy");
+        let file = dir.file("tidy.txt", b"to be\n");
+        replace_in_file(&file, |line| Some(line.replace("be", "go")), false).unwrap();
+
+        let leftovers: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.contains("tidy.txt.minigrep"))
+            .collect();
+        assert!(leftovers.is_empty(), "{leftovers:?}");
+        assert_eq!(fs::read_to_string(&file).unwrap(), "to go\n");
+    }
+
+    #[test]
+    fn unified_diff() {
+        let lines: Vec<String> = include_str!("../poem.txt")
+            .lines()
+            .map(String::from)
+            .collect();
+        let changes = vec![
+            Change {
+                number: 1,
+                old: lines[0].clone(),
+                new: String::from("I'm noone! Who are you?"),
+            },
+            Change {
+                number: 9,
+                old: lines[8].clone(),
+                new: String::from("To an admiring pond!"),
+            },
+        ];
+
+        let mut out = Vec::new();
+        write_diff(&mut out, "poem.txt", &lines, &changes).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+--- a/poem.txt
++++ b/poem.txt
+@@ -1,4 +1,4 @@
+-I'm nobody! Who are you?
++I'm noone! Who are you?
+ Are you nobody, too?
+ Then there's a pair of us - don't tell!
+ They'd banish us, you know.
+@@ -6,4 +6,4 @@
+ How dreary to be somebody!
+ How public, like a frog
+ To tell your name the livelong day
+-To an admiring bog!
++To an admiring pond!
+"
+        );
+    }
+
+    #[test]
+    fn nearby_changes_share_a_hunk() {
+        let lines: Vec<String> = (1..=12).map(|n| format!("line {n}")).collect();
+        let change = |number: usize| Change {
+            number,
+            old: lines[number - 1].clone(),
+            new: format!("LINE {number}"),
+        };
+
+        let mut out = Vec::new();
+        write_diff(&mut out, "f", &lines, &[change(2), change(9)]).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text.matches("@@ -").count(), 1);
+        assert!(text.contains("@@ -1,12 +1,12 @@"));
+    }
+}
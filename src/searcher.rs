@@ -0,0 +1,367 @@
+//Searching from other programs with the Searcher trait
+/*
+Everything else in this crate is about the command line: flags, files,
+colors. Other programs that only want the matching can use a Searcher
+instead. Each way of matching is a type that implements the trait:
+
+    Literal       the query as it is
+    IgnoreCase    the query without regard to case, and optionally after
+                  normalizing (see unicode.rs)
+    Regex         a regular expression (see regex.rs)
+    Fuzzy         the query with a few typos (see fuzzy.rs)
+    WholeWord     any of these, but only whole words
+
+A Searcher only has to say where it matches in a single line. 'search' then
+goes through a whole text and gives back a Match for each place, with the
+line number and the byte range within that line:
+
+    let searcher = Literal::new("frog");
+    for m in searcher.search(&contents) {
+        println!("{}: {}", m.line_no, m.as_str());
+    }
+
+Like 'longest<'a>' in chapter 10, the matches borrow from the text that was
+searched, so they can't outlive it: a Match keeps the line it was found in
+rather than a copy. The iterator borrows the searcher too, but the matches
+it gives don't, so they can still be used once the searcher is gone.
+
+Any type of our own can be a Searcher, and it works with everything here,
+including WholeWord and `Box<dyn Searcher>`.
+*/
+
+use std::ops::Range;
+use std::str::Lines;
+use std::vec;
+
+use crate::fuzzy::Fuzzy;
+use crate::regex::Regex;
+use crate::unicode::{self, Folding, Prepare};
+
+/// One place a `Searcher` matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match<'h> {
+    /// The line it's on, counting from 1.
+    pub line_no: usize,
+    /// Where it is in `line`, in bytes.
+    pub byte_range: Range<usize>,
+    /// The line it was found in, without its line ending.
+    pub line: &'h str,
+}
+
+impl<'h> Match<'h> {
+    /// The text that matched.
+    pub fn as_str(&self) -> &'h str {
+        &self.line[self.byte_range.clone()]
+    }
+}
+
+/// A way of finding matches in text.
+pub trait Searcher {
+    /// The byte ranges of every non-overlapping match in `line`, in order.
+    fn find_in_line(&self, line: &str) -> Vec<Range<usize>>;
+
+    /// Every match in `haystack`, one line at a time. A `dyn Searcher` can
+    /// search through a `Box` or a reference, which are Searchers too.
+    fn search<'s, 'h>(&'s self, haystack: &'h str) -> Matches<'s, 'h, Self>
+    where
+        Self: Sized,
+    {
+        Matches {
+            searcher: self,
+            lines: haystack.lines(),
+            line_no: 0,
+            line: "",
+            pending: Vec::new().into_iter(),
+        }
+    }
+
+    /// Whether `line` has a match anywhere.
+    fn is_match(&self, line: &str) -> bool {
+        !self.find_in_line(line).is_empty()
+    }
+}
+
+/// The iterator that `Searcher::search` returns.
+#[derive(Debug)]
+pub struct Matches<'s, 'h, S> {
+    searcher: &'s S,
+    lines: Lines<'h>,
+    line_no: usize,
+    line: &'h str,
+    pending: vec::IntoIter<Range<usize>>,
+}
+
+impl<'h, S: Searcher> Iterator for Matches<'_, 'h, S> {
+    type Item = Match<'h>;
+
+    fn next(&mut self) -> Option<Match<'h>> {
+        loop {
+            if let Some(byte_range) = self.pending.next() {
+                return Some(Match {
+                    line_no: self.line_no,
+                    byte_range,
+                    line: self.line,
+                });
+            }
+            self.line = self.lines.next()?;
+            self.line_no += 1;
+            self.pending = self.searcher.find_in_line(self.line).into_iter();
+        }
+    }
+}
+
+impl<S: Searcher + ?Sized> Searcher for &S {
+    fn find_in_line(&self, line: &str) -> Vec<Range<usize>> {
+        (**self).find_in_line(line)
+    }
+}
+
+impl<S: Searcher + ?Sized> Searcher for Box<S> {
+    fn find_in_line(&self, line: &str) -> Vec<Range<usize>> {
+        (**self).find_in_line(line)
+    }
+}
+
+/// Matches the query exactly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Literal {
+    query: String,
+}
+
+impl Literal {
+    pub fn new(query: &str) -> Literal {
+        Literal {
+            query: query.to_string(),
+        }
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+}
+
+impl Searcher for Literal {
+    fn find_in_line(&self, line: &str) -> Vec<Range<usize>> {
+        line.match_indices(self.query.as_str())
+            .map(|(start, m)| start..start + m.len())
+            .collect()
+    }
+}
+
+/// Matches the query after case folding and normalizing both it and the
+/// line, so "STRASSE" finds "Straße".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoreCase {
+    // already prepared
+    query: String,
+    prepare: Prepare,
+}
+
+impl IgnoreCase {
+    /// Ignores case with full case folding.
+    pub fn new(query: &str) -> IgnoreCase {
+        IgnoreCase::with(
+            query,
+            Prepare {
+                fold: Some(Folding::Full),
+                normalize: None,
+            },
+        )
+    }
+
+    /// Prepares the query and each line the way `prepare` says. With no
+    /// folding this only normalizes, and doesn't ignore case at all.
+    pub fn with(query: &str, prepare: Prepare) -> IgnoreCase {
+        IgnoreCase {
+            query: prepare.apply(query),
+            prepare,
+        }
+    }
+
+    /// The query after folding and normalizing.
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn prepare(&self) -> Prepare {
+        self.prepare
+    }
+}
+
+impl Searcher for IgnoreCase {
+    fn find_in_line(&self, line: &str) -> Vec<Range<usize>> {
+        self.prepare
+            .map(line)
+            .find_all(&self.query)
+            .into_iter()
+            .map(|(start, end)| start..end)
+            .collect()
+    }
+}
+
+impl Searcher for Regex {
+    fn find_in_line(&self, line: &str) -> Vec<Range<usize>> {
+        self.find_iter(line)
+            .map(|(start, end)| start..end)
+            .collect()
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        Regex::is_match(self, line)
+    }
+}
+
+impl Searcher for Fuzzy {
+    fn find_in_line(&self, line: &str) -> Vec<Range<usize>> {
+        self.find_all(line)
+            .into_iter()
+            .map(|m| m.start..m.end)
+            .collect()
+    }
+}
+
+/// Keeps only the matches of another `Searcher` that start and end at a
+/// word boundary (see unicode.rs).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WholeWord<S>(pub S);
+
+impl<S: Searcher> Searcher for WholeWord<S> {
+    fn find_in_line(&self, line: &str) -> Vec<Range<usize>> {
+        let mut found = self.0.find_in_line(line);
+        found.retain(|range| is_whole_word(line, range.start, range.end));
+        found
+    }
+}
+
+// whether `start..end` of the line begins and ends at a word boundary
+pub(crate) fn is_whole_word(line: &str, start: usize, end: usize) -> bool {
+    unicode::is_word_boundary(line, start) && unicode::is_word_boundary(line, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POEM: &str = include_str!("../poem.txt");
+
+    // (line number, matched text) for every match
+    type Expected = Vec<(usize, &'static str)>;
+
+    fn found<S: Searcher>(searcher: &S, text: &str) -> Vec<(usize, String)> {
+        searcher
+            .search(text)
+            .map(|m| (m.line_no, m.as_str().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn every_strategy_searches_the_poem() {
+        let strategies: Vec<(Box<dyn Searcher>, Expected)> = vec![
+            (
+                Box::new(Literal::new("body")),
+                vec![(1, "body"), (2, "body"), (6, "body")],
+            ),
+            (
+                Box::new(IgnoreCase::new("TO")),
+                vec![(2, "to"), (6, "to"), (8, "To"), (9, "To")],
+            ),
+            (
+                Box::new(Regex::new("fr?og|bog").unwrap()),
+                vec![(7, "frog"), (9, "bog")],
+            ),
+            (
+                Box::new(Fuzzy::new("admring", 1, false)),
+                vec![(9, "admiring")],
+            ),
+            (
+                Box::new(WholeWord(IgnoreCase::new("to"))),
+                vec![(6, "to"), (8, "To"), (9, "To")],
+            ),
+        ];
+        for (searcher, expected) in strategies {
+            let expected: Vec<(usize, String)> = expected
+                .into_iter()
+                .map(|(line_no, text)| (line_no, text.to_string()))
+                .collect();
+            assert_eq!(found(&searcher, POEM), expected);
+        }
+    }
+
+    #[test]
+    fn matches_have_line_numbers_and_byte_ranges() {
+        let matches: Vec<Match> = IgnoreCase::new("été").search("x\nun ÉTÉ, été").collect();
+        assert_eq!(
+            matches,
+            vec![
+                Match {
+                    line_no: 2,
+                    byte_range: 3..8,
+                    line: "un ÉTÉ, été",
+                },
+                Match {
+                    line_no: 2,
+                    byte_range: 10..15,
+                    line: "un ÉTÉ, été",
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_outlive_the_searcher() {
+        let text = String::from("one frog\ntwo frogs");
+        let matches: Vec<Match> = {
+            let searcher = Literal::new("frog");
+            searcher.search(&text).collect()
+        };
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[1].line, "two frogs");
+    }
+
+    // a strategy of our own: runs of digits
+    struct Digits;
+
+    impl Searcher for Digits {
+        fn find_in_line(&self, line: &str) -> Vec<Range<usize>> {
+            let mut found: Vec<Range<usize>> = Vec::new();
+            for (i, c) in line.char_indices() {
+                if !c.is_ascii_digit() {
+                    continue;
+                }
+                match found.last_mut() {
+                    Some(last) if last.end == i => last.end = i + 1,
+                    _ => found.push(i..i + 1),
+                }
+            }
+            found
+        }
+    }
+
+    #[test]
+    fn custom_strategies_work_everywhere() {
+        let text = "12 frogs\nno numbers\n3.14 and x4";
+        assert_eq!(
+            found(&Digits, text),
+            vec![
+                (1, String::from("12")),
+                (3, String::from("3")),
+                (3, String::from("14")),
+                (3, String::from("4")),
+            ]
+        );
+        assert_eq!(
+            found(&WholeWord(Digits), text),
+            vec![(1, String::from("12"))]
+        );
+        assert!(Digits.is_match("4 bogs"));
+        let by_ref: &dyn Searcher = &Digits;
+        assert!(!by_ref.is_match("bogs"));
+        assert_eq!(found(&by_ref, text), found(&Digits, text));
+    }
+
+    #[test]
+    fn empty_text_has_no_matches() {
+        assert_eq!(Literal::new("").search("").count(), 0);
+        assert_eq!(Literal::new("x").search("\n\n").count(), 0);
+    }
+}
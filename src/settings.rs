@@ -0,0 +1,429 @@
+//Settings from a file, the environment and the command line
+/*
+The book turns on case-insensitive search with the IGNORE_CASE environment
+variable. The same idea works for the other settings too, and some of them are
+worth making permanent, so a setting can come from four places. Each one
+overrides the ones before it:
+
+    1. the built-in defaults
+    2. ~/.minigreprc, a file of 'key = value' lines ('#' starts a comment)
+    3. environment variables, like IGNORE_CASE=1 or MINIGREP_THREADS=4
+    4. flags on the command line
+
+As in the book, setting IGNORE_CASE to anything at all turns it on. Other
+on/off variables, like MINIGREP_REGEX, are turned on by an empty value too,
+while the rest treat an empty value as not set at all. The flags work both
+ways, so a setting turned on by the file or the environment can be turned
+off again for one search: --no-ignore-case (or --case-sensitive), --no-regex
+and --no-line-numbers.
+
+For example, a ~/.minigreprc with
+
+    # always show where the matches are
+    line_numbers = true
+    color = always
+
+turns on -n and --color=always for every search. 'minigrep --print-config'
+shows the settings that end up being used and where each one came from.
+
+A mistake in the file is reported with the line it's on, like
+'/home/me/.minigreprc:3: Unknown setting 'colour'', so it's easy to find.
+*/
+
+use std::env;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::color::ColorChoice;
+use crate::Config;
+
+/// Where the value of a setting came from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Source {
+    #[default]
+    Default,
+    File {
+        path: PathBuf,
+        line: usize,
+    },
+    Env(&'static str),
+    Flag,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File { path, line } => write!(f, "{}:{line}", path.display()),
+            Source::Env(name) => write!(f, "environment variable {name}"),
+            Source::Flag => write!(f, "command line"),
+        }
+    }
+}
+
+/// Where each setting in a `Config` came from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sources {
+    pub ignore_case: Source,
+    pub regex: Source,
+    pub line_numbers: Source,
+    pub color: Source,
+    pub threads: Source,
+    pub before_context: Source,
+    pub after_context: Source,
+}
+
+/// A setting in the file or the environment that couldn't be used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub origin: Source,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.origin {
+            Source::File { .. } => write!(f, "{}: {}", self.origin, self.message),
+            Source::Env(name) => write!(f, "{name}: {}", self.message),
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+// every setting that can be set in the file, and its environment variable
+const SETTINGS: [(&str, &str); 7] = [
+    ("ignore_case", "IGNORE_CASE"),
+    ("regex", "MINIGREP_REGEX"),
+    ("line_numbers", "MINIGREP_LINE_NUMBERS"),
+    ("color", "MINIGREP_COLOR"),
+    ("threads", "MINIGREP_THREADS"),
+    ("before_context", "MINIGREP_BEFORE_CONTEXT"),
+    ("after_context", "MINIGREP_AFTER_CONTEXT"),
+];
+
+// the settings that are either on or off
+const ON_OFF: [&str; 3] = ["ignore_case", "regex", "line_numbers"];
+
+/// Applies ~/.minigreprc, if there is one, and then the environment
+/// variables to `config`.
+pub fn load(config: &mut Config) -> Result<(), Box<dyn error::Error>> {
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"));
+    if let Some(home) = home {
+        let path = Path::new(&home).join(".minigreprc");
+        match fs::read_to_string(&path) {
+            Ok(text) => apply_file(config, &path, &text)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("{}: {e}", path.display()).into()),
+        }
+    }
+    apply_env(config, |name| env::var(name).ok())?;
+    Ok(())
+}
+
+/// Applies the settings in `text`, the contents of the file at `path`.
+pub fn apply_file(config: &mut Config, path: &Path, text: &str) -> Result<(), Error> {
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let origin = Source::File {
+            path: path.to_path_buf(),
+            line: index + 1,
+        };
+        let result = match line.split_once('=') {
+            Some((key, value)) => set(config, key.trim(), value.trim(), origin.clone()),
+            None => Err(String::from("Expected 'key = value'")),
+        };
+        result.map_err(|message| Error { origin, message })?;
+    }
+    Ok(())
+}
+
+/// Applies the settings from environment variables, looking each one up
+/// with `var`.
+pub fn apply_env(config: &mut Config, var: impl Fn(&str) -> Option<String>) -> Result<(), Error> {
+    for (key, name) in SETTINGS {
+        let Some(value) = var(name) else {
+            continue;
+        };
+        // as in the book, IGNORE_CASE turns it on whatever its value, even
+        // an empty one or 0. Any other on/off setting is turned on by an
+        // empty value, and the rest are left as they were.
+        let value = if name == "IGNORE_CASE" {
+            "true"
+        } else if value.is_empty() {
+            if !ON_OFF.contains(&key) {
+                continue;
+            }
+            "true"
+        } else {
+            &value
+        };
+        let origin = Source::Env(name);
+        set(config, key, value, origin.clone()).map_err(|message| Error { origin, message })?;
+    }
+    Ok(())
+}
+
+// Sets one setting by its name in the file.
+fn set(config: &mut Config, key: &str, value: &str, origin: Source) -> Result<(), String> {
+    let sources = &mut config.sources;
+    match key {
+        "ignore_case" => {
+            config.ignore_case = parse_bool(value)?;
+            sources.ignore_case = origin;
+        }
+        "regex" => {
+            config.regex = parse_bool(value)?;
+            sources.regex = origin;
+        }
+        "line_numbers" => {
+            config.output.line_numbers = parse_bool(value)?;
+            sources.line_numbers = origin;
+        }
+        "color" => {
+            config.output.color = ColorChoice::parse(value)?;
+            sources.color = origin;
+        }
+        "threads" => {
+            // 0 is allowed here, to go back to one thread per CPU
+            config.threads = match value {
+                "0" => 0,
+                _ => crate::parse_threads(Some(value.to_string()))?,
+            };
+            sources.threads = origin;
+        }
+        "before_context" => {
+            config.output.before_context = crate::parse_context(Some(value.to_string()))?;
+            sources.before_context = origin;
+        }
+        "after_context" => {
+            config.output.after_context = crate::parse_context(Some(value.to_string()))?;
+            sources.after_context = origin;
+        }
+        _ => return Err(format!("Unknown setting '{key}'")),
+    }
+    Ok(())
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(format!("Expected true or false, not '{value}'")),
+    }
+}
+
+/// Writes every setting in `config` and where it came from, for
+/// `--print-config`.
+pub fn write_config<W: Write>(out: &mut W, config: &Config) -> io::Result<()> {
+    let sources = &config.sources;
+    let rows = [
+        (config.ignore_case.to_string(), &sources.ignore_case),
+        (config.regex.to_string(), &sources.regex),
+        (
+            config.output.line_numbers.to_string(),
+            &sources.line_numbers,
+        ),
+        (config.output.color.to_string(), &sources.color),
+        (config.threads.to_string(), &sources.threads),
+        (
+            config.output.before_context.to_string(),
+            &sources.before_context,
+        ),
+        (
+            config.output.after_context.to_string(),
+            &sources.after_context,
+        ),
+    ];
+    for ((key, _), (value, source)) in SETTINGS.iter().zip(rows) {
+        writeln!(out, "{key:<14} = {value:<6} ({source})")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_file(text: &str) -> Result<Config, Error> {
+        let mut config = Config::default();
+        apply_file(&mut config, Path::new("minigreprc"), text)?;
+        Ok(config)
+    }
+
+    fn from_env(vars: &[(&str, &str)]) -> Result<Config, Error> {
+        let mut config = Config::default();
+        apply_env(&mut config, |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        })?;
+        Ok(config)
+    }
+
+    #[test]
+    fn reads_the_file() {
+        let config = from_file(
+            "\
+# my settings
+line_numbers = true
+
+  color=always
+threads = 4
+before_context = 2
+",
+        )
+        .unwrap();
+        assert!(config.output.line_numbers);
+        assert_eq!(config.output.color, ColorChoice::Always);
+        assert_eq!(config.threads, 4);
+        assert_eq!(config.output.before_context, 2);
+        assert_eq!(
+            config.sources.color,
+            Source::File {
+                path: PathBuf::from("minigreprc"),
+                line: 4,
+            }
+        );
+        assert_eq!(config.sources.ignore_case, Source::Default);
+    }
+
+    #[test]
+    fn file_errors_have_line_numbers() {
+        let cases = [
+            ("colour = always", "minigreprc:1: Unknown setting 'colour'"),
+            (
+                "# comment\n\nline_numbers",
+                "minigreprc:3: Expected 'key = value'",
+            ),
+            (
+                "regex = true\nregex = maybe",
+                "minigreprc:2: Expected true or false, not 'maybe'",
+            ),
+            (
+                "threads = -2",
+                "minigreprc:1: Thread count must be a positive number",
+            ),
+            (
+                "color = red",
+                "minigreprc:1: Color must be auto, always or never",
+            ),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(from_file(text).unwrap_err().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn reads_the_environment() {
+        let config = from_env(&[("IGNORE_CASE", "1"), ("MINIGREP_THREADS", "3")]).unwrap();
+        assert!(config.ignore_case);
+        assert_eq!(config.threads, 3);
+        assert_eq!(config.sources.ignore_case, Source::Env("IGNORE_CASE"));
+        assert_eq!(config.sources.regex, Source::Default);
+
+        // the book sets IGNORE_CASE to anything at all
+        for value in ["", "0", "false", "whatever"] {
+            assert!(from_env(&[("IGNORE_CASE", value)]).unwrap().ignore_case);
+        }
+
+        // an empty on/off setting is on, and any other empty one is unset
+        let config = from_env(&[
+            ("MINIGREP_LINE_NUMBERS", ""),
+            ("MINIGREP_COLOR", ""),
+            ("MINIGREP_THREADS", ""),
+        ])
+        .unwrap();
+        assert!(config.output.line_numbers);
+        assert_eq!(config.output.color, Config::default().output.color);
+        assert_eq!(config.sources.color, Source::Default);
+        assert_eq!(config.sources.threads, Source::Default);
+
+        let err = from_env(&[("MINIGREP_COLOR", "loud")]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "MINIGREP_COLOR: Color must be auto, always or never"
+        );
+    }
+
+    #[test]
+    fn later_layers_win() {
+        let mut config = from_file("line_numbers = true\nthreads = 2\ncolor = never").unwrap();
+        apply_env(&mut config, |name| {
+            (name == "MINIGREP_THREADS").then(|| String::from("8"))
+        })
+        .unwrap();
+        let args = ["minigrep", "--color=always", "to", "poem.txt"];
+        let config = config
+            .with_args(args.iter().map(|arg| arg.to_string()))
+            .unwrap();
+
+        assert!(config.output.line_numbers);
+        assert_eq!(config.threads, 8);
+        assert_eq!(config.output.color, ColorChoice::Always);
+        assert_eq!(config.sources.threads, Source::Env("MINIGREP_THREADS"));
+        assert_eq!(config.sources.color, Source::Flag);
+    }
+
+    #[test]
+    fn flags_turn_settings_off_again() {
+        let mut config = from_file("regex = true\nline_numbers = true").unwrap();
+        apply_env(&mut config, |name| {
+            (name == "IGNORE_CASE").then(String::new)
+        })
+        .unwrap();
+        let with = |config: &Config, args: &[&str]| {
+            let args = ["minigrep"].iter().chain(args).map(|arg| arg.to_string());
+            config.clone().with_args(args).unwrap()
+        };
+
+        let on = with(&config, &["to", "poem.txt"]);
+        assert!(on.ignore_case && on.regex && on.output.line_numbers);
+
+        let off = with(
+            &config,
+            &["--no-ignore-case", "--no-regex", "--no-line-numbers", "to"],
+        );
+        assert!(!off.ignore_case && !off.regex && !off.output.line_numbers);
+        assert_eq!(off.sources.ignore_case, Source::Flag);
+        assert_eq!(off.sources.regex, Source::Flag);
+        assert_eq!(off.sources.line_numbers, Source::Flag);
+        assert!(!with(&config, &["--case-sensitive", "to"]).ignore_case);
+
+        // the last flag wins
+        assert!(with(&config, &["--case-sensitive", "-i", "to"]).ignore_case);
+        assert!(with(&off, &["--line-numbers", "to"]).output.line_numbers);
+    }
+
+    #[test]
+    fn print_config() {
+        let mut config = from_file("regex = yes").unwrap();
+        config.output.line_numbers = true;
+        config.sources.line_numbers = Source::Flag;
+        config.sources.threads = Source::Env("MINIGREP_THREADS");
+        config.threads = 2;
+
+        let mut out = Vec::new();
+        write_config(&mut out, &config).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+ignore_case    = false  (default)
+regex          = true   (minigreprc:1)
+line_numbers   = true   (command line)
+color          = auto   (default)
+threads        = 2      (environment variable MINIGREP_THREADS)
+before_context = 0      (default)
+after_context  = 0      (default)
+"
+        );
+    }
+}
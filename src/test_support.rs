@@ -0,0 +1,48 @@
+//Helpers shared by the unit tests
+/*
+Searching directories, building an index, watching and rewriting files all
+need real files to test with. TempDir makes a scratch directory for them
+under the system temp dir and removes it again when the test is done, even
+if it fails. Its name includes the process id, and each test gives it a name
+of its own, so tests running at the same time don't share one.
+*/
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// A scratch directory, removed with everything in it when dropped.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// An empty directory; `name` should be different for every test.
+    pub(crate) fn new(name: &str) -> TempDir {
+        let path = std::env::temp_dir().join(format!("minigrep-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub(crate) fn join(&self, relative: &str) -> PathBuf {
+        self.0.join(relative)
+    }
+
+    /// Writes `contents` to the file at `relative`, making the directories
+    /// on the way, and returns its path.
+    pub(crate) fn file(&self, relative: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.0.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
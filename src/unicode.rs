@@ -0,0 +1,618 @@
+//Unicode-aware matching: case folding, normalization and whole words
+/*
+Comparing 'to_lowercase' versions of two strings gets a lot of text wrong:
+
+    "Straße" and "STRASSE" are the same word, but 'ß' lowercases to itself.
+    In Turkish, the capital of 'i' is 'İ' and the small letter of 'I' is 'ı'.
+    "é" can be one character (U+00E9) or two, 'e' and a combining accent
+    (U+0301), and both are meant to be the same thing.
+
+Unicode has answers for all of these.
+
+Case folding maps every character to a form that is the same for all its
+cases. Full folding may turn one character into several, like 'ß' into "ss",
+and folds some lowercase letters too, like the Greek final sigma 'ς' into
+'σ'. Turkish and Azeri fold 'I' to 'ı' and 'İ' to 'i' instead. '-i' uses full
+folding and '--turkish' the Turkish kind.
+
+Normalization picks one of the ways to write each accented character:
+NFD takes every character apart into a base and combining marks, and puts
+the marks in a standard order; NFC puts them back together wherever a
+composed character exists. '--normalize=nfc' or '--normalize=nfd' puts both
+the query and every line in that form before comparing them, so "é" finds
+both spellings. A match then has to cover whole characters with all their
+accents, so "e" doesn't find the "e" inside an "é".
+
+Whole-word matching ('-w') only keeps matches that start and end at a word
+boundary, following the rules in Unicode Standard Annex #29: letters,
+digits, combining marks and '_' join together, and an apostrophe or a period
+between two letters doesn't split them, so "don't" is one word and "don"
+isn't found in it. As the annex allows, ':' is not treated as part of a
+word, so "frog" is still a word in "frog:toad".
+
+The crate has no dependencies, so the tables are in unicode_tables.rs,
+written by scripts/unicode_tables.py from the whole Unicode Character
+Database. Korean Hangul syllables are taken apart and put together with
+arithmetic instead, like the standard describes.
+*/
+
+use crate::unicode_tables::{CASE_FOLDING, COMBINING_CLASSES, COMPOSITIONS, DECOMPOSITIONS};
+
+/// Which case folding to use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Folding {
+    #[default]
+    Full,
+    Turkish,
+}
+
+/// A normalization form to put text in before matching.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    Nfc,
+    Nfd,
+}
+
+impl Normalization {
+    /// Parses the value given to `--normalize`.
+    pub fn parse(value: &str) -> Result<Normalization, &'static str> {
+        match value {
+            "nfc" | "NFC" => Ok(Normalization::Nfc),
+            "nfd" | "NFD" => Ok(Normalization::Nfd),
+            _ => Err("Normalization must be nfc or nfd"),
+        }
+    }
+}
+
+/// How the query and each line are changed before they're compared.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Prepare {
+    pub fold: Option<Folding>,
+    pub normalize: Option<Normalization>,
+}
+
+impl Prepare {
+    /// Returns `text` folded and normalized.
+    pub fn apply(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        self.apply_to(text, &mut out);
+        out
+    }
+
+    fn apply_to(&self, text: &str, out: &mut String) {
+        let Some(form) = self.normalize else {
+            match self.fold {
+                Some(folding) => text.chars().for_each(|c| fold_char(c, folding, out)),
+                None => out.push_str(text),
+            }
+            return;
+        };
+
+        // folding can undo a normalization, as with 'ǰ', so do it again
+        let mut decomposed = nfd(text);
+        if let Some(folding) = self.fold {
+            decomposed = nfd(&fold(&decomposed, folding));
+        }
+        match form {
+            Normalization::Nfd => out.push_str(&decomposed),
+            Normalization::Nfc => out.push_str(&compose(&decomposed)),
+        }
+    }
+
+    /// Prepares a line, remembering which piece of the result came from
+    /// which piece of the line so that matches can be mapped back.
+    pub fn map(&self, line: &str) -> Mapped {
+        let mut text = String::with_capacity(line.len());
+        let mut bounds = vec![(0, 0)];
+
+        if self.normalize.is_none() {
+            // folding works on any single character, so each one is a piece
+            for (i, c) in line.char_indices() {
+                match self.fold {
+                    Some(folding) => fold_char(c, folding, &mut text),
+                    None => text.push(c),
+                }
+                bounds.push((text.len(), i + c.len_utf8()));
+            }
+        } else {
+            // normalizing works on a base character with its combining marks
+            let mut starts: Vec<usize> = line
+                .char_indices()
+                .filter(|&(i, c)| i == 0 || starts_segment(c))
+                .map(|(i, _)| i)
+                .collect();
+            starts.push(line.len());
+
+            for piece in starts.windows(2) {
+                self.apply_to(&line[piece[0]..piece[1]], &mut text);
+                bounds.push((text.len(), piece[1]));
+            }
+        }
+        // an empty line still maps its one position
+        bounds.dedup();
+
+        Mapped { text, bounds }
+    }
+}
+
+/// A line after `Prepare::map`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mapped {
+    pub text: String,
+    // (offset in text, offset in the line) wherever a piece ends
+    bounds: Vec<(usize, usize)>,
+}
+
+impl Mapped {
+    /// The range of the line that `start..end` of the prepared text came
+    /// from, or `None` if either end falls inside a piece.
+    pub fn original(&self, start: usize, end: usize) -> Option<(usize, usize)> {
+        let start = self.bounds.binary_search_by_key(&start, |b| b.0).ok()?;
+        let end = self.bounds.binary_search_by_key(&end, |b| b.0).ok()?;
+        Some((self.bounds[start].1, self.bounds[end].1))
+    }
+
+    /// Every non-overlapping place `query` is found that maps back to whole
+    /// pieces of the line, as byte ranges of the line.
+    pub fn find_all(&self, query: &str) -> Vec<(usize, usize)> {
+        let mut found = Vec::new();
+        let mut from = 0;
+        while let Some(i) = self.text[from..].find(query) {
+            let start = from + i;
+            let end = start + query.len();
+            match self.original(start, end) {
+                Some(range) if end > start => {
+                    found.push(range);
+                    from = end;
+                    continue;
+                }
+                Some(range) => found.push(range),
+                None => {}
+            }
+            // try again one character further on
+            match self.text[start..].chars().next() {
+                Some(c) => from = start + c.len_utf8(),
+                None => break,
+            }
+        }
+        found
+    }
+}
+
+/// Case folds `text`.
+pub fn fold(text: &str, folding: Folding) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        fold_char(c, folding, &mut out);
+    }
+    out
+}
+
+// Case folds one character onto the end of `out`.
+fn fold_char(c: char, folding: Folding, out: &mut String) {
+    match (folding, c) {
+        (Folding::Turkish, 'I') => out.push('ı'),
+        (Folding::Turkish, 'İ') => out.push('i'),
+        // Cherokee is the one script that folds to its capitals
+        (_, '\u{13a0}'..='\u{13f5}') => out.push(c),
+        (_, '\u{13f8}'..='\u{13fd}') => out.push(shift(c, -8)),
+        (_, '\u{ab70}'..='\u{abbf}') => out.push(shift(c, 0x13a0 - 0xab70)),
+        _ => match CASE_FOLDING.binary_search_by_key(&c, |&(from, _)| from) {
+            Ok(i) => out.push_str(CASE_FOLDING[i].1),
+            Err(_) => out.extend(c.to_lowercase()),
+        },
+    }
+}
+
+/// Puts `text` in Normalization Form D.
+pub fn nfd(text: &str) -> String {
+    let mut chars = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        decompose(c, &mut chars);
+    }
+
+    // the canonical order: combining marks in a row are sorted by class,
+    // keeping marks of the same class in the order they were
+    let mut i = 0;
+    while i < chars.len() {
+        if combining_class(chars[i]) == 0 {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && combining_class(chars[i]) != 0 {
+            i += 1;
+        }
+        chars[start..i].sort_by_key(|&c| combining_class(c));
+    }
+
+    chars.into_iter().collect()
+}
+
+/// Puts `text` in Normalization Form C.
+pub fn nfc(text: &str) -> String {
+    compose(&nfd(text))
+}
+
+/// Whether there's a word boundary at byte offset `at` in `text`, by the
+/// rules of UAX #29 (see the top of this file).
+pub fn is_word_boundary(text: &str, at: usize) -> bool {
+    if at == 0 || at >= text.len() {
+        return true;
+    }
+    let (before, after) = text.split_at(at);
+    let mut after = after.chars().map(|c| (c, word_class(c)));
+    let Some((_, next)) = after.next() else {
+        return true;
+    };
+    // marks stick to whatever comes before them
+    if next == WordClass::Extend {
+        return false;
+    }
+    let next2 = after
+        .map(|(_, class)| class)
+        .find(|&class| class != WordClass::Extend);
+
+    let mut before = before
+        .chars()
+        .rev()
+        .map(word_class)
+        .filter(|&class| class != WordClass::Extend);
+    let Some(prev) = before.next() else {
+        return true;
+    };
+    let prev2 = before.next();
+
+    use WordClass::*;
+    let mid_letter = |class| matches!(class, MidLetter | MidNumLet);
+    let mid_num = |class| matches!(class, MidNum | MidNumLet);
+    let joins = match (prev, next) {
+        (Space, Space) => true,
+        (Letter | Numeric, Letter | Numeric) => true,
+        (Letter, mid) if mid_letter(mid) => next2 == Some(Letter),
+        (mid, Letter) if mid_letter(mid) => prev2 == Some(Letter),
+        (Numeric, mid) if mid_num(mid) => next2 == Some(Numeric),
+        (mid, Numeric) if mid_num(mid) => prev2 == Some(Numeric),
+        (Katakana, Katakana) => true,
+        (Letter | Numeric | Katakana | ExtendNumLet, ExtendNumLet) => true,
+        (ExtendNumLet, Letter | Numeric | Katakana) => true,
+        _ => false,
+    };
+    !joins
+}
+
+// The classes of UAX #29 that matter inside a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WordClass {
+    Letter,
+    Numeric,
+    Katakana,
+    Extend,
+    ExtendNumLet,
+    MidLetter,
+    MidNum,
+    MidNumLet,
+    Space,
+    Other,
+}
+
+fn word_class(c: char) -> WordClass {
+    match c {
+        '\u{200d}' | '\u{ad}' | '\u{2060}' | '\u{feff}' | '\u{fe00}'..='\u{fe0f}' => {
+            WordClass::Extend
+        }
+        '_'
+        | '\u{203f}'
+        | '\u{2040}'
+        | '\u{2054}'
+        | '\u{fe33}'
+        | '\u{fe34}'
+        | '\u{fe4d}'..='\u{fe4f}'
+        | '\u{ff3f}'
+        | '\u{202f}' => WordClass::ExtendNumLet,
+        '\u{b7}' | '\u{387}' | '\u{55f}' | '\u{5f4}' | '\u{2027}' | '\u{fe13}' | '\u{fe55}'
+        | '\u{ff1a}' => WordClass::MidLetter,
+        ',' | ';' | '\u{37e}' | '\u{589}' | '\u{60c}' | '\u{60d}' | '\u{66c}' | '\u{7f8}'
+        | '\u{2044}' | '\u{fe10}' | '\u{fe14}' | '\u{fe50}' | '\u{fe54}' | '\u{ff0c}'
+        | '\u{ff1b}' => WordClass::MidNum,
+        '.' | '\'' | '\u{2018}' | '\u{2019}' | '\u{2024}' | '\u{fe52}' | '\u{ff07}'
+        | '\u{ff0e}' => WordClass::MidNumLet,
+        ' '
+        | '\u{1680}'
+        | '\u{2000}'..='\u{2006}'
+        | '\u{2008}'..='\u{200a}'
+        | '\u{205f}'
+        | '\u{3000}' => WordClass::Space,
+        '\u{30a0}'..='\u{30ff}' | '\u{31f0}'..='\u{31ff}' | '\u{ff66}'..='\u{ff9d}' => {
+            WordClass::Katakana
+        }
+        // each ideograph and kana is a word on its own
+        '\u{3040}'..='\u{309f}'
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{f900}'..='\u{faff}'
+        | '\u{20000}'..='\u{3ffff}' => WordClass::Other,
+        _ if combining_class(c) != 0 || is_mark(c) => WordClass::Extend,
+        _ if c.is_numeric() => WordClass::Numeric,
+        _ if c.is_alphabetic() => WordClass::Letter,
+        _ => WordClass::Other,
+    }
+}
+
+// Combining marks that have class 0, so aren't in COMBINING_CLASSES.
+fn is_mark(c: char) -> bool {
+    matches!(
+        c,
+        '\u{300}'..='\u{36f}'
+            | '\u{483}'..='\u{489}'
+            | '\u{1ab0}'..='\u{1aff}'
+            | '\u{1dc0}'..='\u{1dff}'
+            | '\u{20d0}'..='\u{20ff}'
+            | '\u{fe20}'..='\u{fe2f}'
+    )
+}
+
+fn combining_class(c: char) -> u8 {
+    match COMBINING_CLASSES.binary_search_by(|&(first, last, _)| {
+        if last < c {
+            std::cmp::Ordering::Less
+        } else if first > c {
+            std::cmp::Ordering::Greater
+        } else {
+            std::cmp::Ordering::Equal
+        }
+    }) {
+        Ok(i) => COMBINING_CLASSES[i].2,
+        Err(_) => 0,
+    }
+}
+
+// A new piece of a line for normalizing starts at each base character,
+// except for the Hangul vowels and final consonants that join a syllable.
+fn starts_segment(c: char) -> bool {
+    combining_class(c) == 0 && !matches!(c, '\u{1161}'..='\u{1175}' | '\u{11a8}'..='\u{11c2}')
+}
+
+fn shift(c: char, by: i32) -> char {
+    char::from_u32((c as i32 + by) as u32).unwrap_or(c)
+}
+
+// Hangul syllables are numbered by their leading consonant, vowel and
+// final consonant, so they come apart and go together by arithmetic.
+const HANGUL_BASE: u32 = 0xac00;
+const LEAD_BASE: u32 = 0x1100;
+const VOWEL_BASE: u32 = 0x1161;
+const TRAIL_BASE: u32 = 0x11a7;
+const VOWELS: u32 = 21;
+const TRAILS: u32 = 28;
+const SYLLABLES: u32 = 19 * VOWELS * TRAILS;
+
+fn decompose(c: char, out: &mut Vec<char>) {
+    let s = (c as u32).wrapping_sub(HANGUL_BASE);
+    if s < SYLLABLES {
+        let lead = LEAD_BASE + s / (VOWELS * TRAILS);
+        let vowel = VOWEL_BASE + (s % (VOWELS * TRAILS)) / TRAILS;
+        let trail = TRAIL_BASE + s % TRAILS;
+        out.extend(char::from_u32(lead));
+        out.extend(char::from_u32(vowel));
+        if trail != TRAIL_BASE {
+            out.extend(char::from_u32(trail));
+        }
+        return;
+    }
+
+    match DECOMPOSITIONS.binary_search_by_key(&c, |&(from, _)| from) {
+        Ok(i) => {
+            for part in DECOMPOSITIONS[i].1.chars() {
+                decompose(part, out);
+            }
+        }
+        Err(_) => out.push(c),
+    }
+}
+
+// The canonical composition algorithm, on text that is already in NFD.
+fn compose(text: &str) -> String {
+    let mut out: Vec<char> = Vec::with_capacity(text.len());
+    // the last base character, and the class of the last mark after it
+    let mut starter: Option<usize> = None;
+    let mut last_class: Option<u8> = None;
+
+    for c in text.chars() {
+        let class = combining_class(c);
+        if let Some(s) = starter {
+            // a mark in between with the same or a higher class blocks it
+            let blocked = last_class.is_some_and(|last| last == 0 || last >= class);
+            if !blocked {
+                if let Some(composed) = compose_pair(out[s], c) {
+                    out[s] = composed;
+                    continue;
+                }
+            }
+        }
+        if class == 0 {
+            starter = Some(out.len());
+            last_class = None;
+        } else {
+            last_class = Some(class);
+        }
+        out.push(c);
+    }
+
+    out.into_iter().collect()
+}
+
+fn compose_pair(first: char, second: char) -> Option<char> {
+    let (a, b) = (first as u32, second as u32);
+    // leading consonant + vowel, then syllable + final consonant
+    if (LEAD_BASE..LEAD_BASE + 19).contains(&a) && (VOWEL_BASE..VOWEL_BASE + VOWELS).contains(&b) {
+        let lv = (a - LEAD_BASE) * VOWELS * TRAILS + (b - VOWEL_BASE) * TRAILS;
+        return char::from_u32(HANGUL_BASE + lv);
+    }
+    let s = a.wrapping_sub(HANGUL_BASE);
+    if s < SYLLABLES && s % TRAILS == 0 && (TRAIL_BASE + 1..TRAIL_BASE + TRAILS).contains(&b) {
+        return char::from_u32(a + b - TRAIL_BASE);
+    }
+
+    COMPOSITIONS
+        .binary_search_by_key(&(first, second), |&(a, b, _)| (a, b))
+        .ok()
+        .map(|i| COMPOSITIONS[i].2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FULL: Prepare = Prepare {
+        fold: Some(Folding::Full),
+        normalize: None,
+    };
+
+    // how to prepare, the line, the query before preparing, and the
+    // matches in the line
+    type MapCase = (
+        Prepare,
+        &'static str,
+        &'static str,
+        &'static [(usize, usize)],
+    );
+
+    fn codepoints(text: &str) -> Vec<u32> {
+        text.chars().map(|c| c as u32).collect()
+    }
+
+    #[test]
+    fn case_folding() {
+        let cases = [
+            ("Straße", Folding::Full, "strasse"),
+            ("STRASSE", Folding::Full, "strasse"),
+            ("ẞ", Folding::Full, "ss"),
+            ("ΣΊΣΥΦΟΣ", Folding::Full, "σίσυφοσ"),
+            ("σίσυφος", Folding::Full, "σίσυφοσ"),
+            ("ﬁle", Folding::Full, "file"),
+            ("\u{212a}elvin", Folding::Full, "kelvin"),
+            ("ſ", Folding::Full, "s"),
+            ("µ", Folding::Full, "μ"),
+            ("İstanbul", Folding::Full, "i\u{307}stanbul"),
+            ("İstanbul", Folding::Turkish, "istanbul"),
+            ("DIYARBAKIR", Folding::Full, "diyarbakir"),
+            ("DIYARBAKIR", Folding::Turkish, "dıyarbakır"),
+            ("ı", Folding::Full, "ı"),
+            ("ꮳᏣ", Folding::Full, "ᏣᏣ"),
+            ("Frog", Folding::Turkish, "frog"),
+        ];
+        for (text, folding, expected) in cases {
+            assert_eq!(fold(text, folding), expected, "{text} {folding:?}");
+        }
+    }
+
+    #[test]
+    fn normalization_forms() {
+        // (input, NFD, NFC), checked against Python's unicodedata.normalize
+        let cases: [(&str, &[u32], &[u32]); 13] = [
+            ("é", &[0x65, 0x301], &[0xe9]),
+            ("e\u{301}", &[0x65, 0x301], &[0xe9]),
+            // marks are sorted by class: the dot below (220) goes first
+            (
+                "q\u{307}\u{323}",
+                &[0x71, 0x323, 0x307],
+                &[0x71, 0x323, 0x307],
+            ),
+            ("ṩ", &[0x73, 0x323, 0x307], &[0x1e69]),
+            ("s\u{307}\u{323}", &[0x73, 0x323, 0x307], &[0x1e69]),
+            ("\u{212b}", &[0x41, 0x30a], &[0xc5]),
+            ("\u{212a}", &[0x4b], &[0x4b]),
+            ("ᾷ", &[0x3b1, 0x342, 0x345], &[0x1fb7]),
+            (
+                "한글",
+                &[0x1112, 0x1161, 0x11ab, 0x1100, 0x1173, 0x11af],
+                &[0xd55c, 0xae00],
+            ),
+            // kana with a voicing mark, Arabic and Devanagari
+            ("\u{304b}\u{3099}", &[0x304b, 0x3099], &[0x304c]),
+            ("\u{622}", &[0x627, 0x653], &[0x622]),
+            ("\u{928}\u{93c}", &[0x928, 0x93c], &[0x929]),
+            // U+0958 is a composition exclusion, so it stays apart
+            ("\u{958}", &[0x915, 0x93c], &[0x915, 0x93c]),
+        ];
+        for (text, decomposed, composed) in cases {
+            assert_eq!(codepoints(&nfd(text)), decomposed, "NFD of {text}");
+            assert_eq!(codepoints(&nfc(text)), composed, "NFC of {text}");
+        }
+    }
+
+    #[test]
+    fn normalization_leaves_plain_text_alone() {
+        let poem = include_str!("../poem.txt");
+        assert_eq!(nfd(poem), poem);
+        assert_eq!(nfc(poem), poem);
+    }
+
+    #[test]
+    fn word_boundaries() {
+        // (text, byte offset, is there a boundary)
+        let cases = [
+            ("frog bog", 4, true),
+            ("frog bog", 2, false),
+            ("frog_bog", 4, false),
+            ("frog-bog", 4, true),
+            ("frog:bog", 4, true),
+            ("don't", 3, false),
+            ("don't", 4, false),
+            ("'tis", 1, true),
+            ("3.14", 1, false),
+            ("e.g.", 1, false),
+            ("1,000", 1, false),
+            ("a,b", 1, true),
+            ("café", 3, false),
+            // a combining accent belongs to the letter before it
+            ("cafe\u{301}s", 4, false),
+            ("cafe\u{301} s", 6, true),
+            ("日本語", 3, true),
+            ("カタカナ", 3, false),
+            ("a1", 1, false),
+            ("frog", 0, true),
+            ("frog", 4, true),
+        ];
+        for (text, at, expected) in cases {
+            assert_eq!(is_word_boundary(text, at), expected, "{text:?} at {at}");
+        }
+    }
+
+    #[test]
+    fn matches_map_back_to_the_line() {
+        let nfc = Prepare {
+            fold: None,
+            normalize: Some(Normalization::Nfc),
+        };
+        let nfd_fold = Prepare {
+            fold: Some(Folding::Full),
+            normalize: Some(Normalization::Nfd),
+        };
+        let cases: [MapCase; 7] = [
+            (FULL, "Straße", "STRASSE", &[(0, 7)]),
+            (FULL, "Straße", "s", &[(0, 1)]),
+            (FULL, "MASSE und Maße", "masse", &[(0, 5), (10, 15)]),
+            (nfc, "cafe\u{301} or café", "café", &[(0, 6), (10, 15)]),
+            // "e" alone doesn't split "é" from its accent
+            (nfc, "cafe\u{301}", "e", &[]),
+            (nfd_fold, "CAFE\u{301} or café", "cafÉ", &[(0, 6), (10, 15)]),
+            (nfd_fold, "ΣΊΣΥΦΟΣ", "σίσυφος", &[(0, 14)]),
+        ];
+        for (prepare, line, query, expected) in cases {
+            let mapped = prepare.map(line);
+            assert_eq!(
+                mapped.find_all(&prepare.apply(query)),
+                expected,
+                "{query} in {line}"
+            );
+        }
+    }
+
+    #[test]
+    fn empty_query_matches_every_piece() {
+        let mapped = FULL.map("ßa");
+        assert_eq!(mapped.find_all(""), vec![(0, 0), (2, 2), (3, 3)]);
+    }
+}
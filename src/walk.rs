@@ -0,0 +1,381 @@
+//Walking a directory tree while honouring .gitignore
+/*
+When minigrep is given a directory it searches every file below it. Build
+output and anything else git ignores is noise, so the walk skips 'target/'
+and '.git/' outright and reads the '.gitignore' file of every directory it
+enters. Rules from a '.gitignore' apply to that directory and everything
+below it, and like git, the last rule that matches a path decides whether it
+is ignored, so '!' rules can bring back something an earlier rule excluded.
+
+The rule syntax follows git:
+    # comment     blank lines and comments are skipped
+    *.log         no slash: matches the name at any depth
+    /build        a leading or middle slash anchors the pattern to the
+                  directory holding the .gitignore
+    logs/         a trailing slash only matches directories
+    **            as a whole path segment, matches any number of directories
+    !keep.log     negation re-includes a path
+*/
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// directories that are never worth searching
+const ALWAYS_SKIP: [&str; 2] = ["target", ".git"];
+
+/// One line of a `.gitignore` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    base: PathBuf,
+    pattern: String,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+impl Rule {
+    /// Parses one `.gitignore` line found in the directory `base`.
+    /// Returns `None` for blank lines and comments.
+    pub fn parse(line: &str, base: &Path) -> Option<Rule> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let pattern = line.trim_start_matches('/');
+        if pattern.is_empty() {
+            return None;
+        }
+
+        Some(Rule {
+            base: base.to_path_buf(),
+            pattern: pattern.to_string(),
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let relative = match path.strip_prefix(&self.base) {
+            Ok(relative) => relative,
+            Err(_) => return false,
+        };
+
+        if self.anchored {
+            glob_match(&self.pattern, &slash_path(relative))
+        } else {
+            match relative.file_name() {
+                Some(name) => glob_match(&self.pattern, &name.to_string_lossy()),
+                None => false,
+            }
+        }
+    }
+}
+
+/// The `.gitignore` rules that apply to one directory, including the ones
+/// inherited from its parents.
+#[derive(Debug, Clone, Default)]
+pub struct Ignore {
+    rules: Vec<Rule>,
+}
+
+impl Ignore {
+    pub fn new() -> Ignore {
+        Ignore::default()
+    }
+
+    /// Adds the rules in the text of a `.gitignore` file that lives in `base`.
+    pub fn add_rules(&mut self, text: &str, base: &Path) {
+        self.rules
+            .extend(text.lines().filter_map(|line| Rule::parse(line, base)));
+    }
+
+    /// Whether `path` is excluded. The last matching rule wins.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(path, is_dir))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+/// Returns every file under `root` that isn't ignored, sorted by path.
+/// If `root` is a file, it is returned on its own.
+pub fn walk(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if root.is_dir() {
+        visit(root, &Ignore::new(), &mut files)?;
+    } else {
+        files.push(root.to_path_buf());
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn visit(dir: &Path, inherited: &Ignore, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut ignore = inherited.clone();
+    match fs::read_to_string(dir.join(".gitignore")) {
+        Ok(text) => ignore.add_rules(&text, dir),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        let is_dir = file_type.is_dir();
+
+        if is_dir && ALWAYS_SKIP.iter().any(|skip| entry.file_name() == *skip) {
+            continue;
+        }
+        if ignore.is_ignored(&path, is_dir) {
+            continue;
+        }
+
+        if is_dir {
+            visit(&path, &ignore, files)?;
+        } else if file_type.is_file() {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+// gitignore patterns always use '/', whatever the platform
+pub(crate) fn slash_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Matches a gitignore-style glob against a '/'-separated path.
+/// '*' and '?' stay inside one path segment, '**' crosses segments,
+/// and '[...]' matches one character from a set.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    glob_match_at(&pattern, &path)
+}
+
+fn glob_match_at(pattern: &[char], path: &[char]) -> bool {
+    match pattern.first() {
+        None => path.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            let mut rest = &pattern[2..];
+            // '**/' may also match no directories at all
+            if rest.first() == Some(&'/') {
+                rest = &rest[1..];
+                if glob_match_at(rest, path) {
+                    return true;
+                }
+                return (0..path.len())
+                    .filter(|&i| path[i] == '/')
+                    .any(|i| glob_match_at(rest, &path[i + 1..]));
+            }
+            (0..=path.len()).any(|i| glob_match_at(rest, &path[i..]))
+        }
+        Some('*') => {
+            let rest = &pattern[1..];
+            for i in 0..=path.len() {
+                if glob_match_at(rest, &path[i..]) {
+                    return true;
+                }
+                if i < path.len() && path[i] == '/' {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => match path.first() {
+            Some(&c) if c != '/' => glob_match_at(&pattern[1..], &path[1..]),
+            _ => false,
+        },
+        Some('[') => match (path.first(), class_end(pattern)) {
+            (Some(&c), Some(end)) if c != '/' => {
+                class_matches(&pattern[1..end], c) && glob_match_at(&pattern[end + 1..], &path[1..])
+            }
+            (Some(&c), None) => c == '[' && glob_match_at(&pattern[1..], &path[1..]),
+            _ => false,
+        },
+        Some('\\') if pattern.len() > 1 => {
+            path.first() == Some(&pattern[1]) && glob_match_at(&pattern[2..], &path[1..])
+        }
+        Some(&p) => path.first() == Some(&p) && glob_match_at(&pattern[1..], &path[1..]),
+    }
+}
+
+// the index of the ']' closing the class that starts at pattern[0]
+fn class_end(pattern: &[char]) -> Option<usize> {
+    let mut i = 1;
+    if matches!(pattern.get(i), Some('!') | Some('^')) {
+        i += 1;
+    }
+    if pattern.get(i) == Some(&']') {
+        i += 1;
+    }
+    (i..pattern.len()).find(|&j| pattern[j] == ']')
+}
+
+fn class_matches(class: &[char], c: char) -> bool {
+    let (negated, class) = match class.first() {
+        Some('!') | Some('^') => (true, &class[1..]),
+        _ => (false, class),
+    };
+
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            found |= class[i] <= c && c <= class[i + 2];
+            i += 3;
+        } else {
+            found |= class[i] == c;
+            i += 1;
+        }
+    }
+    found != negated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn relative(dir: &TempDir, files: Vec<PathBuf>) -> Vec<String> {
+        files
+            .iter()
+            .map(|f| slash_path(f.strip_prefix(dir.path()).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn globs() {
+        let cases = [
+            ("*.rs", "main.rs", true),
+            ("*.rs", "main.rc", false),
+            ("*.rs", "src/main.rs", false),
+            ("src/*.rs", "src/main.rs", true),
+            ("src/*.rs", "src/bin/main.rs", false),
+            ("src/**/*.rs", "src/main.rs", true),
+            ("src/**/*.rs", "src/bin/deep/main.rs", true),
+            ("**/target", "target", true),
+            ("**/target", "adder/target", true),
+            ("logs/**", "logs/a/b.txt", true),
+            ("?.txt", "a.txt", true),
+            ("?.txt", "ab.txt", false),
+            ("[abc].txt", "b.txt", true),
+            ("[!abc].txt", "b.txt", false),
+            ("[a-c].txt", "c.txt", true),
+            ("[a-c].txt", "d.txt", false),
+            ("\\*.txt", "*.txt", true),
+            ("\\*.txt", "a.txt", false),
+            ("Cargo.lock", "Cargo.lock", true),
+        ];
+        for (pattern, path, expected) in cases {
+            assert_eq!(
+                glob_match(pattern, path),
+                expected,
+                "{pattern:?} vs {path:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn rule_parsing() {
+        let base = Path::new("repo");
+        assert_eq!(Rule::parse("", base), None);
+        assert_eq!(Rule::parse("# a comment", base), None);
+
+        let rule = Rule::parse("!/build/", base).unwrap();
+        assert!(rule.negated && rule.dir_only && rule.anchored);
+        assert_eq!(rule.pattern, "build");
+
+        let rule = Rule::parse("*.log", base).unwrap();
+        assert!(!rule.negated && !rule.dir_only && !rule.anchored);
+    }
+
+    #[test]
+    fn ignore_rules() {
+        let mut ignore = Ignore::new();
+        ignore.add_rules(
+            "target/\n*.rlib\n/test_output.txt\n!keep.rlib\ndocs/*.md\n",
+            Path::new("repo"),
+        );
+
+        let cases = [
+            ("repo/target", true, true),
+            ("repo/adder/target", true, true),
+            ("repo/target", false, false),
+            ("repo/lib.rlib", false, true),
+            ("repo/keep.rlib", false, false),
+            ("repo/test_output.txt", false, true),
+            ("repo/adder/test_output.txt", false, false),
+            ("repo/docs/notes.md", false, true),
+            ("repo/docs/deep/notes.md", false, false),
+            ("elsewhere/lib.rlib", false, false),
+        ];
+        for (path, is_dir, expected) in cases {
+            assert_eq!(
+                ignore.is_ignored(Path::new(path), is_dir),
+                expected,
+                "{path}"
+            );
+        }
+    }
+
+    #[test]
+    fn walk_skips_target_and_ignored_files() {
+        let dir = TempDir::new("walk-skip");
+        dir.file("poem.txt", "to be");
+        dir.file("target/debug/out.txt", "to be");
+        dir.file("adder/target/debug/out.txt", "to be");
+        dir.file("adder/src/lib.rs", "to be");
+        dir.file(".git/HEAD", "to be");
+        dir.file(".gitignore", "*.log\n/scratch/\n");
+        dir.file("run.log", "to be");
+        dir.file("scratch/notes.txt", "to be");
+        dir.file("adder/scratch/notes.txt", "to be");
+        dir.file("adder/.gitignore", "!keep.log\n");
+        dir.file("adder/keep.log", "to be");
+        dir.file("adder/other.log", "to be");
+
+        let files = walk(dir.path()).unwrap();
+        assert_eq!(
+            relative(&dir, files),
+            vec![
+                ".gitignore",
+                "adder/.gitignore",
+                "adder/keep.log",
+                "adder/scratch/notes.txt",
+                "adder/src/lib.rs",
+                "poem.txt",
+            ]
+        );
+    }
+
+    #[test]
+    fn walk_of_a_file_returns_it() {
+        let files = walk(Path::new("poem.txt")).unwrap();
+        assert_eq!(files, vec![PathBuf::from("poem.txt")]);
+    }
+}
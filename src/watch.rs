@@ -0,0 +1,526 @@
+//Watching files and printing the matches that come and go
+/*
+'minigrep --watch PATTERN PATH' searches PATH like usual and then keeps
+going. Every half second it looks at the modification time and size of each
+file (every file below PATH when it's a directory, found the same way as a
+search does), and searches the ones that changed again. Instead of printing
+all their matches again, it only prints what's different:
+
+    +app.log:1041:ERROR connection refused
+    -app.log:17:ERROR disk full
+
+'+' is a line that matches now and didn't before, and '-' one that matched
+but has gone, with the line number it had. A line that only moved, because
+lines were added above it, isn't printed again. Lines are compared by their
+text, so when a line appears more often than before, the last ones are the
+new ones; that's what happens when a log is appended to.
+
+Logs mostly grow at the end, so a changed file isn't read from the start
+again. The watcher remembers where the last whole line it read ends, and
+reads on from there. A last line without its newline yet is read again once
+the rest of it arrives. The whole file is only read again when it has been
+replaced (on Unix, when it's a different file with the same name, as after
+a log rotation), when it got shorter, or when the bytes just before where
+the last read ended have changed, which means it was rewritten rather than
+appended to.
+
+There's no crate for file system notifications here, and no need for one:
+asking for the metadata of a few files twice a second is cheap. A file that
+is changed twice within the same instant and keeps its size would be missed,
+which file systems with nanosecond timestamps make very unlikely.
+
+Context lines (-A, -B, -C) aren't shown, since the lines around a match
+change all the time in a log.
+*/
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, Metadata};
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::lines::{self, Line, LineReader};
+use crate::output::{self, Found, OutputOptions};
+use crate::walk;
+
+/// How long to wait between looking for changes.
+pub const INTERVAL: Duration = Duration::from_millis(500);
+
+// how many bytes before the end of what was read are kept, to check that
+// they're still the same next time
+const TAIL_LEN: u64 = 64;
+
+// what a file looked like when it was last searched
+struct Snapshot {
+    modified: Option<SystemTime>,
+    len: u64,
+    identity: Option<(u64, u64)>,
+    // where the last whole line read ends, the number of the line after
+    // it, and the bytes just before `end`
+    end: u64,
+    next_line: usize,
+    tail: Vec<u8>,
+    // the selected lines, in order
+    lines: Vec<Line>,
+}
+
+/// Searches every file below a path and remembers the selected lines, so
+/// that the next `poll` can print what changed.
+pub struct Watcher<F> {
+    root: PathBuf,
+    find: F,
+    options: OutputOptions,
+    files: BTreeMap<PathBuf, Snapshot>,
+}
+
+impl<F: Fn(&str) -> Vec<Found>> Watcher<F> {
+    /// A watcher for `root`, a file or a directory. `find` returns the
+    /// matches in a line, as for `output::write_results`.
+    pub fn new(root: &Path, find: F, options: &OutputOptions) -> Watcher<F> {
+        let options = OutputOptions {
+            before_context: 0,
+            after_context: 0,
+            ..options.clone()
+        };
+
+        Watcher {
+            root: root.to_path_buf(),
+            find,
+            options,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Searches everything and prints all the selected lines, the way a
+    /// search without --watch does.
+    pub fn start<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        for path in self.paths()? {
+            if let Some((_, snapshot)) = self.search(&path, None)? {
+                for line in &snapshot.lines {
+                    self.write_line(out, None, &path, line)?;
+                }
+                self.files.insert(path, snapshot);
+            }
+        }
+        Ok(())
+    }
+
+    /// Searches the files that changed since the last look, prints the
+    /// lines that were selected or stopped being, and returns how many it
+    /// printed.
+    pub fn poll<W: Write>(&mut self, out: &mut W) -> io::Result<usize> {
+        let mut printed = 0;
+        let paths = self.paths()?;
+
+        for path in &paths {
+            let unchanged = match (self.files.get(path), fs::metadata(path)) {
+                (Some(old), Ok(meta)) => {
+                    old.modified == meta.modified().ok() && old.len == meta.len()
+                }
+                _ => false,
+            };
+            if unchanged {
+                continue;
+            }
+
+            let old = self.files.remove(path);
+            let new = self.search(path, old.as_ref())?;
+            let mut lines = old.map_or_else(Vec::new, |s| s.lines);
+            match new {
+                Some((from, mut new)) => {
+                    // the lines before `from` weren't read again, since
+                    // they haven't changed
+                    let kept = lines.iter().take_while(|l| l.offset < from).count();
+                    printed += self.write_changes(out, path, &lines[kept..], &new.lines)?;
+                    lines.truncate(kept);
+                    lines.append(&mut new.lines);
+                    new.lines = lines;
+                    self.files.insert(path.clone(), new);
+                }
+                None => printed += self.write_changes(out, path, &lines, &[])?,
+            }
+        }
+
+        // and the files that are gone
+        let removed: Vec<PathBuf> = self
+            .files
+            .keys()
+            .filter(|path| paths.binary_search(path).is_err())
+            .cloned()
+            .collect();
+        for path in removed {
+            if let Some(old) = self.files.remove(&path) {
+                printed += self.write_changes(out, &path, &old.lines, &[])?;
+            }
+        }
+
+        Ok(printed)
+    }
+
+    // every file to watch, sorted
+    fn paths(&self) -> io::Result<Vec<PathBuf>> {
+        let mut paths = if self.root.is_dir() {
+            walk::walk(&self.root)?
+        } else if self.root.exists() {
+            vec![self.root.clone()]
+        } else {
+            // a log that was rotated away may come back
+            Vec::new()
+        };
+        paths.sort();
+        Ok(paths)
+    }
+
+    // Searches one file, reading on from where `old` ended if the file was
+    // only appended to since. Returns the offset the search started at and
+    // the selected lines from there, or None if it's binary or has gone.
+    fn search(&self, path: &Path, old: Option<&Snapshot>) -> io::Result<Option<(u64, Snapshot)>> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let meta = file.metadata()?;
+
+        let (from, first_line) = match old {
+            Some(old) if appended_to(&mut file, &meta, old)? => (old.end, old.next_line),
+            _ => (0, 1),
+        };
+        file.seek(SeekFrom::Start(from))?;
+        let mut input = BufReader::new(file);
+        if from == 0 && lines::looks_binary(&mut input)? {
+            return Ok(None);
+        }
+
+        let mut selected = Vec::new();
+        let mut last = None;
+        let mut reader = LineReader::resume(input, first_line, from);
+        for line in reader.by_ref() {
+            let line = line?;
+            last = Some((line.number, line.offset));
+            if (self.find)(&line.text).is_empty() == self.options.invert {
+                selected.push(line);
+            }
+        }
+
+        // a last line without a newline may still be being written, so
+        // it's read again next time
+        let read_to = reader.offset();
+        let mut file = reader.into_inner().into_inner();
+        let (end, next_line) = match last {
+            Some((number, offset)) if !read_tail(&mut file, read_to)?.ends_with(b"\n") => {
+                (offset, number)
+            }
+            Some((number, _)) => (read_to, number + 1),
+            None => (from, first_line),
+        };
+
+        Ok(Some((
+            from,
+            Snapshot {
+                modified: meta.modified().ok(),
+                len: meta.len(),
+                identity: identity(&meta),
+                end,
+                next_line,
+                tail: read_tail(&mut file, end)?,
+                lines: selected,
+            },
+        )))
+    }
+
+    fn write_changes<W: Write>(
+        &self,
+        out: &mut W,
+        path: &Path,
+        old: &[Line],
+        new: &[Line],
+    ) -> io::Result<usize> {
+        let removed = extra_lines(old, new);
+        let added = extra_lines(new, old);
+        for line in &removed {
+            self.write_line(out, Some('-'), path, line)?;
+        }
+        for line in &added {
+            self.write_line(out, Some('+'), path, line)?;
+        }
+        Ok(removed.len() + added.len())
+    }
+
+    fn write_line<W: Write>(
+        &self,
+        out: &mut W,
+        sign: Option<char>,
+        path: &Path,
+        line: &Line,
+    ) -> io::Result<()> {
+        if let Some(sign) = sign {
+            write!(out, "{sign}")?;
+        }
+        let shown = path.display().to_string();
+        let shown = self.root.is_dir().then_some(shown.as_str());
+        let matches = if self.options.invert {
+            Vec::new()
+        } else {
+            (self.find)(&line.text)
+        };
+        output::write_line(out, shown, line, &matches, ':', &self.options)
+    }
+}
+
+/// Prints the selected lines in `root`, then watches it for changes every
+/// `interval` and prints the lines that are selected or stop being. Only
+/// returns if writing fails.
+pub fn watch<W: Write>(
+    root: &Path,
+    find: impl Fn(&str) -> Vec<Found>,
+    options: &OutputOptions,
+    interval: Duration,
+    out: &mut W,
+) -> io::Result<()> {
+    let mut watcher = Watcher::new(root, find, options);
+    watcher.start(out)?;
+    out.flush()?;
+    loop {
+        thread::sleep(interval);
+        if watcher.poll(out)? > 0 {
+            out.flush()?;
+        }
+    }
+}
+
+// Whether `file` is the one `old` was read from, with only more added at
+// the end since.
+fn appended_to(file: &mut File, meta: &Metadata, old: &Snapshot) -> io::Result<bool> {
+    Ok(identity(meta) == old.identity
+        && meta.len() >= old.end
+        && read_tail(file, old.end)? == old.tail)
+}
+
+// the up to TAIL_LEN bytes just before `end`
+fn read_tail(file: &mut File, end: u64) -> io::Result<Vec<u8>> {
+    let start = end.saturating_sub(TAIL_LEN);
+    file.seek(SeekFrom::Start(start))?;
+    let mut tail = Vec::new();
+    file.take(end - start).read_to_end(&mut tail)?;
+    Ok(tail)
+}
+
+// which file this is, to notice when another one takes its name
+#[cfg(unix)]
+fn identity(meta: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn identity(_meta: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+// The lines of `these` whose text is in `others` fewer times, taking the
+// last ones of each text.
+fn extra_lines<'a>(these: &'a [Line], others: &[Line]) -> Vec<&'a Line> {
+    let mut budget: HashMap<&str, isize> = HashMap::new();
+    for line in these {
+        *budget.entry(&line.text).or_default() += 1;
+    }
+    for line in others {
+        *budget.entry(&line.text).or_default() -= 1;
+    }
+
+    let mut extra: Vec<&Line> = these
+        .iter()
+        .rev()
+        .filter(|line| match budget.get_mut(line.text.as_str()) {
+            Some(left) if *left > 0 => {
+                *left -= 1;
+                true
+            }
+            _ => false,
+        })
+        .collect();
+    extra.reverse();
+    extra
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use std::fs::OpenOptions;
+
+    fn find(line: &str) -> Vec<Found> {
+        line.match_indices("ERROR")
+            .map(|(start, m)| Found::from((start, start + m.len())))
+            .collect()
+    }
+
+    fn output<F: Fn(&str) -> Vec<Found>>(
+        watcher: &mut Watcher<F>,
+        step: impl FnOnce(&mut Watcher<F>, &mut Vec<u8>) -> io::Result<()>,
+    ) -> String {
+        let mut out = Vec::new();
+        step(watcher, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn poll<F: Fn(&str) -> Vec<Found>>(watcher: &mut Watcher<F>) -> String {
+        output(watcher, |w, out| w.poll(out).map(|_| ()))
+    }
+
+    fn append(path: &Path, text: &str) {
+        let mut file = OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn prints_only_new_and_removed_matches() {
+        let dir = TempDir::new("watch-file");
+        let log = dir.join("app.log");
+        fs::write(&log, "INFO start\nERROR disk full\n").unwrap();
+        let options = OutputOptions {
+            line_numbers: true,
+            ..OutputOptions::default()
+        };
+
+        let mut watcher = Watcher::new(&log, find, &options);
+        assert_eq!(output(&mut watcher, Watcher::start), "2:ERROR disk full\n");
+        assert_eq!(poll(&mut watcher), "");
+
+        append(&log, "INFO retry\nERROR connection refused\n");
+        assert_eq!(poll(&mut watcher), "+4:ERROR connection refused\n");
+
+        // a line that only moves isn't new, but a second copy of it is
+        fs::write(
+            &log,
+            "INFO start\nINFO more\nERROR connection refused\nERROR connection refused\n",
+        )
+        .unwrap();
+        assert_eq!(
+            poll(&mut watcher),
+            "-2:ERROR disk full\n+4:ERROR connection refused\n"
+        );
+
+        // and a log that's rotated away comes back empty
+        fs::remove_file(&log).unwrap();
+        assert_eq!(
+            poll(&mut watcher),
+            "-3:ERROR connection refused\n-4:ERROR connection refused\n"
+        );
+        fs::write(&log, "ERROR again\n").unwrap();
+        assert_eq!(poll(&mut watcher), "+1:ERROR again\n");
+    }
+
+    #[test]
+    fn only_reads_what_was_appended() {
+        let dir = TempDir::new("watch-append");
+        let log = dir.join("app.log");
+        let padding = "INFO nothing to see\n".repeat(10);
+        fs::write(&log, format!("ERROR a\n{padding}")).unwrap();
+        let options = OutputOptions {
+            line_numbers: true,
+            ..OutputOptions::default()
+        };
+        let mut watcher = Watcher::new(&log, find, &options);
+        assert_eq!(output(&mut watcher, Watcher::start), "1:ERROR a\n");
+
+        // the first line changes too, but it's long before where the last
+        // read ended, so only the new line is read
+        fs::write(&log, format!("ERROR b\n{padding}ERROR c\n")).unwrap();
+        assert_eq!(poll(&mut watcher), "+12:ERROR c\n");
+
+        // a line still being written is read again when it's finished
+        append(&log, "ERROR hal");
+        assert_eq!(poll(&mut watcher), "+13:ERROR hal\n");
+        append(&log, "f done\nERROR d\n");
+        assert_eq!(
+            poll(&mut watcher),
+            "-13:ERROR hal\n+13:ERROR half done\n+14:ERROR d\n"
+        );
+
+        // a shorter file is read again from the start
+        fs::write(&log, "ERROR b\n").unwrap();
+        assert_eq!(
+            poll(&mut watcher),
+            "-1:ERROR a\n-12:ERROR c\n-13:ERROR half done\n-14:ERROR d\n+1:ERROR b\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_replaced_file_is_read_again() {
+        let dir = TempDir::new("watch-replace");
+        let log = dir.join("app.log");
+        let padding = "INFO nothing to see\n".repeat(10);
+        fs::write(&log, format!("ERROR old\n{padding}")).unwrap();
+        let mut watcher = Watcher::new(&log, find, &OutputOptions::default());
+        assert_eq!(output(&mut watcher, Watcher::start), "ERROR old\n");
+
+        // a new file moved into place that only differs at the start
+        let new = dir.join("app.log.new");
+        fs::write(&new, format!("ERROR new\n{padding}ERROR more\n")).unwrap();
+        fs::rename(&new, &log).unwrap();
+        assert_eq!(poll(&mut watcher), "-ERROR old\n+ERROR new\n+ERROR more\n");
+    }
+
+    #[test]
+    fn watches_every_file_in_a_directory() {
+        let dir = TempDir::new("watch-dir");
+        fs::write(dir.join("a.log"), "ERROR one\n").unwrap();
+        let options = OutputOptions {
+            line_numbers: true,
+            ..OutputOptions::default()
+        };
+        let mut watcher = Watcher::new(dir.path(), find, &options);
+        let a = dir.join("a.log").display().to_string();
+        let b = dir.join("b.log").display().to_string();
+
+        assert_eq!(
+            output(&mut watcher, Watcher::start),
+            format!("{a}:1:ERROR one\n")
+        );
+        fs::write(dir.join("b.log"), "fine\nERROR two\n").unwrap();
+        assert_eq!(poll(&mut watcher), format!("+{b}:2:ERROR two\n"));
+        fs::remove_file(dir.join("a.log")).unwrap();
+        assert_eq!(poll(&mut watcher), format!("-{a}:1:ERROR one\n"));
+    }
+
+    #[test]
+    fn inverted_watch_follows_the_other_lines() {
+        let dir = TempDir::new("watch-invert");
+        let log = dir.join("app.log");
+        fs::write(&log, "ERROR x\n").unwrap();
+        let options = OutputOptions {
+            invert: true,
+            ..OutputOptions::default()
+        };
+
+        let mut watcher = Watcher::new(&log, find, &options);
+        assert_eq!(output(&mut watcher, Watcher::start), "");
+        append(&log, "all good\nERROR y\n");
+        assert_eq!(poll(&mut watcher), "+all good\n");
+    }
+
+    #[test]
+    fn extra_lines_takes_the_last_copies() {
+        let lines = |texts: &[&str]| -> Vec<Line> {
+            texts
+                .iter()
+                .enumerate()
+                .map(|(i, text)| Line {
+                    number: i + 1,
+                    offset: 0,
+                    text: text.to_string(),
+                })
+                .collect()
+        };
+        let old = lines(&["a", "b", "a"]);
+        let new = lines(&["a", "a", "c", "a"]);
+        let numbers = |extra: Vec<&Line>| extra.iter().map(|l| l.number).collect::<Vec<_>>();
+
+        assert_eq!(numbers(extra_lines(&new, &old)), vec![3, 4]);
+        assert_eq!(numbers(extra_lines(&old, &new)), vec![2]);
+    }
+}